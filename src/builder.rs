@@ -0,0 +1,102 @@
+//! Generation from build scripts
+//!
+//! [`Builder`] collects SVD files and a [`Config`] and returns everything `svd2rust` generates as
+//! a [`Generation`], without writing any files. [`Generation::write_out_dir`] then puts the code
+//! where a crate can `include!` it.
+
+use std::path::PathBuf;
+
+use anyhow::{Context, Result};
+
+use crate::config::SourceType;
+use crate::{Config, Generation};
+
+/// Builder of a [`Generation`] for one device or a family of devices
+///
+/// ```no_run
+/// // build.rs
+/// fn main() -> anyhow::Result<()> {
+///     println!("cargo:rerun-if-changed=device.svd");
+///     svd2rust::Builder::new()
+///         .file("device.svd")
+///         .configure(|config| config.target = svd2rust::Target::CortexM)
+///         .generate()?
+///         .write_out_dir()?;
+///     Ok(())
+/// }
+/// ```
+///
+/// The crate then includes the code at its root:
+///
+/// ```ignore
+/// #![no_std]
+/// #![allow(non_camel_case_types, non_snake_case)]
+/// include!(concat!(env!("OUT_DIR"), "/lib.rs"));
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct Builder {
+    config: Config,
+    inputs: Vec<Input>,
+}
+
+#[derive(Clone, Debug)]
+enum Input {
+    Source(String),
+    File(PathBuf),
+}
+
+impl Builder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Replaces the whole configuration
+    pub fn config(mut self, config: Config) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Changes the configuration
+    pub fn configure(mut self, f: impl FnOnce(&mut Config)) -> Self {
+        f(&mut self.config);
+        self
+    }
+
+    /// Adds a device description in the `source_type` format of the configuration
+    pub fn source(mut self, input: impl Into<String>) -> Self {
+        self.inputs.push(Input::Source(input.into()));
+        self
+    }
+
+    /// Adds a device description file, its format is guessed from the extension
+    pub fn file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.inputs.push(Input::File(path.into()));
+        self
+    }
+
+    /// Generates the code, several devices are rendered as a family
+    ///
+    /// Only reads the inputs and the settings and patch files of the configuration.
+    pub fn generate(&self) -> Result<Generation> {
+        if self.inputs.is_empty() {
+            anyhow::bail!("No device description given");
+        }
+        let mut config = crate::prepare_config(&self.config)?;
+        let mut devices = Vec::with_capacity(self.inputs.len());
+        for input in &self.inputs {
+            let device = match input {
+                Input::Source(input) => crate::load_from(input, &config)?,
+                Input::File(path) => {
+                    let input = std::fs::read_to_string(path)
+                        .with_context(|| format!("Cannot read the SVD file {}", path.display()))?;
+                    config.source_type = SourceType::from_path(path);
+                    let device = crate::load_from(&input, &config);
+                    config.source_type = self.config.source_type;
+                    device?
+                }
+            };
+            devices.push(device);
+        }
+        crate::render(&devices, &config)
+    }
+}
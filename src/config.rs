@@ -1,5 +1,6 @@
 use anyhow::{bail, Result};
 use proc_macro2::{Span, TokenStream};
+use regex::Regex;
 use std::{
     collections::HashMap,
     ops::{Deref, DerefMut},
@@ -8,6 +9,8 @@ use std::{
 };
 use syn::{punctuated::Punctuated, Ident};
 
+use crate::generate::filter::Pattern;
+use crate::hooks::SharedHooks;
 use crate::util::path_segment;
 
 #[cfg_attr(feature = "serde", derive(serde::Deserialize), serde(default))]
@@ -20,6 +23,22 @@ pub struct Config {
     pub atomics_feature: Option<String>,
     pub generic_mod: bool,
     pub make_mod: bool,
+    /// Generate a complete crate with `Cargo.toml` and `src` directory
+    pub make_crate: bool,
+    /// Write modules of the generated code into separate files
+    pub split: bool,
+    /// Size in bytes above which nested modules get their own file when splitting
+    pub split_threshold: Option<usize>,
+    /// Write a JSON map of all registers with their addresses next to the generated code
+    pub register_map: bool,
+    /// Write a GDB target description and memory map of all registers next to the generated code
+    pub gdb_xml: bool,
+    /// Write a CMSIS style C header with the names and layout of the generated code
+    pub c_header: bool,
+    /// Write a static HTML reference of the registers, which `html_url` links can point to
+    pub html_reference: bool,
+    /// Pretty-print the generated code
+    pub format: bool,
     pub skip_crate_attributes: bool,
     pub skip_peripherals_struct: bool,
     pub ignore_groups: bool,
@@ -28,11 +47,31 @@ pub struct Config {
     pub feature_group: bool,
     pub feature_peripheral: bool,
     pub max_cluster_size: bool,
+    /// Access registers through raw pointers instead of references
+    pub pointer_access: bool,
+    /// Generate an `Instance` trait for each family of peripherals sharing a register block
+    pub instance_traits: bool,
+    /// Render structurally identical peripherals and clusters as derived from the first one
+    pub derive_identical: bool,
+    /// Render identical enumerated value sets as derived from the first one
+    pub derive_identical_enums: Option<DeriveScope>,
     pub impl_debug: bool,
     pub impl_debug_feature: Option<String>,
     pub impl_defmt: Option<String>,
+    /// Add tables of the bits and enumerated values of the fields to the docs of each register
+    pub bit_layout_docs: bool,
+    /// Feature which switches registers to the simulated register file
+    pub mock: Option<String>,
+    /// Feature which enables register access tracing
+    pub trace: Option<String>,
     pub output_dir: Option<PathBuf>,
     pub input: Option<PathBuf>,
+    /// Input files of devices generated into one crate with a feature for each chip
+    pub family: Vec<PathBuf>,
+    /// Paths of peripherals, clusters and registers to generate, all when empty
+    pub include: Vec<String>,
+    /// Paths of peripherals, clusters and registers to leave out
+    pub exclude: Vec<String>,
     pub source_type: SourceType,
     pub log_level: Option<String>,
     pub interrupt_link_section: Option<String>,
@@ -42,12 +81,23 @@ pub struct Config {
     pub add_cortex_m_int_num: bool,
     pub ident_formats: IdentFormats,
     pub ident_formats_theme: Option<IdentFormatsTheme>,
+    /// Regex rewrites of SVD names for each identifier type, applied before `ident_formats`
+    pub ident_renames: HashMap<String, Vec<IdentRename>>,
+    /// Number colliding identifiers instead of failing
+    pub disambiguate_idents: bool,
     pub field_names_for_enums: bool,
     pub base_address_shift: u64,
     /// Path to YAML file with chip-specific settings
     pub settings_file: Option<PathBuf>,
     /// Chip-specific settings
     pub settings: Settings,
+    /// Path to YAML file with changes to the device description
+    pub patch_file: Option<PathBuf>,
+    /// Code added next to the elements matching their path patterns
+    pub extra_items: Vec<ExtraItem>,
+    /// Callbacks adding code to the generated items
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub hooks: Option<SharedHooks>,
 }
 
 impl Config {
@@ -122,6 +172,20 @@ impl Target {
     }
 }
 
+/// Where identical items are looked for
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Deserialize),
+    serde(rename_all = "lowercase")
+)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum DeriveScope {
+    /// Inside of the same peripheral
+    Peripheral,
+    /// In the whole crate
+    Crate,
+}
+
 #[cfg_attr(
     feature = "serde",
     derive(serde::Deserialize),
@@ -198,6 +262,9 @@ pub struct IdentFormat {
     pub case: Option<Case>,
     pub prefix: String,
     pub suffix: String,
+    /// Rewrites applied to the name before the case
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub rename: Vec<IdentRename>,
 }
 
 impl IdentFormat {
@@ -234,6 +301,7 @@ impl IdentFormat {
                     case,
                     prefix: p.into(),
                     suffix: s.into(),
+                    rename: Vec::new(),
                 })
             }
             (Some(p), Some(c), None) => {
@@ -242,6 +310,7 @@ impl IdentFormat {
                     case,
                     prefix: p.into(),
                     suffix: "".into(),
+                    rename: Vec::new(),
                 })
             }
             (Some(c), None, None) => {
@@ -250,6 +319,7 @@ impl IdentFormat {
                     case,
                     prefix: "".into(),
                     suffix: "".into(),
+                    rename: Vec::new(),
                 })
             }
             _ => Err(IdentFormatError::Other),
@@ -323,6 +393,127 @@ impl IdentFormats {
 
         map
     }
+
+    /// Adds the `ident_renames` rewrites to the formats of their identifier types
+    pub fn add_renames(&mut self, renames: &HashMap<String, Vec<IdentRename>>) -> Result<()> {
+        for (name, rename) in renames {
+            let Some(format) = self.get_mut(name) else {
+                bail!("Ident format name `{name}` is unknown");
+            };
+            format.rename.extend(rename.iter().cloned());
+        }
+        Ok(())
+    }
+}
+
+/// Regex rewrite of SVD names, `to` may refer to groups of `from` like `$1`
+#[derive(Clone, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Deserialize),
+    serde(try_from = "IdentRenameDef")
+)]
+pub struct IdentRename {
+    pub from: Regex,
+    pub to: String,
+}
+
+impl IdentRename {
+    pub fn new(from: &str, to: &str) -> Result<Self, regex::Error> {
+        Ok(Self {
+            from: Regex::new(from)?,
+            to: to.into(),
+        })
+    }
+}
+
+impl PartialEq for IdentRename {
+    fn eq(&self, other: &Self) -> bool {
+        self.from.as_str() == other.from.as_str() && self.to == other.to
+    }
+}
+
+impl Eq for IdentRename {}
+
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+struct IdentRenameDef {
+    from: String,
+    to: String,
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<IdentRenameDef> for IdentRename {
+    type Error = regex::Error;
+    fn try_from(def: IdentRenameDef) -> Result<Self, Self::Error> {
+        Self::new(&def.from, &def.to)
+    }
+}
+
+/// Code added to the generated items of one kind whose path matches `path`
+///
+/// `code` may contain `{name}`, `{path}` and `{ident}` placeholders, which are replaced with
+/// the SVD name, the SVD path and the identifier of the element.
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Deserialize),
+    serde(try_from = "ExtraItemDef")
+)]
+#[derive(Clone, Debug)]
+pub struct ExtraItem {
+    pub on: ExtraItemKind,
+    /// Pattern of the path like the ones of `include`
+    pub path: String,
+    pub code: String,
+    pub(crate) pattern: Pattern,
+}
+
+impl ExtraItem {
+    pub fn new(on: ExtraItemKind, path: &str, code: &str) -> Result<Self> {
+        Ok(Self {
+            on,
+            path: path.into(),
+            code: code.into(),
+            pattern: Pattern::new(path)?,
+        })
+    }
+}
+
+impl PartialEq for ExtraItem {
+    fn eq(&self, other: &Self) -> bool {
+        self.on == other.on && self.path == other.path && self.code == other.code
+    }
+}
+
+impl Eq for ExtraItem {}
+
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+struct ExtraItemDef {
+    on: ExtraItemKind,
+    path: String,
+    code: String,
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<ExtraItemDef> for ExtraItem {
+    type Error = anyhow::Error;
+    fn try_from(def: ExtraItemDef) -> Result<Self> {
+        Self::new(def.on, &def.path, &def.code)
+    }
+}
+
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExtraItemKind {
+    Peripheral,
+    RegisterBlock,
+    RegisterMod,
+    Field,
 }
 
 impl Deref for IdentFormats {
@@ -352,7 +543,7 @@ pub enum IdentFormatsTheme {
 #[non_exhaustive]
 /// Chip-specific settings
 pub struct Settings {
-    /// Path to chip HTML generated by svdtools
+    /// URL of the chip HTML generated by svdtools or `--html-reference`
     pub html_url: Option<url::Url>,
     pub crate_path: Option<CratePath>,
     /// RISC-V specific settings
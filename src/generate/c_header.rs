@@ -0,0 +1,356 @@
+//! CMSIS style C header with the layout and names of the generated code
+//!
+//! Register blocks become structs laid out by the same code as the `RegisterBlock`s, and all
+//! identifiers are the ones of the Rust API joined by `_`: the register block of `usart1` is
+//! `usart1_RegisterBlock`, the `txe` field of `usart1::sr` gets `usart1_sr_txe_Pos` and
+//! `usart1_sr_txe_Msk`.
+
+use std::collections::HashSet;
+use std::fmt::Write;
+
+use anyhow::{Context, Result};
+use proc_macro2::Span;
+use svd_parser::expand::{derive_peripheral, Index};
+
+use crate::config::Config;
+use crate::generate::filter;
+use crate::generate::peripheral::{self, LayoutKind, LayoutMember};
+use crate::ir;
+use crate::svd::{self, Access, Device, Peripheral};
+use crate::util::{self, ident};
+
+/// Header of `device`
+pub fn render(device: &Device, config: &Config) -> Result<String> {
+    let filtered;
+    let device = if filter::is_active(config) {
+        filtered = filter::filter(device, config)?;
+        &filtered
+    } else {
+        device
+    };
+    let derived;
+    let device = if config.derive_identical {
+        derived = peripheral::derive_identical(device, config)?;
+        &derived
+    } else {
+        device
+    };
+    let index = Index::create(device);
+    let span = Span::call_site();
+
+    let guard = format!("{}_H", device.name.to_uppercase());
+    let mut out = String::new();
+    writeln!(
+        out,
+        "/* Peripheral access header for {}, generated using svd2rust v{} */",
+        device.name,
+        env!("CARGO_PKG_VERSION")
+    )?;
+    writeln!(out)?;
+    writeln!(out, "#ifndef {guard}")?;
+    writeln!(out, "#define {guard}")?;
+    writeln!(out)?;
+    writeln!(out, "#include <stdint.h>")?;
+    writeln!(out)?;
+    for (qualifier, definition) in [
+        ("__IM", "volatile const"),
+        ("__OM", "volatile"),
+        ("__IOM", "volatile"),
+    ] {
+        writeln!(out, "#ifndef {qualifier}")?;
+        writeln!(out, "#define {qualifier} {definition}")?;
+        writeln!(out, "#endif")?;
+    }
+    writeln!(out)?;
+    writeln!(out, "#ifdef __cplusplus")?;
+    writeln!(out, "extern \"C\" {{")?;
+    writeln!(out, "#endif")?;
+
+    let mut interrupts: Vec<&svd::Interrupt> = Vec::new();
+    for i in device.peripherals.iter().flat_map(|p| &p.interrupt) {
+        if !interrupts.iter().any(|other| other.name == i.name) {
+            interrupts.push(i);
+        }
+    }
+    interrupts.sort_by_key(|i| i.value);
+    if !interrupts.is_empty() {
+        writeln!(out)?;
+        writeln!(out, "typedef enum {{")?;
+        for i in interrupts {
+            let name = ident(&i.name, config, "interrupt", span);
+            writeln!(out, "  {name}_IRQn = {},", i.value)?;
+        }
+        writeln!(out, "}} IRQn_Type;")?;
+    }
+
+    for p in &device.peripherals {
+        let module = ident(
+            &util::name_of(p, config.ignore_groups),
+            config,
+            "peripheral_mod",
+            span,
+        );
+        let mut base = p.clone();
+        let dpath = match base.derived_from.take() {
+            Some(dpath) => derive_peripheral(&mut base, &dpath, &index)?,
+            None => None,
+        };
+        let block = match dpath {
+            Some(dpath) => {
+                let base = ident(&dpath.peripheral, config, "peripheral_mod", span);
+                format!("{base}_RegisterBlock")
+            }
+            None if base.registers.as_ref().map_or(true, Vec::is_empty) => continue,
+            None => {
+                let members = peripheral::layout(p, &index, config)
+                    .with_context(|| format!("Error laying out peripheral {}", p.name))?;
+                let block = format!("{module}_RegisterBlock");
+                writeln!(out)?;
+                writeln!(
+                    out,
+                    "/* {} */",
+                    comment(p.description.as_deref().unwrap_or(&p.name))
+                )?;
+                let mut macros = String::new();
+                let mut emitted = HashSet::new();
+                render_block(
+                    &members,
+                    &block,
+                    None,
+                    &[module.to_string()],
+                    &mut out,
+                    &mut macros,
+                    &mut emitted,
+                    config,
+                )?;
+                out.push_str(&macros);
+                block
+            }
+        };
+
+        writeln!(out)?;
+        let instances: Vec<_> = match p {
+            Peripheral::Single(info) => vec![(info.name.clone(), info.base_address)],
+            Peripheral::Array(info, dim) => svd::peripheral::expand(info, dim)
+                .map(|pi| (pi.name, pi.base_address))
+                .collect(),
+        };
+        for (name, address) in instances {
+            let p_ty = ident(&name, config, "peripheral", span);
+            let address = address + config.base_address_shift;
+            writeln!(out, "#define {p_ty}_ADDRESS {address:#x}UL")?;
+            writeln!(out, "#define {p_ty} (({block} *){p_ty}_ADDRESS)")?;
+        }
+    }
+
+    writeln!(out)?;
+    writeln!(out, "#ifdef __cplusplus")?;
+    writeln!(out, "}}")?;
+    writeln!(out, "#endif")?;
+    writeln!(out)?;
+    writeln!(out, "#endif /* {guard} */")?;
+    Ok(out)
+}
+
+/// Writes the struct of a register block after the structs of its clusters, and the field
+/// macros of its registers to `macros`
+///
+/// `emitted` holds the names of the structs and register modules already written.
+#[allow(clippy::too_many_arguments)]
+fn render_block(
+    members: &[LayoutMember],
+    name: &str,
+    size: Option<u32>,
+    prefix: &[String],
+    out: &mut String,
+    macros: &mut String,
+    emitted: &mut HashSet<String>,
+    config: &Config,
+) -> Result<()> {
+    let mut types = Vec::with_capacity(members.len());
+    for m in members {
+        match &m.kind {
+            LayoutKind::Register { module, elements } => {
+                let mut path = prefix.to_vec();
+                path.push(module.clone());
+                if emitted.insert(path.join("_")) {
+                    field_macros(&elements[0], &path, macros, config)?;
+                }
+                types.push(None);
+            }
+            LayoutKind::Cluster {
+                module,
+                ty,
+                size,
+                members,
+                ..
+            } => {
+                let ty = format!("{}_{ty}", prefix.join("_"));
+                // elements of a cluster list all have the type
+                if emitted.insert(ty.clone()) {
+                    // pad the elements of arrays to the increment so they can be indexed
+                    let size = m.array.map_or(*size, |(_, increment)| increment);
+                    let mut path = prefix.to_vec();
+                    path.push(module.clone());
+                    render_block(
+                        members,
+                        &ty,
+                        Some(size),
+                        &path,
+                        out,
+                        macros,
+                        emitted,
+                        config,
+                    )?;
+                }
+                types.push(Some(ty));
+            }
+        }
+    }
+
+    writeln!(out)?;
+    writeln!(out, "typedef struct {{")?;
+    let mut end = 0;
+    let mut reserved = 0;
+    let mut i = 0;
+    while i < members.len() {
+        // members sharing addresses go to a union
+        let start = members[i].offset;
+        let mut group_end = start + member_size(&members[i]);
+        let mut j = i + 1;
+        while j < members.len() && members[j].offset < group_end {
+            group_end = group_end.max(members[j].offset + member_size(&members[j]));
+            j += 1;
+        }
+        if start > end {
+            writeln!(out, "  uint8_t _reserved{reserved}[{}];", start - end)?;
+            reserved += 1;
+        }
+        if j == i + 1 {
+            writeln!(out, "  {}", member(&members[i], types[i].as_deref()))?;
+        } else {
+            writeln!(out, "  union {{")?;
+            for (m, ty) in members[i..j].iter().zip(&types[i..j]) {
+                if m.offset == start {
+                    writeln!(out, "    {}", member(m, ty.as_deref()))?;
+                } else {
+                    writeln!(out, "    struct {{")?;
+                    writeln!(
+                        out,
+                        "      uint8_t _reserved{reserved}[{}];",
+                        m.offset - start
+                    )?;
+                    writeln!(out, "      {}", member(m, ty.as_deref()))?;
+                    writeln!(out, "    }};")?;
+                    reserved += 1;
+                }
+            }
+            writeln!(out, "  }};")?;
+        }
+        end = end.max(group_end);
+        i = j;
+    }
+    if let Some(size) = size.filter(|size| *size > end) {
+        writeln!(out, "  uint8_t _reserved{reserved}[{}];", size - end)?;
+    }
+    writeln!(out, "}} {name};")?;
+    Ok(())
+}
+
+/// Size in bytes of the member, elements of arrays are padded to the increment
+fn member_size(m: &LayoutMember) -> u32 {
+    match (&m.kind, m.array) {
+        (_, Some((dim, increment))) => dim * increment,
+        (LayoutKind::Register { elements, .. }, None) => register_bytes(&elements[0]),
+        (LayoutKind::Cluster { size, .. }, None) => *size,
+    }
+}
+
+fn register_bytes(info: &svd::RegisterInfo) -> u32 {
+    info.properties
+        .size
+        .unwrap_or(32)
+        .div_ceil(8)
+        .next_power_of_two()
+}
+
+/// Declaration of a struct member with the type `ty` of a cluster
+fn member(m: &LayoutMember, ty: Option<&str>) -> String {
+    let (decl, description) = match (&m.kind, ty) {
+        (LayoutKind::Register { elements, .. }, _) => {
+            let info = &elements[0];
+            let qualifier = match util::access_of(&info.properties, info.fields.as_deref()) {
+                Access::ReadOnly => "__IM ",
+                Access::WriteOnly | Access::WriteOnce => "__OM ",
+                _ => "__IOM",
+            };
+            let bytes = register_bytes(info);
+            let ty = format!("uint{}_t", bytes * 8);
+            let decl = match m.array {
+                Some((dim, increment)) if increment != bytes => format!(
+                    "struct {{ {qualifier} {ty} value; uint8_t _reserved[{}]; }} {}[{dim}];",
+                    increment - bytes,
+                    m.ident
+                ),
+                Some((dim, _)) => format!("{qualifier} {ty} {}[{dim}];", m.ident),
+                None => format!("{qualifier} {ty} {};", m.ident),
+            };
+            (decl, info.description.as_deref().unwrap_or(&info.name))
+        }
+        (
+            LayoutKind::Cluster {
+                names, description, ..
+            },
+            Some(ty),
+        ) => {
+            let decl = match m.array {
+                Some((dim, _)) => format!("{ty} {}[{dim}];", m.ident),
+                None => format!("{ty} {};", m.ident),
+            };
+            (decl, description.as_deref().unwrap_or(&names[0]))
+        }
+        (LayoutKind::Cluster { .. }, None) => unreachable!(),
+    };
+    format!(
+        "{decl} /*!< (@ {:#010x}) {} */",
+        m.offset,
+        comment(description)
+    )
+}
+
+/// `_Pos` and `_Msk` macros of the fields of a register and the values of their enumerations
+fn field_macros(
+    info: &svd::RegisterInfo,
+    path: &[String],
+    out: &mut String,
+    config: &Config,
+) -> Result<()> {
+    let rust_path = path.join("::");
+    let module = path.join("_");
+    let register = ir::register(info, &rust_path, 0, config)?;
+    if register.fields.is_empty() {
+        return Ok(());
+    }
+    let suffix = if register.size > 32 { "ULL" } else { "UL" };
+    writeln!(out)?;
+    writeln!(out, "/* {rust_path} */")?;
+    let mut values = HashSet::new();
+    for f in &register.fields {
+        let name = format!("{module}_{}", f.ident);
+        let mask = u64::MAX >> (64 - f.bit_width);
+        writeln!(out, "#define {name}_Pos {}U", f.bit_offset)?;
+        writeln!(out, "#define {name}_Msk ({mask:#x}{suffix} << {name}_Pos)")?;
+        for v in f.enums.iter().flat_map(|e| &e.values) {
+            if let Some(value) = v.value {
+                if values.insert((f.ident.as_str(), v.ident.as_str())) {
+                    writeln!(out, "#define {name}_{} {value:#x}{suffix}", v.ident)?;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+fn comment(s: &str) -> String {
+    util::respace(s).replace("*/", "* /")
+}
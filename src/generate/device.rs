@@ -1,20 +1,44 @@
 use crate::svd::{array::names, Device, Peripheral};
-use proc_macro2::{Span, TokenStream};
+use proc_macro2::{Ident, Span, TokenStream};
 use quote::{quote, ToTokens};
 
 use log::{debug, warn};
-use std::fs::File;
-use std::io::Write;
-use std::path::Path;
+use std::fmt::Write;
 
 use crate::config::{Config, RustEdition, Target};
 use crate::util::{self, ident};
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
 
-use crate::generate::{avr, interrupt, peripheral, riscv};
+use crate::generate::{avr, filter, idents, interrupt, peripheral, register, riscv};
 
 /// Whole device generation
 pub fn render(d: &Device, config: &Config, device_x: &mut String) -> Result<TokenStream> {
+    if config.mock.is_some() && config.target == Target::Avr {
+        bail!("Mock register backend does not support AVR configuration change protection");
+    }
+    if config.mock.is_some() && config.pointer_access {
+        bail!("Mock register backend can't be used with pointer access");
+    }
+
+    let filtered;
+    let d = if filter::is_active(config) {
+        filtered = filter::filter(d, config)?;
+        &filtered
+    } else {
+        d
+    };
+
+    idents::check_renames(d, config)?;
+    idents::check_collisions(d, config)?;
+
+    let derived;
+    let d = if config.derive_identical {
+        derived = peripheral::derive_identical(d, config)?;
+        &derived
+    } else {
+        d
+    };
+
     let index = svd_parser::expand::Index::create(d);
     let mut out = TokenStream::new();
 
@@ -111,55 +135,34 @@ pub fn render(d: &Device, config: &Config, device_x: &mut String) -> Result<Toke
             }
         }
 
-        Target::Msp430 => {
+        Target::Msp430 if config.reexport_interrupt => {
             // XXX: Are there any core peripherals, really? Requires bump of msp430 crate.
             // pub use msp430::peripheral::Peripherals as CorePeripherals;
-            if config.reexport_interrupt {
-                out.extend(quote! {
-                    #[cfg(feature = "rt")]
-                    pub use msp430_rt::interrupt;
-                    #[cfg(feature = "rt")]
-                    pub use self::Interrupt as interrupt;
-                });
-            }
+            out.extend(quote! {
+                #[cfg(feature = "rt")]
+                pub use msp430_rt::interrupt;
+                #[cfg(feature = "rt")]
+                pub use self::Interrupt as interrupt;
+            });
         }
 
-        Target::Mips => {
-            if config.reexport_interrupt {
-                out.extend(quote! {
-                    #[cfg(feature = "rt")]
-                    pub use mips_rt::interrupt;
-                });
-            }
+        Target::Mips if config.reexport_interrupt => {
+            out.extend(quote! {
+                #[cfg(feature = "rt")]
+                pub use mips_rt::interrupt;
+            });
         }
 
         _ => {}
     }
 
     let generic_file = include_str!("generic.rs");
-    let generic_reg_file = include_str!("generic_reg_vcell.rs");
+    let generic_trace_file = generic_trace_file(config);
+    let generic_reg_file = generic_reg_file(config);
     let generic_atomic_file = include_str!("generic_atomic.rs");
     let avr_ccp_file = include_str!("generic_avr_ccp.rs");
+    let atomics_cfg = atomics_cfg(config);
     if config.generic_mod {
-        let mut file = File::create(
-            config
-                .output_dir
-                .as_deref()
-                .unwrap_or(Path::new("."))
-                .join("generic.rs"),
-        )?;
-        writeln!(file, "{generic_file}")?;
-        writeln!(file, "{generic_reg_file}")?;
-        if config.atomics {
-            if let Some(atomics_feature) = config.atomics_feature.as_ref() {
-                writeln!(file, "#[cfg(feature = \"{atomics_feature}\")]")?;
-            }
-            writeln!(file, "\n{generic_atomic_file}")?;
-        }
-        if config.target == Target::Avr {
-            writeln!(file, "\n{}", avr_ccp_file)?;
-        }
-
         if !config.make_mod {
             out.extend(quote! {
                 #[allow(unused_imports)]
@@ -170,10 +173,12 @@ pub fn render(d: &Device, config: &Config, device_x: &mut String) -> Result<Toke
         }
     } else {
         let mut tokens = syn::parse_file(generic_file)?.into_token_stream();
-        syn::parse_file(generic_reg_file)?.to_tokens(&mut tokens);
+        syn::parse_file(&generic_trace_file)?.to_tokens(&mut tokens);
+        syn::parse_file(&generic_reg_file)?.to_tokens(&mut tokens);
         if config.atomics {
-            if let Some(atomics_feature) = config.atomics_feature.as_ref() {
-                quote!(#[cfg(feature = #atomics_feature)]).to_tokens(&mut tokens);
+            if let Some(atomics_cfg) = atomics_cfg.as_ref() {
+                let atomics_cfg = syn::parse_str::<TokenStream>(atomics_cfg)?;
+                quote!(#[cfg(#atomics_cfg)]).to_tokens(&mut tokens);
             }
             syn::parse_file(generic_atomic_file)?.to_tokens(&mut tokens);
         }
@@ -218,7 +223,10 @@ pub fn render(d: &Device, config: &Config, device_x: &mut String) -> Result<Toke
         }
     }
 
+    let instances = peripheral::instances(&d.peripherals, &index, config)?;
+    let mut enum_sets = register::EnumSets::default();
     let feature_format = config.ident_formats.get("peripheral_feature").unwrap();
+    let mut trace_checks = TokenStream::new();
     for p in &d.peripherals {
         if config.target == Target::CortexM
             && core_peripherals.contains(&p.name.to_uppercase().as_ref())
@@ -228,15 +236,16 @@ pub fn render(d: &Device, config: &Config, device_x: &mut String) -> Result<Toke
         }
         if config.target != Target::RISCV || !riscv::is_riscv_peripheral(p, &config.settings) {
             debug!("Rendering peripheral {}", p.name);
-            let periph = peripheral::render(p, &index, config).with_context(|| {
-                let group_name = p.group_name.as_deref().unwrap_or("No group name");
-                let mut context_string =
-                    format!("can't render peripheral '{}', group '{group_name}'", p.name);
-                if let Some(dname) = p.derived_from.as_ref() {
-                    context_string += &format!(", derived from: '{dname}'");
-                }
-                context_string
-            })?;
+            let periph = peripheral::render(p, &index, &instances, &mut enum_sets, config)
+                .with_context(|| {
+                    let group_name = p.group_name.as_deref().unwrap_or("No group name");
+                    let mut context_string =
+                        format!("can't render peripheral '{}', group '{group_name}'", p.name);
+                    if let Some(dname) = p.derived_from.as_ref() {
+                        context_string += &format!(", derived from: '{dname}'");
+                    }
+                    context_string
+                })?;
 
             out.extend(periph);
 
@@ -278,6 +287,9 @@ pub fn render(d: &Device, config: &Config, device_x: &mut String) -> Result<Toke
                 } else {
                     quote!(#feature_attribute #p_singleton: #p_ty::steal(),)
                 });
+                if config.trace.is_some() {
+                    trace_checks.extend(trace_check(&p_ty, &feature_attribute, config));
+                }
             }
             Peripheral::Array(p, dim_element) => {
                 for p_name in names(p, dim_element) {
@@ -297,11 +309,25 @@ pub fn render(d: &Device, config: &Config, device_x: &mut String) -> Result<Toke
                     } else {
                         quote!(#feature_attribute #p_singleton: #p_ty::steal(),)
                     });
+                    if config.trace.is_some() {
+                        trace_checks.extend(trace_check(&p_ty, &feature_attribute, config));
+                    }
                 }
             }
         }
     }
 
+    if let Some(trace) = config.trace.as_ref() {
+        out.extend(quote! {
+            /// Returns name of the peripheral which register block contains `address`
+            #[cfg(feature = #trace)]
+            pub fn peripheral_at(address: usize) -> Option<&'static str> {
+                #trace_checks
+                None
+            }
+        });
+    }
+
     if !config.skip_peripherals_struct {
         let nomangle = if config.edition >= RustEdition::E2024 {
             quote!(#[unsafe(no_mangle)])
@@ -375,3 +401,120 @@ pub fn render(d: &Device, config: &Config, device_x: &mut String) -> Result<Toke
 
     Ok(out)
 }
+
+fn trace_check(p_ty: &Ident, feature_attribute: &TokenStream, config: &Config) -> TokenStream {
+    let pspec = ident(
+        &p_ty.to_string(),
+        config,
+        "peripheral_spec",
+        Span::call_site(),
+    );
+    quote! {
+        #feature_attribute
+        if (#pspec::ADDRESS..#pspec::ADDRESS + <<#pspec as crate::PeripheralSpec>::RB>::SIZE)
+            .contains(&address)
+        {
+            return Some(#pspec::NAME);
+        };
+    }
+}
+
+/// Contents of `generic.rs` when the `generic` module is generated as a separate file
+pub fn generic_mod(config: &Config) -> String {
+    let mut file = String::new();
+    writeln!(file, "{}", include_str!("generic.rs")).unwrap();
+    writeln!(file, "{}", generic_trace_file(config)).unwrap();
+    writeln!(file, "{}", generic_reg_file(config)).unwrap();
+    if config.atomics {
+        if let Some(atomics_cfg) = atomics_cfg(config) {
+            writeln!(file, "#[cfg({atomics_cfg})]").unwrap();
+        }
+        writeln!(file, "\n{}", include_str!("generic_atomic.rs")).unwrap();
+    }
+    if config.target == Target::Avr {
+        writeln!(file, "\n{}", include_str!("generic_avr_ccp.rs")).unwrap();
+    }
+    file
+}
+
+/// `cfg` predicate of the atomic operations of the `generic` module
+fn atomics_cfg(config: &Config) -> Option<String> {
+    // The mock backend provides its own atomic operations
+    match (config.atomics_feature.as_ref(), config.mock.as_ref()) {
+        (Some(atomics_feature), Some(mock)) => Some(format!(
+            "all(feature = \"{atomics_feature}\", not(feature = \"{mock}\"))"
+        )),
+        (Some(atomics_feature), None) => Some(format!("feature = \"{atomics_feature}\"")),
+        (None, Some(mock)) => Some(format!("not(feature = \"{mock}\")")),
+        (None, None) => None,
+    }
+}
+
+/// Register backend of `generic` module, optionally switchable to the mock one
+fn generic_reg_file(config: &Config) -> String {
+    let generic_reg_file = if config.pointer_access {
+        include_str!("generic_reg_ptr.rs")
+    } else {
+        include_str!("generic_reg_vcell.rs")
+    };
+    if let Some(mock) = config.mock.as_ref() {
+        let generic_reg_mock_file = include_str!("generic_reg_mock.rs");
+        format!(
+            "#[cfg(not(feature = \"{mock}\"))]
+mod reg_vcell {{
+use super::*;
+
+{generic_reg_file}
+}}
+#[cfg(not(feature = \"{mock}\"))]
+pub use reg_vcell::Reg;
+
+/// Simulated register file for testing on host
+#[cfg(feature = \"{mock}\")]
+pub mod mock {{
+use super::*;
+
+{generic_reg_mock_file}
+}}
+#[cfg(feature = \"{mock}\")]
+pub use mock::{{MockBlock, MockSpec, Reg}};
+"
+        )
+    } else {
+        generic_reg_file.to_string()
+    }
+}
+
+/// Access tracing part of `generic` module.
+///
+/// Register backends report accesses with the `trace!` macro, which expands to nothing
+/// unless tracing is enabled.
+fn generic_trace_file(config: &Config) -> String {
+    let trace_stub = "macro_rules! trace {
+    ($access:ident, $reg:expr, $value:expr) => {};
+}";
+    if let Some(trace) = config.trace.as_ref() {
+        let generic_trace_file = include_str!("generic_trace.rs");
+        format!(
+            "/// Register access tracing
+#[cfg(feature = \"{trace}\")]
+pub mod trace {{
+use super::*;
+
+{generic_trace_file}
+}}
+
+#[cfg(feature = \"{trace}\")]
+macro_rules! trace {{
+    ($access:ident, $reg:expr, $value:expr) => {{
+        crate::generic::trace::record(crate::generic::trace::Access::$access, $reg, $value)
+    }};
+}}
+#[cfg(not(feature = \"{trace}\"))]
+{trace_stub}
+"
+        )
+    } else {
+        trace_stub.to_string()
+    }
+}
@@ -0,0 +1,496 @@
+//! Generation of one crate for a family of devices
+//!
+//! Every device is rendered on its own and the resulting syntax trees are merged. Items that
+//! are the same in all devices are emitted once, items that exist only in some devices or differ
+//! between them are gated by the `#[cfg(feature = "chip")]` features of the devices which have
+//! them. Modules, `impl` blocks and enums are merged item by item (variant by variant), so
+//! chip-specific peripherals, registers, fields and interrupts get their own `cfg`s.
+
+use std::collections::HashMap;
+
+use crate::svd::Device;
+use proc_macro2::TokenStream;
+use quote::{quote, ToTokens};
+use syn::{
+    parse_quote, Attribute, Field, Fields, ImplItem, Item, ItemEnum, ItemImpl, ItemMod, ItemStruct,
+    Variant,
+};
+
+use crate::config::Config;
+use crate::generate::device;
+use anyhow::{bail, Context, Result};
+
+/// Names of the chip features of `devices`
+pub fn chips(devices: &[Device], config: &Config) -> Result<Vec<String>> {
+    let feature_format = config.ident_formats.get("peripheral_feature").unwrap();
+    let mut chips: Vec<String> = Vec::with_capacity(devices.len());
+    for d in devices {
+        let chip = feature_format.apply(&d.name).into_owned();
+        if chips.contains(&chip) {
+            bail!("Device `{}` is passed more than once", d.name);
+        }
+        chips.push(chip);
+    }
+    Ok(chips)
+}
+
+/// Whole family generation
+///
+/// Linker script lines of all devices are collected in `device_x`.
+pub fn render(devices: &[Device], config: &Config, device_x: &mut String) -> Result<TokenStream> {
+    if devices.is_empty() {
+        bail!("Family has no devices");
+    }
+    let chips = chips(devices, config)?;
+
+    let mut files = Vec::with_capacity(devices.len());
+    for d in devices {
+        let mut dx = String::new();
+        let tokens = device::render(d, config, &mut dx)
+            .with_context(|| format!("Error rendering device {}", d.name))?;
+        for line in dx.lines() {
+            if !device_x.lines().any(|l| l == line) {
+                device_x.push_str(line);
+                device_x.push('\n');
+            }
+        }
+        files.push(syn::parse2::<syn::File>(tokens).context("Error parsing generated code")?);
+    }
+
+    let merger = Merger { chips: &chips };
+    let all: Vec<usize> = (0..chips.len()).collect();
+    let attrs = merger.attrs(
+        files
+            .iter_mut()
+            .enumerate()
+            .map(|(chip, f)| (chip, std::mem::take(&mut f.attrs)))
+            .collect(),
+    );
+    let items = merger.list(
+        files
+            .into_iter()
+            .enumerate()
+            .map(|(chip, f)| (chip, f.items))
+            .collect(),
+        &all,
+    )?;
+
+    let features = chips.iter().map(|chip| quote!(feature = #chip));
+    let error = format!("Enable one of the chip features: {}", chips.join(", "));
+    let mut exclusive = TokenStream::new();
+    for (i, a) in chips.iter().enumerate() {
+        for b in &chips[i + 1..] {
+            let error = format!("Chip features `{a}` and `{b}` can't be enabled together");
+            exclusive.extend(quote! {
+                #[cfg(all(feature = #a, feature = #b))]
+                compile_error!(#error);
+            });
+        }
+    }
+    Ok(quote! {
+        #(#attrs)*
+        #[cfg(not(any(#(#features),*)))]
+        compile_error!(#error);
+        #exclusive
+        #(#items)*
+    })
+}
+
+/// Elements of the syntax tree which can be gated
+trait Element: Clone + PartialEq + ToTokens {
+    /// Identifies the element in the scope, elements of different chips with the same key are
+    /// merged
+    fn key(&self) -> String;
+    /// Attributes of the element, `None` for elements which can't have them
+    fn attrs_mut(&mut self) -> Option<&mut Vec<Attribute>>;
+    /// Merges variants of the element which differ between chips, `None` if it can't be done
+    fn merge(
+        _merger: &Merger,
+        _variants: Vec<(usize, Self)>,
+        _scope: &[usize],
+    ) -> Result<Option<Self>> {
+        Ok(None)
+    }
+}
+
+struct Merger<'a> {
+    chips: &'a [String],
+}
+
+impl Merger<'_> {
+    /// `cfg` predicate which is true for `chips`
+    fn predicate(&self, chips: &[usize]) -> TokenStream {
+        let features = chips.iter().map(|&c| {
+            let chip = &self.chips[c];
+            quote!(feature = #chip)
+        });
+        if chips.len() == 1 {
+            quote!(#(#features)*)
+        } else {
+            quote!(any(#(#features),*))
+        }
+    }
+
+    fn gate<T: Element>(&self, mut element: T, chips: &[usize], scope: &[usize]) -> Result<T> {
+        if chips != scope {
+            let predicate = self.predicate(chips);
+            let key = element.key();
+            element
+                .attrs_mut()
+                .with_context(|| format!("`{key}` differs between chips but can't be gated"))?
+                .insert(0, parse_quote!(#[cfg(#predicate)]));
+        }
+        Ok(element)
+    }
+
+    /// Merges the element lists of chips of `scope`
+    fn list<T: Element>(&self, lists: Vec<(usize, Vec<T>)>, scope: &[usize]) -> Result<Vec<T>> {
+        // element variants of each key in order of appearance
+        let mut entries: Entries<String, T> = Vec::new();
+        for (chip, list) in lists {
+            let mut occurrences = HashMap::new();
+            let keyed = list
+                .into_iter()
+                .map(|element| {
+                    let key = element.key();
+                    let n = occurrences.entry(key.clone()).or_insert(0);
+                    *n += 1;
+                    (format!("{key}#{n}"), element)
+                })
+                .collect();
+            align(&mut entries, chip, keyed);
+        }
+
+        let mut merged = Vec::with_capacity(entries.len());
+        for (_, variants) in entries {
+            let present: Vec<usize> = variants.iter().map(|(chip, _)| *chip).collect();
+            let mut groups: Vec<(Vec<usize>, T)> = Vec::new();
+            for (chip, element) in &variants {
+                if let Some(group) = groups.iter_mut().find(|(_, e)| e == element) {
+                    group.0.push(*chip);
+                } else {
+                    groups.push((vec![*chip], element.clone()));
+                }
+            }
+            if groups.len() == 1 {
+                let (_, element) = groups.pop().unwrap();
+                merged.push(self.gate(element, &present, scope)?);
+            } else if let Some(element) = T::merge(self, variants, &present)? {
+                merged.push(self.gate(element, &present, scope)?);
+            } else {
+                for (chips, element) in groups {
+                    merged.push(self.gate(element, &chips, scope)?);
+                }
+            }
+        }
+        Ok(merged)
+    }
+
+    /// Merges attributes, the ones missing in some chips are put into `cfg_attr`
+    fn attrs(&self, lists: Vec<(usize, Vec<Attribute>)>) -> Vec<Attribute> {
+        // repeated attributes like empty doc lines are told apart by occurrence
+        let mut merged: Entries<(Attribute, usize), ()> = Vec::new();
+        let n = lists.len();
+        for (chip, list) in lists {
+            let mut keyed: Vec<((Attribute, usize), ())> = Vec::with_capacity(list.len());
+            for attr in list {
+                let occurrence = keyed.iter().filter(|((a, _), _)| *a == attr).count();
+                keyed.push(((attr, occurrence), ()));
+            }
+            align(&mut merged, chip, keyed);
+        }
+        merged
+            .into_iter()
+            .map(|((attr, _), chips)| {
+                let chips: Vec<usize> = chips.into_iter().map(|(chip, _)| chip).collect();
+                if chips.len() == n {
+                    return attr;
+                }
+                let predicate = self.predicate(&chips);
+                let meta = &attr.meta;
+                let mut gated: Attribute = parse_quote!(#[cfg_attr(#predicate, #meta)]);
+                gated.style = attr.style;
+                gated
+            })
+            .collect()
+    }
+}
+
+/// Keys with the elements of each chip which have them
+type Entries<K, V> = Vec<(K, Vec<(usize, V)>)>;
+
+/// Adds the keyed elements of `chip` to `entries`, elements with new keys are put before the
+/// next element which is already known, so the order of all chips is kept where possible
+fn align<K: PartialEq, V>(entries: &mut Entries<K, V>, chip: usize, list: Vec<(K, V)>) {
+    let mut cursor = 0;
+    // whether new elements go to `cursor`, otherwise the insertion point has to be found
+    let mut inserting = false;
+    let mut list: Vec<_> = list.into_iter().map(Some).collect();
+    for i in 0..list.len() {
+        let (key, element) = list[i].take().unwrap();
+        if let Some(pos) = entries.iter().position(|(k, _)| *k == key) {
+            entries[pos].1.push((chip, element));
+            cursor = pos + 1;
+            inserting = false;
+            continue;
+        }
+        if !inserting {
+            cursor = list[i + 1..]
+                .iter()
+                .flatten()
+                .find_map(|(next, _)| entries[cursor..].iter().position(|(k, _)| k == next))
+                .map_or(entries.len(), |pos| cursor + pos);
+            inserting = true;
+        }
+        entries.insert(cursor, (key, vec![(chip, element)]));
+        cursor += 1;
+    }
+}
+
+fn take_attrs<T: Element>(variants: &mut [(usize, T)]) -> Vec<(usize, Vec<Attribute>)> {
+    variants
+        .iter_mut()
+        .map(|(chip, e)| (*chip, e.attrs_mut().map(std::mem::take).unwrap_or_default()))
+        .collect()
+}
+
+impl Element for Item {
+    fn key(&self) -> String {
+        let ident = match self {
+            Item::Const(i) => &i.ident,
+            Item::Enum(i) => &i.ident,
+            Item::Fn(i) => &i.sig.ident,
+            Item::Mod(i) => &i.ident,
+            Item::Static(i) => &i.ident,
+            Item::Struct(i) => &i.ident,
+            Item::Trait(i) => &i.ident,
+            Item::Type(i) => &i.ident,
+            Item::Union(i) => &i.ident,
+            Item::Impl(i) => {
+                let ItemImpl {
+                    generics,
+                    trait_,
+                    self_ty,
+                    ..
+                } = i;
+                let trait_ = trait_.as_ref().map(|(_, path, _)| path);
+                return quote!(impl #generics #trait_ for #self_ty).to_string();
+            }
+            _ => return self.to_token_stream().to_string(),
+        };
+        let kind = match self {
+            Item::Mod(_) => "mod",
+            Item::Type(_) | Item::Struct(_) | Item::Enum(_) | Item::Union(_) | Item::Trait(_) => {
+                "type"
+            }
+            _ => "value",
+        };
+        format!("{kind} {ident}")
+    }
+
+    fn attrs_mut(&mut self) -> Option<&mut Vec<Attribute>> {
+        Some(match self {
+            Item::Const(i) => &mut i.attrs,
+            Item::Enum(i) => &mut i.attrs,
+            Item::ExternCrate(i) => &mut i.attrs,
+            Item::Fn(i) => &mut i.attrs,
+            Item::ForeignMod(i) => &mut i.attrs,
+            Item::Impl(i) => &mut i.attrs,
+            Item::Macro(i) => &mut i.attrs,
+            Item::Mod(i) => &mut i.attrs,
+            Item::Static(i) => &mut i.attrs,
+            Item::Struct(i) => &mut i.attrs,
+            Item::Trait(i) => &mut i.attrs,
+            Item::TraitAlias(i) => &mut i.attrs,
+            Item::Type(i) => &mut i.attrs,
+            Item::Union(i) => &mut i.attrs,
+            Item::Use(i) => &mut i.attrs,
+            _ => return None,
+        })
+    }
+
+    fn merge(
+        merger: &Merger,
+        mut variants: Vec<(usize, Self)>,
+        scope: &[usize],
+    ) -> Result<Option<Self>> {
+        let attrs = take_attrs(&mut variants);
+        match &variants[0].1 {
+            Item::Mod(_) => merge_mod(merger, attrs, variants, scope),
+            Item::Impl(_) => merge_impl(merger, attrs, variants, scope),
+            Item::Enum(_) => merge_enum(merger, attrs, variants, scope),
+            Item::Struct(_) => merge_struct(merger, attrs, variants, scope),
+            _ => Ok(None),
+        }
+    }
+}
+
+fn merge_mod(
+    merger: &Merger,
+    attrs: Vec<(usize, Vec<Attribute>)>,
+    variants: Vec<(usize, Item)>,
+    scope: &[usize],
+) -> Result<Option<Item>> {
+    let mut shell: Option<ItemMod> = None;
+    let mut lists = Vec::with_capacity(variants.len());
+    for (chip, item) in variants {
+        let Item::Mod(mut m) = item else {
+            return Ok(None);
+        };
+        let Some((_, items)) = m.content.as_mut() else {
+            return Ok(None);
+        };
+        let items = std::mem::take(items);
+        if shell.as_ref().is_some_and(|s| *s != m) {
+            return Ok(None);
+        }
+        shell = Some(m);
+        lists.push((chip, items));
+    }
+    let Some(mut merged) = shell else {
+        return Ok(None);
+    };
+    merged.attrs = merger.attrs(attrs);
+    if let Some((_, items)) = merged.content.as_mut() {
+        *items = merger.list(lists, scope)?;
+    }
+    Ok(Some(Item::Mod(merged)))
+}
+
+fn merge_impl(
+    merger: &Merger,
+    attrs: Vec<(usize, Vec<Attribute>)>,
+    variants: Vec<(usize, Item)>,
+    scope: &[usize],
+) -> Result<Option<Item>> {
+    let mut shell = None;
+    let mut lists = Vec::with_capacity(variants.len());
+    for (chip, item) in variants {
+        let Item::Impl(mut i) = item else {
+            return Ok(None);
+        };
+        let items = std::mem::take(&mut i.items);
+        if shell.as_ref().is_some_and(|s| *s != i) {
+            return Ok(None);
+        }
+        shell = Some(i);
+        lists.push((chip, items));
+    }
+    let Some(mut merged) = shell else {
+        return Ok(None);
+    };
+    merged.attrs = merger.attrs(attrs);
+    merged.items = merger.list(lists, scope)?;
+    Ok(Some(Item::Impl(merged)))
+}
+
+fn merge_enum(
+    merger: &Merger,
+    attrs: Vec<(usize, Vec<Attribute>)>,
+    variants: Vec<(usize, Item)>,
+    scope: &[usize],
+) -> Result<Option<Item>> {
+    let mut shell: Option<ItemEnum> = None;
+    let mut lists = Vec::with_capacity(variants.len());
+    for (chip, item) in variants {
+        let Item::Enum(mut e) = item else {
+            return Ok(None);
+        };
+        let vars = std::mem::take(&mut e.variants).into_iter().collect();
+        if shell.as_ref().is_some_and(|s| *s != e) {
+            return Ok(None);
+        }
+        shell = Some(e);
+        lists.push((chip, vars));
+    }
+    let Some(mut merged) = shell else {
+        return Ok(None);
+    };
+    merged.attrs = merger.attrs(attrs);
+    merged.variants = merger.list(lists, scope)?.into_iter().collect();
+    Ok(Some(Item::Enum(merged)))
+}
+
+/// Merges fields of structs which layout does not matter
+fn merge_struct(
+    merger: &Merger,
+    attrs: Vec<(usize, Vec<Attribute>)>,
+    variants: Vec<(usize, Item)>,
+    scope: &[usize],
+) -> Result<Option<Item>> {
+    if attrs
+        .iter()
+        .flat_map(|(_, attrs)| attrs)
+        .any(|attr| attr.path().is_ident("repr"))
+    {
+        return Ok(None);
+    }
+    let mut shell: Option<ItemStruct> = None;
+    let mut lists = Vec::with_capacity(variants.len());
+    for (chip, item) in variants {
+        let Item::Struct(mut s) = item else {
+            return Ok(None);
+        };
+        let Fields::Named(fields) = &mut s.fields else {
+            return Ok(None);
+        };
+        let fields = std::mem::take(&mut fields.named).into_iter().collect();
+        if shell.as_ref().is_some_and(|sh| *sh != s) {
+            return Ok(None);
+        }
+        shell = Some(s);
+        lists.push((chip, fields));
+    }
+    let Some(mut merged) = shell else {
+        return Ok(None);
+    };
+    merged.attrs = merger.attrs(attrs);
+    if let Fields::Named(fields) = &mut merged.fields {
+        fields.named = merger.list(lists, scope)?.into_iter().collect();
+    }
+    Ok(Some(Item::Struct(merged)))
+}
+
+impl Element for ImplItem {
+    fn key(&self) -> String {
+        match self {
+            ImplItem::Const(i) => format!("value {}", i.ident),
+            ImplItem::Fn(i) => format!("value {}", i.sig.ident),
+            ImplItem::Type(i) => format!("type {}", i.ident),
+            _ => self.to_token_stream().to_string(),
+        }
+    }
+
+    fn attrs_mut(&mut self) -> Option<&mut Vec<Attribute>> {
+        Some(match self {
+            ImplItem::Const(i) => &mut i.attrs,
+            ImplItem::Fn(i) => &mut i.attrs,
+            ImplItem::Type(i) => &mut i.attrs,
+            ImplItem::Macro(i) => &mut i.attrs,
+            _ => return None,
+        })
+    }
+}
+
+impl Element for Field {
+    fn key(&self) -> String {
+        match &self.ident {
+            Some(ident) => ident.to_string(),
+            None => self.to_token_stream().to_string(),
+        }
+    }
+
+    fn attrs_mut(&mut self) -> Option<&mut Vec<Attribute>> {
+        Some(&mut self.attrs)
+    }
+}
+
+impl Element for Variant {
+    fn key(&self) -> String {
+        self.ident.to_string()
+    }
+
+    fn attrs_mut(&mut self) -> Option<&mut Vec<Attribute>> {
+        Some(&mut self.attrs)
+    }
+}
@@ -0,0 +1,289 @@
+//! Selection of the peripherals, clusters and registers to generate
+
+use std::collections::{HashMap, HashSet};
+
+use anyhow::{Context, Result};
+use log::info;
+use regex::Regex;
+
+use crate::config::Config;
+use crate::svd::{Device, PeripheralInfo, RegisterCluster};
+use crate::util;
+
+/// Whether `--include` or `--exclude` patterns are given
+pub fn is_active(config: &Config) -> bool {
+    !config.include.is_empty() || !config.exclude.is_empty()
+}
+
+/// Removes peripherals, clusters and registers not selected by `--include` and `--exclude`
+///
+/// An element is selected when its path or the path of one of its parents matches an `include`
+/// pattern (or there are none) and neither matches an `exclude` pattern. Elements that selected
+/// elements derive from are kept as well, as are the peripherals and clusters containing
+/// selected elements.
+pub fn filter(device: &Device, config: &Config) -> Result<Device> {
+    let include = patterns(&config.include)?;
+    let exclude = patterns(&config.exclude)?;
+
+    let mut nodes = Vec::new();
+    for p in &device.peripherals {
+        nodes.push(Node {
+            path: p.name.clone(),
+            dpaths: p
+                .derived_from
+                .iter()
+                .map(|d| (String::new(), d.clone()))
+                .collect(),
+        });
+        collect(
+            p.registers.as_deref().unwrap_or_default(),
+            &p.name,
+            &mut nodes,
+        );
+    }
+    let known: HashSet<&str> = nodes.iter().map(|n| n.path.as_str()).collect();
+
+    let mut selected = HashSet::new();
+    let mut queue = Vec::new();
+    for node in &nodes {
+        let path: Vec<_> = node.path.split('.').collect();
+        let lineage: Vec<_> = (1..=path.len()).map(|len| &path[..len]).collect();
+        let included =
+            include.is_empty() || lineage.iter().any(|p| include.iter().any(|i| i.matches(p)));
+        let excluded = lineage.iter().any(|p| exclude.iter().any(|e| e.matches(p)));
+        if included && !excluded {
+            selected.insert(node.path.as_str());
+            queue.push(node);
+        }
+    }
+
+    // keep what selected elements are derived from, with everything inside of it
+    let by_path: HashMap<&str, &Node> = nodes.iter().map(|n| (n.path.as_str(), n)).collect();
+    while let Some(node) = queue.pop() {
+        for (block, dpath) in &node.dpaths {
+            let Some(base) = resolve(&known, block, dpath) else {
+                continue;
+            };
+            for (path, dependency) in &by_path {
+                let inside = path
+                    .strip_prefix(base)
+                    .is_some_and(|rest| rest.is_empty() || rest.starts_with('.'));
+                if inside && selected.insert(*path) {
+                    if *path == base {
+                        info!("Keeping {base} as {} is derived from it", node.path);
+                    }
+                    queue.push(dependency);
+                }
+            }
+        }
+    }
+
+    let mut device = device.clone();
+    device.peripherals.retain_mut(|p| {
+        let p: &mut PeripheralInfo = p;
+        if let Some(registers) = p.registers.as_mut() {
+            retain(registers, &p.name, &selected);
+        }
+        selected.contains(p.name.as_str()) || p.registers.as_ref().is_some_and(|r| !r.is_empty())
+    });
+    Ok(device)
+}
+
+/// Element of the device with the `derivedFrom` paths of it and its fields and enumerated values
+///
+/// The paths come with the path of the block they are relative to.
+struct Node {
+    path: String,
+    dpaths: Vec<(String, String)>,
+}
+
+fn collect(children: &[RegisterCluster], block: &str, nodes: &mut Vec<Node>) {
+    for child in children {
+        match child {
+            RegisterCluster::Cluster(c) => {
+                let path = format!("{block}.{}", c.name);
+                collect(&c.children, &path, nodes);
+                nodes.push(Node {
+                    dpaths: c
+                        .derived_from
+                        .iter()
+                        .map(|d| (block.to_string(), d.clone()))
+                        .collect(),
+                    path,
+                });
+            }
+            RegisterCluster::Register(r) => {
+                let mut dpaths: Vec<_> = r
+                    .derived_from
+                    .iter()
+                    .map(|d| (block.to_string(), d.clone()))
+                    .collect();
+                for f in r.fields() {
+                    // `REGISTER.FIELD`
+                    let fpaths = f.derived_from.iter().filter_map(|d| d.rsplit_once('.'));
+                    // `REGISTER.FIELD.ENUM`
+                    let epaths = f
+                        .enumerated_values
+                        .iter()
+                        .filter_map(|ev| ev.derived_from.as_deref()?.rsplitn(3, '.').nth(2));
+                    dpaths.extend(
+                        fpaths
+                            .map(|(rpath, _)| rpath)
+                            .chain(epaths)
+                            .map(|rpath| (block.to_string(), rpath.to_string())),
+                    );
+                }
+                nodes.push(Node {
+                    path: format!("{block}.{}", r.name),
+                    dpaths,
+                });
+            }
+        }
+    }
+}
+
+/// Finds the element `dpath` refers to, relative to `block` or one of its parents or absolute
+fn resolve<'a>(known: &HashSet<&'a str>, block: &str, dpath: &str) -> Option<&'a str> {
+    let mut block = Some(block).filter(|b| !b.is_empty());
+    while let Some(b) = block {
+        if let Some(path) = known.get(format!("{b}.{dpath}").as_str()) {
+            return Some(path);
+        }
+        block = b.rsplit_once('.').map(|(parent, _)| parent);
+    }
+    known.get(dpath).copied()
+}
+
+fn retain(children: &mut Vec<RegisterCluster>, block: &str, selected: &HashSet<&str>) {
+    children.retain_mut(|child| match child {
+        RegisterCluster::Register(r) => selected.contains(format!("{block}.{}", r.name).as_str()),
+        RegisterCluster::Cluster(c) => {
+            let path = format!("{block}.{}", c.name);
+            retain(&mut c.children, &path, selected);
+            selected.contains(path.as_str()) || !c.children.is_empty()
+        }
+    });
+}
+
+/// Pattern matching the path of an element
+///
+/// Either a regular expression between slashes matched against the whole path or
+/// a name pattern for each level of the path.
+#[derive(Clone, Debug)]
+pub(crate) enum Pattern {
+    Regex(Regex),
+    Glob(Vec<Regex>),
+}
+
+impl Pattern {
+    pub(crate) fn new(pattern: &str) -> Result<Self> {
+        match pattern
+            .strip_prefix('/')
+            .and_then(|re| re.strip_suffix('/'))
+        {
+            Some(re) => Regex::new(&format!("^(?:{re})$"))
+                .map(Self::Regex)
+                .with_context(|| format!("Invalid path regex `{pattern}`")),
+            None => pattern
+                .split('.')
+                .map(util::glob)
+                .collect::<Result<_>>()
+                .map(Self::Glob),
+        }
+    }
+
+    pub(crate) fn matches(&self, path: &[&str]) -> bool {
+        match self {
+            Self::Regex(re) => re.is_match(&path.join(".")),
+            Self::Glob(segments) => {
+                segments.len() == path.len()
+                    && segments.iter().zip(path).all(|(s, name)| s.is_match(name))
+            }
+        }
+    }
+}
+
+fn patterns(patterns: &[String]) -> Result<Vec<Pattern>> {
+    patterns
+        .iter()
+        .map(|pattern| Pattern::new(pattern))
+        .collect()
+}
+
+#[cfg(test)]
+const TEST_SVD: &str = r#"<device><name>CHIP</name><size>32</size><peripherals>
+<peripheral><name>GPIOA</name><baseAddress>0x40010800</baseAddress><registers>
+<register><name>CRL</name><addressOffset>0x0</addressOffset></register>
+<register><name>IDR</name><addressOffset>0x8</addressOffset></register>
+<cluster><name>LOCK</name><addressOffset>0x10</addressOffset>
+<register><name>KEY</name><addressOffset>0x0</addressOffset></register>
+</cluster>
+</registers></peripheral>
+<peripheral derivedFrom="GPIOA"><name>GPIOB</name><baseAddress>0x40010C00</baseAddress></peripheral>
+<peripheral><name>TIM2</name><baseAddress>0x40000000</baseAddress><registers>
+<register><name>CR1</name><addressOffset>0x0</addressOffset></register>
+<register derivedFrom="CR1"><name>CR2</name><addressOffset>0x4</addressOffset></register>
+</registers></peripheral>
+</peripherals></device>"#;
+
+#[cfg(test)]
+fn filtered(include: &[&str], exclude: &[&str]) -> Vec<String> {
+    let device = svd_parser::parse(TEST_SVD).unwrap();
+    let config = Config {
+        include: include.iter().map(|p| p.to_string()).collect(),
+        exclude: exclude.iter().map(|p| p.to_string()).collect(),
+        ..Default::default()
+    };
+    let mut paths = Vec::new();
+    let mut nodes = Vec::new();
+    for p in &filter(&device, &config).unwrap().peripherals {
+        paths.push(p.name.clone());
+        collect(
+            p.registers.as_deref().unwrap_or_default(),
+            &p.name,
+            &mut nodes,
+        );
+    }
+    paths.extend(nodes.into_iter().map(|n| n.path));
+    paths.sort();
+    paths
+}
+
+#[test]
+fn path_patterns() {
+    let glob = Pattern::new("GPIO?.*").unwrap();
+    assert!(glob.matches(&["GPIOA", "CRL"]));
+    assert!(!glob.matches(&["GPIOA"]) && !glob.matches(&["GPIOA", "LOCK", "KEY"]));
+    let regex = Pattern::new("/GPIOA\\..*KEY/").unwrap();
+    assert!(regex.matches(&["GPIOA", "LOCK", "KEY"]));
+    assert!(!regex.matches(&["GPIOB", "LOCK", "KEY"]));
+    assert!(Pattern::new("/(/").is_err());
+}
+
+#[test]
+fn include_exclude() {
+    assert_eq!(
+        filtered(&["TIM*"], &["TIM2.CR1"]),
+        ["TIM2", "TIM2.CR1", "TIM2.CR2"]
+    );
+    assert_eq!(
+        filtered(&["GPIOA.LOCK"], &[]),
+        ["GPIOA", "GPIOA.LOCK", "GPIOA.LOCK.KEY"]
+    );
+    assert_eq!(
+        filtered(&["GPIOA"], &["*.IDR"]),
+        ["GPIOA", "GPIOA.CRL", "GPIOA.LOCK", "GPIOA.LOCK.KEY"]
+    );
+    // what selected peripherals derive from is kept whole
+    assert_eq!(
+        filtered(&["GPIOB"], &["*.IDR"]),
+        [
+            "GPIOA",
+            "GPIOA.CRL",
+            "GPIOA.IDR",
+            "GPIOA.LOCK",
+            "GPIOA.LOCK.KEY",
+            "GPIOB"
+        ]
+    );
+}
@@ -0,0 +1,176 @@
+//! GDB target description and memory map of the peripheral registers
+//!
+//! Both are built from the [`RegisterMap`], so the debugger sees the registers at the addresses
+//! the generated code uses.
+
+use std::fmt::Write;
+use std::path::PathBuf;
+
+use anyhow::Result;
+
+use crate::config::Config;
+use crate::generate::register_map::{MappedRegister, RegisterMap};
+use crate::ir;
+use crate::svd::{Device, Usage};
+use crate::util::escape_html;
+
+/// Target description feature and memory map files of each device
+///
+/// A single device gives `gdb-target.xml` and `gdb-memory-map.xml`, the files of the devices
+/// of a family are prefixed with their name.
+pub fn render(devices: &[Device], config: &Config) -> Result<Vec<(PathBuf, String)>> {
+    let mut files = Vec::with_capacity(2 * devices.len());
+    for device in devices {
+        let map = RegisterMap::new(device, config)?;
+        let prefix = if config.family.is_empty() && devices.len() == 1 {
+            String::new()
+        } else {
+            format!("{}-", device.name.to_lowercase())
+        };
+        files.push((
+            PathBuf::from(format!("{prefix}gdb-target.xml")),
+            target_feature(&map)?,
+        ));
+        files.push((
+            PathBuf::from(format!("{prefix}gdb-memory-map.xml")),
+            memory_map(&map)?,
+        ));
+    }
+    Ok(files)
+}
+
+/// `<feature>` with a `<reg>` for each register, typed with `<flags>` of its fields
+///
+/// GDB has no notion of memory mapped registers, the address of each one is given in a comment
+/// and its peripheral as the register group.
+pub fn target_feature(map: &RegisterMap) -> Result<String> {
+    let mut out = String::new();
+    writeln!(out, r#"<?xml version="1.0"?>"#)?;
+    writeln!(out, r#"<!DOCTYPE feature SYSTEM "gdb-target.dtd">"#)?;
+    writeln!(
+        out,
+        r#"<feature name="org.svd2rust.{}">"#,
+        escape_html(&map.device.to_lowercase())
+    )?;
+    for r in &map.registers {
+        let name = reg_name(r);
+        let register = &r.register;
+        let bytes = u64::from(register.size).div_ceil(8);
+        let ty = if register.fields.is_empty() {
+            match register.size {
+                8 | 16 | 32 | 64 => Some(format!("uint{}", register.size)),
+                _ => None,
+            }
+        } else {
+            for f in &register.fields {
+                let Some(e) = read_enum(f) else {
+                    continue;
+                };
+                writeln!(
+                    out,
+                    r#"  <enum id="{name}_{}" size="{bytes}">"#,
+                    escape_html(&f.name)
+                )?;
+                for v in &e.values {
+                    if let Some(value) = v.value {
+                        writeln!(
+                            out,
+                            r#"    <evalue name="{}" value="{value}"/>"#,
+                            escape_html(&v.name)
+                        )?;
+                    }
+                }
+                writeln!(out, "  </enum>")?;
+            }
+            writeln!(out, r#"  <flags id="{name}_flags" size="{bytes}">"#)?;
+            for f in &register.fields {
+                let fname = escape_html(&f.name);
+                let start = f.bit_offset;
+                let end = f.bit_offset + f.bit_width - 1;
+                if read_enum(f).is_some() {
+                    writeln!(
+                        out,
+                        r#"    <field name="{fname}" start="{start}" end="{end}" type="{name}_{fname}"/>"#
+                    )?;
+                } else {
+                    writeln!(
+                        out,
+                        r#"    <field name="{fname}" start="{start}" end="{end}"/>"#
+                    )?;
+                }
+            }
+            writeln!(out, "  </flags>")?;
+            Some(format!("{name}_flags"))
+        };
+        writeln!(out, "  <!-- {} at {:#x} -->", r.path, register.address)?;
+        write!(
+            out,
+            r#"  <reg name="{name}" bitsize="{}" group="{}""#,
+            register.size,
+            escape_html(&r.peripheral)
+        )?;
+        if let Some(ty) = ty {
+            write!(out, r#" type="{ty}""#)?;
+        }
+        writeln!(out, "/>")?;
+    }
+    writeln!(out, "</feature>")?;
+    Ok(out)
+}
+
+/// `<memory-map>` with a region for the registers of each peripheral instance
+///
+/// Regions span from the first to the last register of the peripheral, overlapping ones are
+/// merged.
+pub fn memory_map(map: &RegisterMap) -> Result<String> {
+    let mut peripherals: Vec<(&str, u64, u64)> = Vec::new();
+    for r in &map.registers {
+        let start = r.register.address;
+        let end = start + u64::from(r.register.size).div_ceil(8);
+        match peripherals.iter_mut().find(|p| p.0 == r.peripheral) {
+            Some(p) => {
+                p.1 = p.1.min(start);
+                p.2 = p.2.max(end);
+            }
+            None => peripherals.push((&r.peripheral, start, end)),
+        }
+    }
+    peripherals.sort_by_key(|p| p.1);
+
+    let mut regions: Vec<(u64, u64)> = Vec::new();
+    for (_, start, end) in peripherals {
+        match regions.last_mut() {
+            Some(last) if start < last.1 => last.1 = last.1.max(end),
+            _ => regions.push((start, end)),
+        }
+    }
+
+    let mut out = String::new();
+    writeln!(out, r#"<?xml version="1.0"?>"#)?;
+    writeln!(
+        out,
+        r#"<!DOCTYPE memory-map PUBLIC "+//IDN gnu.org//DTD GDB Memory Map V1.0//EN" "http://sourceware.org/gdb/gdb-memory-map.dtd">"#
+    )?;
+    writeln!(out, "<memory-map>")?;
+    for (start, end) in regions {
+        writeln!(
+            out,
+            r#"  <memory type="ram" start="{start:#x}" length="{:#x}"/>"#,
+            end - start
+        )?;
+    }
+    writeln!(out, "</memory-map>")?;
+    Ok(out)
+}
+
+/// Enumerated values shown when reading the field
+fn read_enum(f: &ir::Field) -> Option<&ir::Enum> {
+    f.enums
+        .iter()
+        .find(|e| e.usage != Usage::Write && e.values.iter().any(|v| v.value.is_some()))
+}
+
+/// Register name unique in the device, the SVD path with `_` instead of `.`
+fn reg_name(r: &MappedRegister) -> String {
+    escape_html(&r.path.replace('.', "_"))
+}
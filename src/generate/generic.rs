@@ -24,15 +24,6 @@ impl<PER: PeripheralSpec> core::fmt::Debug for Periph<PER> {
 }
 
 impl<PER: PeripheralSpec> Periph<PER> {
-    ///Pointer to the register block
-    pub const PTR: *const PER::RB = PER::ADDRESS as *const _;
-
-    ///Return the pointer to the register block
-    #[inline(always)]
-    pub const fn ptr() -> *const PER::RB {
-        Self::PTR
-    }
-
     /// Steal an instance of this peripheral
     ///
     /// # Safety
@@ -53,19 +44,11 @@ impl<PER: PeripheralSpec> Periph<PER> {
     }
 }
 
-impl<PER: PeripheralSpec> core::ops::Deref for Periph<PER> {
-    type Target = PER::RB;
-
-    #[inline(always)]
-    fn deref(&self) -> &Self::Target {
-        unsafe { &*Self::PTR }
-    }
-}
-
 /// Raw register type (`u8`, `u16`, `u32`, ...)
 pub trait RawReg:
     Copy
     + From<bool>
+    + Into<u64>
     + core::ops::BitOr<Output = Self>
     + core::ops::BitAnd<Output = Self>
     + core::ops::BitOrAssign
@@ -109,6 +92,8 @@ raw_reg!(u64, 64, mask_u64);
 pub trait RegisterSpec {
     /// Raw register type (`u8`, `u16`, `u32`, ...).
     type Ux: RawReg;
+    /// Register name reported by access tracing
+    const NAME: &'static str = "";
 }
 
 /// Raw field type
@@ -57,7 +57,8 @@ mod atomic {
                 _reg: marker::PhantomData,
             })
             .bits;
-            REG::Ux::atomic_or(self.register.as_ptr(), bits);
+            REG::Ux::atomic_or(self.as_ptr(), bits);
+            trace!(SetBits, self, bits);
         }
 
         /// Clear every bit in the register that was cleared in the write proxy. Leave other bits
@@ -76,7 +77,8 @@ mod atomic {
                 _reg: marker::PhantomData,
             })
             .bits;
-            REG::Ux::atomic_and(self.register.as_ptr(), bits);
+            REG::Ux::atomic_and(self.as_ptr(), bits);
+            trace!(ClearBits, self, bits);
         }
 
         /// Toggle every bit in the register that was set in the write proxy. Leave other bits
@@ -95,7 +97,8 @@ mod atomic {
                 _reg: marker::PhantomData,
             })
             .bits;
-            REG::Ux::atomic_xor(self.register.as_ptr(), bits);
+            REG::Ux::atomic_xor(self.as_ptr(), bits);
+            trace!(ToggleBits, self, bits);
         }
     }
 }
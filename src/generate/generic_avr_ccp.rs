@@ -112,7 +112,7 @@ where
             _reg: marker::PhantomData,
         })).bits;
 
-        unsafe { ccp_protected_write::<REG>(self.register.as_ptr(), val) }
+        unsafe { ccp_protected_write::<REG>(self.as_ptr(), val) }
     }
 }
 
@@ -140,6 +140,6 @@ impl<REG: RegisterSpec<Ux = u8> + Readable + Writable + Protected> Reg<REG> {
         )
         .bits;
 
-        unsafe { ccp_protected_write::<REG>(self.register.as_ptr(), val) }
+        unsafe { ccp_protected_write::<REG>(self.as_ptr(), val) }
     }
 }
@@ -0,0 +1,619 @@
+extern crate std;
+
+use core::cell::{RefCell, UnsafeCell};
+use std::alloc::{self, Layout};
+use std::boxed::Box;
+use std::collections::{BTreeMap, BTreeSet};
+use std::vec::Vec;
+
+/// Raw register type which can be stored in the simulated register file
+pub trait MockRaw: RawReg {
+    #[doc(hidden)]
+    fn from_u64(value: u64) -> Self;
+}
+
+macro_rules! impl_mock_raw {
+    ($($U:ty),*) => {
+        $(
+            impl MockRaw for $U {
+                #[inline(always)]
+                fn from_u64(value: u64) -> Self {
+                    value as $U
+                }
+            }
+        )*
+    };
+}
+impl_mock_raw!(u8, u16, u32, u64);
+
+/// Behaviour of register in the simulated register file
+pub trait MockSpec: RegisterSpec
+where
+    Self::Ux: MockRaw,
+{
+    /// Value the register holds after reset
+    const RESET_VALUE: Self::Ux = Self::Ux::ZERO;
+    /// Bits cleared by reading the register (`readAction` is `clear`)
+    const READ_CLEAR_BITMAP: Self::Ux = Self::Ux::ZERO;
+    /// Bits set by reading the register (`readAction` is `set`)
+    const READ_SET_BITMAP: Self::Ux = Self::Ux::ZERO;
+    /// Bits set by writing `1` (`oneToSet`)
+    const ONE_TO_SET_BITMAP: Self::Ux = Self::Ux::ZERO;
+    /// Bits toggled by writing `1` (`oneToToggle`)
+    const ONE_TO_TOGGLE_BITMAP: Self::Ux = Self::Ux::ZERO;
+    /// Bits set by writing `0` (`zeroToSet`)
+    const ZERO_TO_SET_BITMAP: Self::Ux = Self::Ux::ZERO;
+    /// Bits toggled by writing `0` (`zeroToToggle`)
+    const ZERO_TO_TOGGLE_BITMAP: Self::Ux = Self::Ux::ZERO;
+    /// Bits of read-only fields, which writes don't change
+    const READ_ONLY_BITMAP: Self::Ux = Self::Ux::ZERO;
+}
+
+/// Register block of peripheral in the simulated register file
+pub trait MockBlock {
+    /// Size of the registers in bytes, which can be larger than the size of the block type
+    const SIZE: usize;
+}
+
+type ReadHook = Box<dyn FnMut(u64) -> u64>;
+type WriteHook = Box<dyn FnMut(u64, u64) -> u64>;
+
+struct Block {
+    address: usize,
+    name: &'static str,
+    ptr: *mut u8,
+    layout: Layout,
+}
+
+#[derive(Default)]
+struct State {
+    blocks: Vec<Block>,
+    initialized: BTreeSet<usize>,
+    read_hooks: BTreeMap<usize, ReadHook>,
+    write_hooks: BTreeMap<usize, WriteHook>,
+}
+
+impl Drop for State {
+    fn drop(&mut self) {
+        for block in &self.blocks {
+            if block.layout.size() != 0 {
+                unsafe { alloc::dealloc(block.ptr, block.layout) };
+            }
+        }
+    }
+}
+
+std::thread_local! {
+    static STATE: RefCell<State> = RefCell::new(State::default());
+}
+
+/// Returns simulated register block of peripheral, allocating it on first use
+fn block<PER: PeripheralSpec>() -> *const PER::RB
+where
+    PER::RB: MockBlock,
+{
+    STATE.with(|state| {
+        let mut state = state.borrow_mut();
+        if let Some(block) = state
+            .blocks
+            .iter()
+            .find(|b| b.address == PER::ADDRESS && b.name == PER::NAME)
+        {
+            return block.ptr as *const PER::RB;
+        }
+        // Register blocks are never freed, so references obtained through `Periph` stay valid
+        let layout = Layout::new::<PER::RB>();
+        let layout = Layout::from_size_align(PER::RB::SIZE.max(layout.size()), layout.align())
+            .unwrap();
+        let ptr = if layout.size() == 0 {
+            core::ptr::NonNull::<PER::RB>::dangling().as_ptr().cast::<u8>()
+        } else {
+            let ptr = unsafe { alloc::alloc_zeroed(layout) };
+            if ptr.is_null() {
+                alloc::handle_alloc_error(layout);
+            }
+            ptr
+        };
+        state.blocks.push(Block {
+            address: PER::ADDRESS,
+            name: PER::NAME,
+            ptr,
+            layout,
+        });
+        ptr as *const PER::RB
+    })
+}
+
+impl<PER: PeripheralSpec> Periph<PER>
+where
+    PER::RB: MockBlock,
+{
+    ///Pointer to the register block on the device, use `ptr()` to access the simulated one
+    pub const PTR: *const PER::RB = PER::ADDRESS as *const _;
+
+    ///Return the pointer to the simulated register block
+    #[inline(always)]
+    pub fn ptr() -> *const PER::RB {
+        block::<PER>()
+    }
+}
+
+impl<PER: PeripheralSpec> core::ops::Deref for Periph<PER>
+where
+    PER::RB: MockBlock,
+{
+    type Target = PER::RB;
+
+    #[inline(always)]
+    fn deref(&self) -> &Self::Target {
+        unsafe { &*block::<PER>() }
+    }
+}
+
+/// Peripheral accessor with register block at address known only at runtime
+///
+/// Dereferences to the same register block as [`Periph`].
+pub struct DynPeriph<PER: PeripheralSpec> {
+    ptr: *const PER::RB,
+}
+
+unsafe impl<PER: PeripheralSpec> Send for DynPeriph<PER> {}
+
+impl<PER: PeripheralSpec> core::fmt::Debug for DynPeriph<PER> {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        f.debug_struct(PER::NAME).field("ptr", &self.ptr).finish()
+    }
+}
+
+impl<PER: PeripheralSpec> Periph<PER> {
+    /// Creates accessor of peripheral which registers are mapped at `ptr`
+    /// instead of `PeripheralSpec::ADDRESS`, e.g. with `mmap` in userspace.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must point to the registers of this peripheral and stay valid while
+    /// the accessor is used. Same rules as for `steal` apply.
+    #[inline(always)]
+    pub const unsafe fn from_ptr(ptr: *mut u8) -> DynPeriph<PER> {
+        DynPeriph {
+            ptr: ptr as *const _,
+        }
+    }
+}
+
+impl<PER: PeripheralSpec> DynPeriph<PER> {
+    ///Return the base address of the register block
+    #[inline(always)]
+    pub const fn as_ptr(&self) -> *mut u8 {
+        self.ptr as *mut _
+    }
+}
+
+impl<PER: PeripheralSpec> core::ops::Deref for DynPeriph<PER> {
+    type Target = PER::RB;
+
+    #[inline(always)]
+    fn deref(&self) -> &Self::Target {
+        unsafe { &*self.ptr }
+    }
+}
+
+/// Puts all simulated registers of the current thread back to their reset values
+/// and removes installed hooks.
+pub fn reset() {
+    let hooks = STATE.with(|state| {
+        let mut state = state.borrow_mut();
+        for block in &state.blocks {
+            if block.layout.size() != 0 {
+                unsafe { core::ptr::write_bytes(block.ptr, 0, block.layout.size()) };
+            }
+        }
+        state.initialized.clear();
+        (
+            core::mem::take(&mut state.read_hooks),
+            core::mem::take(&mut state.write_hooks),
+        )
+    });
+    // Hooks may capture registers, drop them after `STATE` is released
+    drop(hooks);
+}
+
+/// Installs hook called on every read of register.
+///
+/// Hook receives stored value and returns value seen by reader.
+pub fn on_read<REG: MockSpec>(reg: &Reg<REG>, mut hook: impl FnMut(REG::Ux) -> REG::Ux + 'static)
+where
+    REG::Ux: MockRaw,
+{
+    let hook: ReadHook = Box::new(move |value| hook(REG::Ux::from_u64(value)).into());
+    let old = STATE.with(|state| state.borrow_mut().read_hooks.insert(reg.key(), hook));
+    drop(old);
+}
+
+/// Installs hook called on every write of register.
+///
+/// Hook receives bits written by software and value register would store after applying
+/// `modifiedWriteValues` semantics and returns value to store.
+pub fn on_write<REG: MockSpec>(
+    reg: &Reg<REG>,
+    mut hook: impl FnMut(REG::Ux, REG::Ux) -> REG::Ux + 'static,
+) where
+    REG::Ux: MockRaw,
+{
+    let hook: WriteHook = Box::new(move |written, value| {
+        hook(REG::Ux::from_u64(written), REG::Ux::from_u64(value)).into()
+    });
+    let old = STATE.with(|state| state.borrow_mut().write_hooks.insert(reg.key(), hook));
+    drop(old);
+}
+
+/// Removes read and write hooks of register.
+pub fn clear_hooks<REG: MockSpec>(reg: &Reg<REG>)
+where
+    REG::Ux: MockRaw,
+{
+    let old = STATE.with(|state| {
+        let mut state = state.borrow_mut();
+        (
+            state.read_hooks.remove(&reg.key()),
+            state.write_hooks.remove(&reg.key()),
+        )
+    });
+    drop(old);
+}
+
+/// Returns value stored in register without triggering hooks or read side effects.
+pub fn peek<REG: MockSpec>(reg: &Reg<REG>) -> REG::Ux
+where
+    REG::Ux: MockRaw,
+{
+    reg.init();
+    unsafe { reg.register.get().read() }
+}
+
+/// Stores value in register without triggering hooks or write semantics.
+///
+/// Use it to simulate hardware changing register, e.g. setting status flags.
+pub fn poke<REG: MockSpec>(reg: &Reg<REG>, value: REG::Ux)
+where
+    REG::Ux: MockRaw,
+{
+    reg.init();
+    unsafe { reg.register.get().write(value) }
+}
+
+/// This structure provides access to register stored in the simulated register file.
+#[repr(transparent)]
+pub struct Reg<REG: RegisterSpec> {
+    register: UnsafeCell<REG::Ux>,
+    _marker: marker::PhantomData<REG>,
+}
+
+unsafe impl<REG: RegisterSpec> Send for Reg<REG> where REG::Ux: Send {}
+
+impl<REG: RegisterSpec> Reg<REG> {
+    /// Address the register has on the device
+    #[allow(dead_code)]
+    pub(crate) fn address(&self) -> usize {
+        let host = self as *const Self as usize;
+        STATE.with(|state| {
+            state
+                .borrow()
+                .blocks
+                .iter()
+                .find(|b| (b.ptr as usize..b.ptr as usize + b.layout.size()).contains(&host))
+                .map_or(host, |b| b.address + (host - b.ptr as usize))
+        })
+    }
+}
+
+impl<REG: MockSpec> Reg<REG>
+where
+    REG::Ux: MockRaw,
+{
+    #[inline(always)]
+    fn key(&self) -> usize {
+        self.register.get() as usize
+    }
+
+    /// Puts reset value into register on first access
+    fn init(&self) {
+        let first = STATE.with(|state| state.borrow_mut().initialized.insert(self.key()));
+        if first {
+            unsafe { self.register.get().write(<REG as MockSpec>::RESET_VALUE) };
+        }
+    }
+
+    fn load(&self) -> REG::Ux {
+        self.init();
+        let ptr = self.register.get();
+        let stored = unsafe { ptr.read() };
+        let value = match STATE.with(|state| state.borrow_mut().read_hooks.remove(&self.key())) {
+            Some(mut hook) => {
+                let value = REG::Ux::from_u64(hook(stored.into()));
+                STATE.with(|state| {
+                    state.borrow_mut().read_hooks.entry(self.key()).or_insert(hook);
+                });
+                value
+            }
+            None => stored,
+        };
+        // hook could change register, so reload it
+        let stored = unsafe { ptr.read() };
+        unsafe { ptr.write(stored & !REG::READ_CLEAR_BITMAP | REG::READ_SET_BITMAP) };
+        value
+    }
+
+    fn store(&self, written: REG::Ux)
+    where
+        REG: Writable,
+    {
+        self.init();
+        let ptr = self.register.get();
+        let old = unsafe { ptr.read() };
+        let one = REG::ONE_TO_MODIFY_FIELDS_BITMAP;
+        let zero = REG::ZERO_TO_MODIFY_FIELDS_BITMAP;
+        let plain = !(one | zero | REG::READ_ONLY_BITMAP);
+        let ones = written & one;
+        let zeros = !written & zero;
+        let set = ones & REG::ONE_TO_SET_BITMAP | zeros & REG::ZERO_TO_SET_BITMAP;
+        let toggle = ones & REG::ONE_TO_TOGGLE_BITMAP | zeros & REG::ZERO_TO_TOGGLE_BITMAP;
+        let clear = (ones | zeros) & !(set | toggle);
+        let value = written & plain | old & !plain;
+        let value = value & !clear | set;
+        let value = value & !toggle | !value & toggle;
+        let value = match STATE.with(|state| state.borrow_mut().write_hooks.remove(&self.key()))
+        {
+            Some(mut hook) => {
+                let value = REG::Ux::from_u64(hook(written.into(), value.into()));
+                STATE.with(|state| {
+                    state.borrow_mut().write_hooks.entry(self.key()).or_insert(hook);
+                });
+                value
+            }
+            None => value,
+        };
+        unsafe { ptr.write(value) };
+    }
+
+    /// Changes stored value like atomic instructions do, without hooks or
+    /// `readAction` and `modifiedWriteValues` semantics.
+    fn update(&self, f: impl FnOnce(REG::Ux) -> REG::Ux) {
+        self.init();
+        let ptr = self.register.get();
+        unsafe { ptr.write(f(ptr.read())) };
+    }
+
+    /// Returns the address of register in the simulated register file.
+    #[inline(always)]
+    pub fn as_ptr(&self) -> *mut REG::Ux {
+        self.init();
+        self.register.get()
+    }
+}
+
+impl<REG: Readable + MockSpec> Reg<REG>
+where
+    REG::Ux: MockRaw,
+{
+    /// Reads the contents of a `Readable` register.
+    #[inline(always)]
+    pub fn read(&self) -> R<REG> {
+        let bits = self.load();
+        trace!(Read, self, bits);
+        R {
+            bits,
+            _reg: marker::PhantomData,
+        }
+    }
+}
+
+impl<REG: Resettable + Writable + MockSpec> Reg<REG>
+where
+    REG::Ux: MockRaw,
+{
+    /// Writes the reset value to `Writable` register.
+    #[inline(always)]
+    pub fn reset(&self) {
+        self.store(<REG as Resettable>::RESET_VALUE);
+        trace!(Write, self, <REG as Resettable>::RESET_VALUE);
+    }
+
+    /// Writes bits to a `Writable` register.
+    #[inline(always)]
+    pub fn write<F>(&self, f: F) -> REG::Ux
+    where
+        F: FnOnce(&mut W<REG>) -> &mut W<REG>,
+    {
+        let value = f(&mut W {
+            bits: <REG as Resettable>::RESET_VALUE & !REG::ONE_TO_MODIFY_FIELDS_BITMAP
+                | REG::ZERO_TO_MODIFY_FIELDS_BITMAP,
+            _reg: marker::PhantomData,
+        })
+        .bits;
+        self.store(value);
+        trace!(Write, self, value);
+        value
+    }
+
+    /// Writes bits to a `Writable` register and produce a value.
+    #[inline(always)]
+    pub fn from_write<F, T>(&self, f: F) -> T
+    where
+        F: FnOnce(&mut W<REG>) -> T,
+    {
+        let mut writer = W {
+            bits: <REG as Resettable>::RESET_VALUE & !REG::ONE_TO_MODIFY_FIELDS_BITMAP
+                | REG::ZERO_TO_MODIFY_FIELDS_BITMAP,
+            _reg: marker::PhantomData,
+        };
+        let result = f(&mut writer);
+        self.store(writer.bits);
+        trace!(Write, self, writer.bits);
+        result
+    }
+}
+
+impl<REG: Writable + MockSpec> Reg<REG>
+where
+    REG::Ux: MockRaw,
+{
+    /// Writes 0 to a `Writable` register.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe to use with registers which don't allow to write 0.
+    #[inline(always)]
+    pub unsafe fn write_with_zero<F>(&self, f: F) -> REG::Ux
+    where
+        F: FnOnce(&mut W<REG>) -> &mut W<REG>,
+    {
+        let value = f(&mut W {
+            bits: REG::Ux::ZERO,
+            _reg: marker::PhantomData,
+        })
+        .bits;
+        self.store(value);
+        trace!(WriteWithZero, self, value);
+        value
+    }
+
+    /// Writes 0 to a `Writable` register and produces a value.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe to use with registers which don't allow to write 0.
+    #[inline(always)]
+    pub unsafe fn from_write_with_zero<F, T>(&self, f: F) -> T
+    where
+        F: FnOnce(&mut W<REG>) -> T,
+    {
+        let mut writer = W {
+            bits: REG::Ux::ZERO,
+            _reg: marker::PhantomData,
+        };
+        let result = f(&mut writer);
+        self.store(writer.bits);
+        trace!(WriteWithZero, self, writer.bits);
+        result
+    }
+}
+
+impl<REG: Readable + Writable + MockSpec> Reg<REG>
+where
+    REG::Ux: MockRaw,
+{
+    /// Modifies the contents of the register by reading and then writing it.
+    #[inline(always)]
+    pub fn modify<F>(&self, f: F) -> REG::Ux
+    where
+        for<'w> F: FnOnce(&R<REG>, &'w mut W<REG>) -> &'w mut W<REG>,
+    {
+        let bits = self.load();
+        let value = f(
+            &R {
+                bits,
+                _reg: marker::PhantomData,
+            },
+            &mut W {
+                bits: bits & !REG::ONE_TO_MODIFY_FIELDS_BITMAP | REG::ZERO_TO_MODIFY_FIELDS_BITMAP,
+                _reg: marker::PhantomData,
+            },
+        )
+        .bits;
+        self.store(value);
+        trace!(Modify, self, value);
+        value
+    }
+
+    /// Modifies the contents of the register by reading and then writing it
+    /// and produces a value.
+    #[inline(always)]
+    pub fn from_modify<F, T>(&self, f: F) -> T
+    where
+        for<'w> F: FnOnce(&R<REG>, &'w mut W<REG>) -> T,
+    {
+        let bits = self.load();
+        let mut writer = W {
+            bits: bits & !REG::ONE_TO_MODIFY_FIELDS_BITMAP | REG::ZERO_TO_MODIFY_FIELDS_BITMAP,
+            _reg: marker::PhantomData,
+        };
+        let result = f(
+            &R {
+                bits,
+                _reg: marker::PhantomData,
+            },
+            &mut writer,
+        );
+        self.store(writer.bits);
+        trace!(Modify, self, writer.bits);
+        result
+    }
+
+    /// Set high every bit in the register that was set in the write proxy.
+    ///
+    /// # Safety
+    ///
+    /// The resultant bit pattern may not be valid for the register.
+    #[inline(always)]
+    pub unsafe fn set_bits<F>(&self, f: F)
+    where
+        F: FnOnce(&mut W<REG>) -> &mut W<REG>,
+    {
+        let bits = f(&mut W {
+            bits: REG::Ux::ZERO,
+            _reg: marker::PhantomData,
+        })
+        .bits;
+        self.update(|value| value | bits);
+        trace!(SetBits, self, bits);
+    }
+
+    /// Clear every bit in the register that was cleared in the write proxy.
+    ///
+    /// # Safety
+    ///
+    /// The resultant bit pattern may not be valid for the register.
+    #[inline(always)]
+    pub unsafe fn clear_bits<F>(&self, f: F)
+    where
+        F: FnOnce(&mut W<REG>) -> &mut W<REG>,
+    {
+        let bits = f(&mut W {
+            bits: !REG::Ux::ZERO,
+            _reg: marker::PhantomData,
+        })
+        .bits;
+        self.update(|value| value & bits);
+        trace!(ClearBits, self, bits);
+    }
+
+    /// Toggle every bit in the register that was set in the write proxy.
+    ///
+    /// # Safety
+    ///
+    /// The resultant bit pattern may not be valid for the register.
+    #[inline(always)]
+    pub unsafe fn toggle_bits<F>(&self, f: F)
+    where
+        F: FnOnce(&mut W<REG>) -> &mut W<REG>,
+    {
+        let bits = f(&mut W {
+            bits: REG::Ux::ZERO,
+            _reg: marker::PhantomData,
+        })
+        .bits;
+        self.update(|value| value & !bits | !value & bits);
+        trace!(ToggleBits, self, bits);
+    }
+}
+
+impl<REG: Readable + MockSpec> core::fmt::Debug for crate::generic::Reg<REG>
+where
+    REG::Ux: MockRaw,
+    R<REG>: core::fmt::Debug,
+{
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        core::fmt::Debug::fmt(&self.read(), f)
+    }
+}
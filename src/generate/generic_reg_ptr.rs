@@ -0,0 +1,454 @@
+/// Peripheral with register block accessed through raw pointer
+pub trait PeripheralBlock: PeripheralSpec
+where
+    Self::RB: 'static,
+{
+    /// Register block at peripheral address
+    const BLOCK: &'static Self::RB;
+}
+
+impl<PER: PeripheralBlock> Periph<PER>
+where
+    PER::RB: 'static,
+{
+    ///Pointer to the register block
+    ///
+    ///The register block is a pointer itself, so this points to the constant
+    ///[`PeripheralBlock::BLOCK`], not to the registers at [`PeripheralSpec::ADDRESS`].
+    pub const PTR: *const PER::RB = PER::BLOCK;
+
+    ///Return the pointer to the register block
+    ///
+    ///See [`Periph::PTR`] about what it points to.
+    #[inline(always)]
+    pub const fn ptr() -> *const PER::RB {
+        Self::PTR
+    }
+}
+
+impl<PER: PeripheralBlock> core::ops::Deref for Periph<PER>
+where
+    PER::RB: 'static,
+{
+    type Target = PER::RB;
+
+    #[inline(always)]
+    fn deref(&self) -> &Self::Target {
+        PER::BLOCK
+    }
+}
+
+/// Register block which is a pointer to its registers
+pub trait PointerBlock: Copy {
+    /// Creates register block from its base address.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must point to the registers of this block.
+    unsafe fn from_ptr(ptr: *mut u8) -> Self;
+    /// Returns base address of the register block.
+    fn as_ptr(&self) -> *mut u8;
+}
+
+/// Peripheral accessor with register block at address known only at runtime
+///
+/// Dereferences to the same register block as [`Periph`].
+pub struct DynPeriph<PER: PeripheralSpec> {
+    block: PER::RB,
+}
+
+unsafe impl<PER: PeripheralSpec> Send for DynPeriph<PER> {}
+
+impl<PER: PeripheralSpec> core::fmt::Debug for DynPeriph<PER>
+where
+    PER::RB: PointerBlock,
+{
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        f.debug_struct(PER::NAME)
+            .field("ptr", &self.block.as_ptr())
+            .finish()
+    }
+}
+
+impl<PER: PeripheralSpec> Periph<PER>
+where
+    PER::RB: PointerBlock,
+{
+    /// Creates accessor of peripheral which registers are mapped at `ptr`
+    /// instead of `PeripheralSpec::ADDRESS`, e.g. with `mmap` in userspace.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must point to the registers of this peripheral and stay valid while
+    /// the accessor is used. Same rules as for `steal` apply.
+    #[inline(always)]
+    pub unsafe fn from_ptr(ptr: *mut u8) -> DynPeriph<PER> {
+        DynPeriph {
+            block: PER::RB::from_ptr(ptr),
+        }
+    }
+}
+
+impl<PER: PeripheralSpec> DynPeriph<PER>
+where
+    PER::RB: PointerBlock,
+{
+    ///Return the base address of the register block
+    #[inline(always)]
+    pub fn as_ptr(&self) -> *mut u8 {
+        self.block.as_ptr()
+    }
+}
+
+impl<PER: PeripheralSpec> core::ops::Deref for DynPeriph<PER> {
+    type Target = PER::RB;
+
+    #[inline(always)]
+    fn deref(&self) -> &Self::Target {
+        &self.block
+    }
+}
+
+/// This structure provides volatile access to register through raw pointer.
+///
+/// Unlike reference to `VolatileCell` it never creates Rust references to device memory.
+pub struct Reg<REG: RegisterSpec> {
+    ptr: *mut REG::Ux,
+    _marker: marker::PhantomData<REG>,
+}
+
+impl<REG: RegisterSpec> Clone for Reg<REG> {
+    #[inline(always)]
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<REG: RegisterSpec> Copy for Reg<REG> {}
+
+unsafe impl<REG: RegisterSpec> Send for Reg<REG> where REG::Ux: Send {}
+
+impl<REG: RegisterSpec> Reg<REG> {
+    /// Creates register accessor from its address.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must point to the register of `REG` type.
+    #[inline(always)]
+    pub const unsafe fn from_ptr(ptr: *mut REG::Ux) -> Self {
+        Self {
+            ptr,
+            _marker: marker::PhantomData,
+        }
+    }
+
+    /// Returns the underlying memory address of register.
+    ///
+    /// ```ignore
+    /// let reg_ptr = periph.reg.as_ptr();
+    /// ```
+    #[inline(always)]
+    pub const fn as_ptr(&self) -> *mut REG::Ux {
+        self.ptr
+    }
+
+    #[allow(dead_code)]
+    #[inline(always)]
+    pub(crate) fn address(&self) -> usize {
+        self.ptr as usize
+    }
+
+    #[inline(always)]
+    fn get(&self) -> REG::Ux {
+        unsafe { self.ptr.read_volatile() }
+    }
+
+    #[inline(always)]
+    fn set(&self, value: REG::Ux) {
+        unsafe { self.ptr.write_volatile(value) }
+    }
+}
+
+impl<REG: Readable> Reg<REG> {
+    /// Reads the contents of a `Readable` register.
+    ///
+    /// You can read the raw contents of a register by using `bits`:
+    /// ```ignore
+    /// let bits = periph.reg.read().bits();
+    /// ```
+    /// or get the content of a particular field of a register:
+    /// ```ignore
+    /// let reader = periph.reg.read();
+    /// let bits = reader.field1().bits();
+    /// let flag = reader.field2().bit_is_set();
+    /// ```
+    #[inline(always)]
+    pub fn read(&self) -> R<REG> {
+        let bits = self.get();
+        trace!(Read, self, bits);
+        R {
+            bits,
+            _reg: marker::PhantomData,
+        }
+    }
+}
+
+impl<REG: Resettable + Writable> Reg<REG> {
+    /// Writes the reset value to `Writable` register.
+    ///
+    /// Resets the register to its initial state.
+    #[inline(always)]
+    pub fn reset(&self) {
+        self.set(REG::RESET_VALUE);
+        trace!(Write, self, REG::RESET_VALUE);
+    }
+
+    /// Writes bits to a `Writable` register.
+    ///
+    /// You can write raw bits into a register:
+    /// ```ignore
+    /// periph.reg.write(|w| unsafe { w.bits(rawbits) });
+    /// ```
+    /// or write only the fields you need:
+    /// ```ignore
+    /// periph.reg.write(|w| w
+    ///     .field1().bits(newfield1bits)
+    ///     .field2().set_bit()
+    ///     .field3().variant(VARIANT)
+    /// );
+    /// ```
+    /// or an alternative way of saying the same:
+    /// ```ignore
+    /// periph.reg.write(|w| {
+    ///     w.field1().bits(newfield1bits);
+    ///     w.field2().set_bit();
+    ///     w.field3().variant(VARIANT)
+    /// });
+    /// ```
+    /// In the latter case, other fields will be set to their reset value.
+    #[inline(always)]
+    pub fn write<F>(&self, f: F) -> REG::Ux
+    where
+        F: FnOnce(&mut W<REG>) -> &mut W<REG>,
+    {
+        let value = f(&mut W {
+            bits: REG::RESET_VALUE & !REG::ONE_TO_MODIFY_FIELDS_BITMAP
+                | REG::ZERO_TO_MODIFY_FIELDS_BITMAP,
+            _reg: marker::PhantomData,
+        })
+        .bits;
+        self.set(value);
+        trace!(Write, self, value);
+        value
+    }
+
+    /// Writes bits to a `Writable` register and produce a value.
+    ///
+    /// You can write raw bits into a register:
+    /// ```ignore
+    /// periph.reg.write_and(|w| unsafe { w.bits(rawbits); });
+    /// ```
+    /// or write only the fields you need:
+    /// ```ignore
+    /// periph.reg.write_and(|w| {
+    ///     w.field1().bits(newfield1bits)
+    ///         .field2().set_bit()
+    ///         .field3().variant(VARIANT);
+    /// });
+    /// ```
+    /// or an alternative way of saying the same:
+    /// ```ignore
+    /// periph.reg.write_and(|w| {
+    ///     w.field1().bits(newfield1bits);
+    ///     w.field2().set_bit();
+    ///     w.field3().variant(VARIANT);
+    /// });
+    /// ```
+    /// In the latter case, other fields will be set to their reset value.
+    ///
+    /// Values can be returned from the closure:
+    /// ```ignore
+    /// let state = periph.reg.write_and(|w| State::set(w.field1()));
+    /// ```
+    #[inline(always)]
+    pub fn from_write<F, T>(&self, f: F) -> T
+    where
+        F: FnOnce(&mut W<REG>) -> T,
+    {
+        let mut writer = W {
+            bits: REG::RESET_VALUE & !REG::ONE_TO_MODIFY_FIELDS_BITMAP
+                | REG::ZERO_TO_MODIFY_FIELDS_BITMAP,
+            _reg: marker::PhantomData,
+        };
+        let result = f(&mut writer);
+
+        self.set(writer.bits);
+        trace!(Write, self, writer.bits);
+
+        result
+    }
+}
+
+impl<REG: Writable> Reg<REG> {
+    /// Writes 0 to a `Writable` register.
+    ///
+    /// Similar to `write`, but unused bits will contain 0.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe to use with registers which don't allow to write 0.
+    #[inline(always)]
+    pub unsafe fn write_with_zero<F>(&self, f: F) -> REG::Ux
+    where
+        F: FnOnce(&mut W<REG>) -> &mut W<REG>,
+    {
+        let value = f(&mut W {
+            bits: REG::Ux::ZERO,
+            _reg: marker::PhantomData,
+        })
+        .bits;
+        self.set(value);
+        trace!(WriteWithZero, self, value);
+        value
+    }
+
+    /// Writes 0 to a `Writable` register and produces a value.
+    ///
+    /// Similar to `write`, but unused bits will contain 0.
+    ///
+    /// # Safety
+    ///
+    /// Unsafe to use with registers which don't allow to write 0.
+    #[inline(always)]
+    pub unsafe fn from_write_with_zero<F, T>(&self, f: F) -> T
+    where
+        F: FnOnce(&mut W<REG>) -> T,
+    {
+        let mut writer = W {
+            bits: REG::Ux::ZERO,
+            _reg: marker::PhantomData,
+        };
+
+        let result = f(&mut writer);
+
+        self.set(writer.bits);
+        trace!(WriteWithZero, self, writer.bits);
+
+        result
+    }
+}
+
+impl<REG: Readable + Writable> Reg<REG> {
+    /// Modifies the contents of the register by reading and then writing it.
+    ///
+    /// E.g. to do a read-modify-write sequence to change parts of a register:
+    /// ```ignore
+    /// periph.reg.modify(|r, w| unsafe { w.bits(
+    ///    r.bits() | 3
+    /// ) });
+    /// ```
+    /// or
+    /// ```ignore
+    /// periph.reg.modify(|_, w| w
+    ///     .field1().bits(newfield1bits)
+    ///     .field2().set_bit()
+    ///     .field3().variant(VARIANT)
+    /// );
+    /// ```
+    /// or an alternative way of saying the same:
+    /// ```ignore
+    /// periph.reg.modify(|_, w| {
+    ///     w.field1().bits(newfield1bits);
+    ///     w.field2().set_bit();
+    ///     w.field3().variant(VARIANT)
+    /// });
+    /// ```
+    /// Other fields will have the value they had before the call to `modify`.
+    #[inline(always)]
+    pub fn modify<F>(&self, f: F) -> REG::Ux
+    where
+        for<'w> F: FnOnce(&R<REG>, &'w mut W<REG>) -> &'w mut W<REG>,
+    {
+        let bits = self.get();
+        let value = f(
+            &R {
+                bits,
+                _reg: marker::PhantomData,
+            },
+            &mut W {
+                bits: bits & !REG::ONE_TO_MODIFY_FIELDS_BITMAP | REG::ZERO_TO_MODIFY_FIELDS_BITMAP,
+                _reg: marker::PhantomData,
+            },
+        )
+        .bits;
+        self.set(value);
+        trace!(Modify, self, value);
+        value
+    }
+
+    /// Modifies the contents of the register by reading and then writing it
+    /// and produces a value.
+    ///
+    /// E.g. to do a read-modify-write sequence to change parts of a register:
+    /// ```ignore
+    /// let bits = periph.reg.modify(|r, w| {
+    ///     let new_bits = r.bits() | 3;
+    ///     unsafe {
+    ///         w.bits(new_bits);
+    ///     }
+    ///
+    ///     new_bits
+    /// });
+    /// ```
+    /// or
+    /// ```ignore
+    /// periph.reg.modify(|_, w| {
+    ///     w.field1().bits(newfield1bits)
+    ///         .field2().set_bit()
+    ///         .field3().variant(VARIANT);
+    /// });
+    /// ```
+    /// or an alternative way of saying the same:
+    /// ```ignore
+    /// periph.reg.modify(|_, w| {
+    ///     w.field1().bits(newfield1bits);
+    ///     w.field2().set_bit();
+    ///     w.field3().variant(VARIANT);
+    /// });
+    /// ```
+    /// Other fields will have the value they had before the call to `modify`.
+    #[inline(always)]
+    pub fn from_modify<F, T>(&self, f: F) -> T
+    where
+        for<'w> F: FnOnce(&R<REG>, &'w mut W<REG>) -> T,
+    {
+        let bits = self.get();
+
+        let mut writer = W {
+            bits: bits & !REG::ONE_TO_MODIFY_FIELDS_BITMAP | REG::ZERO_TO_MODIFY_FIELDS_BITMAP,
+            _reg: marker::PhantomData,
+        };
+
+        let result = f(
+            &R {
+                bits,
+                _reg: marker::PhantomData,
+            },
+            &mut writer,
+        );
+
+        self.set(writer.bits);
+        trace!(Modify, self, writer.bits);
+
+        result
+    }
+}
+
+impl<REG: Readable> core::fmt::Debug for crate::generic::Reg<REG>
+where
+    R<REG>: core::fmt::Debug,
+{
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        core::fmt::Debug::fmt(&self.read(), f)
+    }
+}
@@ -1,3 +1,71 @@
+impl<PER: PeripheralSpec> Periph<PER> {
+    ///Pointer to the register block
+    pub const PTR: *const PER::RB = PER::ADDRESS as *const _;
+
+    ///Return the pointer to the register block
+    #[inline(always)]
+    pub const fn ptr() -> *const PER::RB {
+        Self::PTR
+    }
+}
+
+impl<PER: PeripheralSpec> core::ops::Deref for Periph<PER> {
+    type Target = PER::RB;
+
+    #[inline(always)]
+    fn deref(&self) -> &Self::Target {
+        unsafe { &*Self::PTR }
+    }
+}
+
+/// Peripheral accessor with register block at address known only at runtime
+///
+/// Dereferences to the same register block as [`Periph`].
+pub struct DynPeriph<PER: PeripheralSpec> {
+    ptr: *const PER::RB,
+}
+
+unsafe impl<PER: PeripheralSpec> Send for DynPeriph<PER> {}
+
+impl<PER: PeripheralSpec> core::fmt::Debug for DynPeriph<PER> {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        f.debug_struct(PER::NAME).field("ptr", &self.ptr).finish()
+    }
+}
+
+impl<PER: PeripheralSpec> Periph<PER> {
+    /// Creates accessor of peripheral which registers are mapped at `ptr`
+    /// instead of `PeripheralSpec::ADDRESS`, e.g. with `mmap` in userspace.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must point to the registers of this peripheral and stay valid while
+    /// the accessor is used. Same rules as for `steal` apply.
+    #[inline(always)]
+    pub const unsafe fn from_ptr(ptr: *mut u8) -> DynPeriph<PER> {
+        DynPeriph {
+            ptr: ptr as *const _,
+        }
+    }
+}
+
+impl<PER: PeripheralSpec> DynPeriph<PER> {
+    ///Return the base address of the register block
+    #[inline(always)]
+    pub const fn as_ptr(&self) -> *mut u8 {
+        self.ptr as *mut _
+    }
+}
+
+impl<PER: PeripheralSpec> core::ops::Deref for DynPeriph<PER> {
+    type Target = PER::RB;
+
+    #[inline(always)]
+    fn deref(&self) -> &Self::Target {
+        unsafe { &*self.ptr }
+    }
+}
+
 /// This structure provides volatile access to registers.
 #[repr(transparent)]
 pub struct Reg<REG: RegisterSpec> {
@@ -17,6 +85,12 @@ impl<REG: RegisterSpec> Reg<REG> {
     pub fn as_ptr(&self) -> *mut REG::Ux {
         self.register.as_ptr()
     }
+
+    #[allow(dead_code)]
+    #[inline(always)]
+    pub(crate) fn address(&self) -> usize {
+        self.register.as_ptr() as usize
+    }
 }
 
 impl<REG: Readable> Reg<REG> {
@@ -34,8 +108,10 @@ impl<REG: Readable> Reg<REG> {
     /// ```
     #[inline(always)]
     pub fn read(&self) -> R<REG> {
+        let bits = self.register.get();
+        trace!(Read, self, bits);
         R {
-            bits: self.register.get(),
+            bits,
             _reg: marker::PhantomData,
         }
     }
@@ -47,7 +123,8 @@ impl<REG: Resettable + Writable> Reg<REG> {
     /// Resets the register to its initial state.
     #[inline(always)]
     pub fn reset(&self) {
-        self.register.set(REG::RESET_VALUE)
+        self.register.set(REG::RESET_VALUE);
+        trace!(Write, self, REG::RESET_VALUE);
     }
 
     /// Writes bits to a `Writable` register.
@@ -85,6 +162,7 @@ impl<REG: Resettable + Writable> Reg<REG> {
         })
         .bits;
         self.register.set(value);
+        trace!(Write, self, value);
         value
     }
 
@@ -129,6 +207,7 @@ impl<REG: Resettable + Writable> Reg<REG> {
         let result = f(&mut writer);
 
         self.register.set(writer.bits);
+        trace!(Write, self, writer.bits);
 
         result
     }
@@ -153,6 +232,7 @@ impl<REG: Writable> Reg<REG> {
         })
         .bits;
         self.register.set(value);
+        trace!(WriteWithZero, self, value);
         value
     }
 
@@ -176,6 +256,7 @@ impl<REG: Writable> Reg<REG> {
         let result = f(&mut writer);
 
         self.register.set(writer.bits);
+        trace!(WriteWithZero, self, writer.bits);
 
         result
     }
@@ -225,6 +306,7 @@ impl<REG: Readable + Writable> Reg<REG> {
         )
         .bits;
         self.register.set(value);
+        trace!(Modify, self, value);
         value
     }
 
@@ -280,6 +362,7 @@ impl<REG: Readable + Writable> Reg<REG> {
         );
 
         self.register.set(writer.bits);
+        trace!(Modify, self, writer.bits);
 
         result
     }
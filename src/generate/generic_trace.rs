@@ -0,0 +1,70 @@
+use core::cell::Cell;
+use critical_section::Mutex;
+
+/// Kind of register access
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Access {
+    /// `read`
+    Read,
+    /// `write`, `from_write` or `reset`
+    Write,
+    /// `write_with_zero` or `from_write_with_zero`
+    WriteWithZero,
+    /// `modify` or `from_modify`
+    Modify,
+    /// `set_bits`
+    SetBits,
+    /// `clear_bits`
+    ClearBits,
+    /// `toggle_bits`
+    ToggleBits,
+}
+
+/// Register access report
+#[derive(Clone, Copy, Debug)]
+pub struct Event {
+    /// Kind of access
+    pub access: Access,
+    /// Address of the register
+    pub address: usize,
+    /// Name of the peripheral containing the register, if known
+    pub peripheral: Option<&'static str>,
+    /// Name of the register
+    pub register: &'static str,
+    /// Value read or written, bit mask for atomic operations
+    pub value: u64,
+}
+
+/// Receiver of register access reports
+pub trait Sink: Sync {
+    /// Called after each register access
+    fn event(&self, event: &Event);
+}
+
+static SINK: Mutex<Cell<Option<&'static dyn Sink>>> = Mutex::new(Cell::new(None));
+
+/// Installs sink which receives all following register accesses
+pub fn set_sink(sink: &'static dyn Sink) {
+    critical_section::with(|cs| SINK.borrow(cs).set(Some(sink)));
+}
+
+/// Removes installed sink
+pub fn remove_sink() {
+    critical_section::with(|cs| SINK.borrow(cs).set(None));
+}
+
+#[doc(hidden)]
+#[inline]
+pub fn record<REG: RegisterSpec>(access: Access, reg: &Reg<REG>, value: REG::Ux) {
+    // Sink is called outside of critical section so it can access registers itself
+    if let Some(sink) = critical_section::with(|cs| SINK.borrow(cs).get()) {
+        let address = reg.address();
+        sink.event(&Event {
+            access,
+            address,
+            peripheral: crate::peripheral_at(address),
+            register: REG::NAME,
+            value: value.into(),
+        });
+    }
+}
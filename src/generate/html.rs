@@ -0,0 +1,316 @@
+//! Static HTML reference of the registers of a device
+//!
+//! One page per device with an index of the peripherals, the register map of each peripheral
+//! instance and the fields and enumerated values of each register. Registers are laid out like
+//! in the [`RegisterMap`], and get the anchor
+//! `PERIPHERAL:CLUSTER.REGISTER`, with the names of the first elements of arrays, which the
+//! `html_url` links in the generated docs point to.
+
+use std::fmt::Write;
+
+use anyhow::Result;
+use proc_macro2::Span;
+use svd_parser::expand::{BlockPath, Index, RegisterPath};
+
+use crate::config::Config;
+use crate::generate::filter;
+use crate::generate::register_map::{MappedRegister, RegisterMap};
+use crate::svd::{self, MaybeArray, Name, Peripheral, PeripheralInfo, Usage};
+use crate::util::{self, escape_html, ident};
+
+const STYLE: &str = "body { font-family: sans-serif; margin: 2em; } \
+    table { border-collapse: collapse; margin: 1em 0; } \
+    th, td { border: 1px solid #ccc; padding: 0.2em 0.5em; text-align: left; vertical-align: top; } \
+    th { background: #eee; } \
+    tr.reserved td { color: #888; font-style: italic; }";
+
+/// Reference page of `device`
+pub fn render(device: &svd::Device, config: &Config) -> Result<String> {
+    let map = RegisterMap::new(device, config)?;
+    let filtered;
+    let device = if filter::is_active(config) {
+        filtered = filter::filter(device, config)?;
+        &filtered
+    } else {
+        device
+    };
+    let span = Span::call_site();
+    let mut peripherals = Vec::new();
+    for p in &device.peripherals {
+        let module = ident(
+            &util::name_of(p, config.ignore_groups),
+            config,
+            "peripheral_mod",
+            span,
+        );
+        let instances = match p {
+            Peripheral::Single(info) => vec![info.clone()],
+            Peripheral::Array(info, dim) => svd::peripheral::expand(info, dim).collect(),
+        };
+        for info in instances {
+            let ty = ident(&info.name, config, "peripheral", span);
+            peripherals.push((info, ty.to_string(), module.to_string()));
+        }
+    }
+    peripherals.sort_by_key(|(info, ..)| info.base_address);
+
+    let mut out = String::new();
+    writeln!(out, "<!DOCTYPE html>")?;
+    writeln!(out, r#"<html lang="en">"#)?;
+    writeln!(out, "<head>")?;
+    writeln!(out, r#"<meta charset="utf-8">"#)?;
+    writeln!(
+        out,
+        "<title>{} registers</title>",
+        escape_html(&device.name)
+    )?;
+    writeln!(out, "<style>{STYLE}</style>")?;
+    writeln!(out, "</head>")?;
+    writeln!(out, "<body>")?;
+    writeln!(out, "<h1>{}</h1>", escape_html(&device.name))?;
+    if !device.description.is_empty() {
+        writeln!(out, "<p>{}</p>", text(Some(&device.description)))?;
+    }
+    writeln!(
+        out,
+        "<p>Generated using svd2rust v{}</p>",
+        env!("CARGO_PKG_VERSION")
+    )?;
+
+    writeln!(out, "<h2>Peripherals</h2>")?;
+    writeln!(out, "<table>")?;
+    writeln!(
+        out,
+        "<tr><th>Name</th><th>Address</th><th>Description</th></tr>"
+    )?;
+    for (p, ..) in &peripherals {
+        let mut description = text(p.description.as_deref());
+        if let Some(base) = p.derived_from.as_deref() {
+            if !description.is_empty() {
+                description.push(' ');
+            }
+            write!(
+                description,
+                r##"(derived from <a href="#{0}">{0}</a>)"##,
+                escape_html(base)
+            )?;
+        }
+        write!(
+            out,
+            r##"<tr><td><a href="#{0}">{0}</a></td><td><code>{1:#010x}</code></td><td>{description}"##,
+            escape_html(&p.name),
+            p.base_address + config.base_address_shift,
+        )?;
+        writeln!(out, "</td></tr>")?;
+    }
+    writeln!(out, "</table>")?;
+
+    for (p, ty, module) in &peripherals {
+        let registers: Vec<_> = map
+            .registers
+            .iter()
+            .filter(|r| r.peripheral == p.name)
+            .collect();
+        peripheral(&mut out, p, ty, module, &registers, config)?;
+    }
+    writeln!(out, "</body>")?;
+    writeln!(out, "</html>")?;
+    Ok(out)
+}
+
+/// Register map of a peripheral instance followed by its registers
+fn peripheral(
+    out: &mut String,
+    p: &PeripheralInfo,
+    ty: &str,
+    module: &str,
+    registers: &[&MappedRegister],
+    config: &Config,
+) -> Result<()> {
+    let name = escape_html(&p.name);
+    let base = p.base_address + config.base_address_shift;
+    writeln!(out, r#"<section id="{name}">"#)?;
+    writeln!(out, "<h2>{name}</h2>")?;
+    if p.description.is_some() {
+        writeln!(out, "<p>{}</p>", text(p.description.as_deref()))?;
+    }
+    writeln!(
+        out,
+        "<p>Base address <code>{base:#010x}</code>, Rust type <code>{}</code> in module <code>{}</code></p>",
+        escape_html(ty),
+        escape_html(module),
+    )?;
+    if let Some(dpath) = p.derived_from.as_deref() {
+        writeln!(
+            out,
+            r##"<p>Derived from <a href="#{0}">{0}</a></p>"##,
+            escape_html(dpath)
+        )?;
+    }
+    if registers.is_empty() {
+        writeln!(out, "</section>")?;
+        return Ok(());
+    }
+
+    writeln!(out, "<h3>Register map</h3>")?;
+    writeln!(out, "<table>")?;
+    writeln!(
+        out,
+        "<tr><th>Offset</th><th>Name</th><th>Size</th><th>Access</th><th>Reset</th><th>Description</th></tr>"
+    )?;
+    let mut end = base;
+    for r in registers {
+        let register = &r.register;
+        if register.address > end {
+            writeln!(
+                out,
+                r#"<tr class="reserved"><td><code>{:#06x}</code></td><td colspan="5">reserved, {} bytes</td></tr>"#,
+                end - base,
+                register.address - end,
+            )?;
+        }
+        let anchor = escape_html(&anchor(r));
+        writeln!(
+            out,
+            r##"<tr><td><code>{:#06x}</code></td><td><a href="#{anchor}">{}</a></td><td>{}</td><td>{}</td><td><code>{:#x}</code></td><td>{}</td></tr>"##,
+            register.address - base,
+            escape_html(&r.path[r.peripheral.len() + 1..]),
+            register.size,
+            register.access.as_str(),
+            register.reset_value,
+            text(register.description.as_deref()),
+        )?;
+        end = end.max(register.address + u64::from(register.size).div_ceil(8));
+    }
+    writeln!(out, "</table>")?;
+
+    for r in registers {
+        register(out, r, base)?;
+    }
+    writeln!(out, "</section>")?;
+    Ok(())
+}
+
+/// Section of a register with its fields and their enumerated values
+fn register(out: &mut String, mapped: &MappedRegister, base: u64) -> Result<()> {
+    let r = &mapped.register;
+    writeln!(out, r#"<section id="{}">"#, escape_html(&anchor(mapped)))?;
+    writeln!(out, "<h3>{}</h3>", escape_html(&mapped.path))?;
+    if r.description.is_some() {
+        writeln!(out, "<p>{}</p>", text(r.description.as_deref()))?;
+    }
+    write!(
+        out,
+        "<p>Address <code>{:#010x}</code>, offset <code>{:#x}</code>, {} bits, {}, reset value <code>{:#x}</code>",
+        r.address,
+        r.address - base,
+        r.size,
+        r.access.as_str(),
+        r.reset_value,
+    )?;
+    if r.reset_mask != u64::MAX >> (64 - r.size) {
+        write!(out, " with mask <code>{:#x}</code>", r.reset_mask)?;
+    }
+    writeln!(out, "</p>")?;
+    if r.fields.is_empty() {
+        writeln!(out, "</section>")?;
+        return Ok(());
+    }
+
+    writeln!(out, "<table>")?;
+    writeln!(
+        out,
+        "<tr><th>Bits</th><th>Name</th><th>Access</th><th>Description</th></tr>"
+    )?;
+    for f in r.fields.iter().rev() {
+        let bits = if f.bit_width == 1 {
+            f.bit_offset.to_string()
+        } else {
+            format!("{}:{}", f.bit_offset + f.bit_width - 1, f.bit_offset)
+        };
+        writeln!(
+            out,
+            "<tr><td>{bits}</td><td><code>{}</code></td><td>{}</td><td>{}</td></tr>",
+            escape_html(&f.name),
+            f.access.as_str(),
+            text(f.description.as_deref()),
+        )?;
+    }
+    writeln!(out, "</table>")?;
+
+    for f in &r.fields {
+        for e in &f.enums {
+            write!(out, "<h4><code>{}</code> values", escape_html(&f.name))?;
+            match e.usage {
+                Usage::Read if f.access.can_write() => write!(out, " on read")?,
+                Usage::Write if f.access.can_read() => write!(out, " on write")?,
+                _ => {}
+            }
+            writeln!(out, "</h4>")?;
+            writeln!(out, "<table>")?;
+            writeln!(
+                out,
+                "<tr><th>Value</th><th>Name</th><th>Description</th></tr>"
+            )?;
+            for v in &e.values {
+                let value = match v.value {
+                    Some(value) => format!("<code>{value:#x}</code>"),
+                    None => "other".to_string(),
+                };
+                writeln!(
+                    out,
+                    "<tr><td>{value}</td><td><code>{}</code></td><td>{}</td></tr>",
+                    escape_html(&v.name),
+                    text(v.description.as_deref()),
+                )?;
+            }
+            writeln!(out, "</table>")?;
+        }
+    }
+    writeln!(out, "</section>")?;
+    Ok(())
+}
+
+/// `PERIPHERAL:CLUSTER.REGISTER` anchor of a register
+fn anchor(r: &MappedRegister) -> String {
+    format!("{}:{}", r.peripheral, &r.path[r.peripheral.len() + 1..])
+}
+
+/// Anchor of the register rendered for `register` in the reference, the first element of arrays
+pub(crate) fn register_anchor(
+    register: &svd::Register,
+    rpath: &RegisterPath,
+    index: &Index,
+) -> String {
+    let block = &rpath.block;
+    let mut path = BlockPath::new(&block.peripheral);
+    let mut anchor = match index.peripherals.get(&path) {
+        Some(p) => first_name(p),
+        None => block.peripheral.clone(),
+    };
+    anchor.push(':');
+    for c in &block.path {
+        path = path.new_cluster(c);
+        match index.clusters.get(&path) {
+            Some(c) => anchor.push_str(&first_name(c)),
+            None => anchor.push_str(c),
+        }
+        anchor.push('.');
+    }
+    anchor.push_str(&first_name(register));
+    anchor
+}
+
+fn first_name<T: Name>(item: &MaybeArray<T>) -> String {
+    match item {
+        MaybeArray::Single(info) => info.name().to_string(),
+        MaybeArray::Array(info, dim) => svd::array::names(info, dim)
+            .next()
+            .unwrap_or_else(|| info.name().to_string()),
+    }
+}
+
+fn text(s: Option<&str>) -> String {
+    s.map(|s| escape_html(&util::respace(s)))
+        .unwrap_or_default()
+}
@@ -0,0 +1,455 @@
+//! Identifiers generated for the device and their collisions
+//!
+//! Identifiers which share a scope, like the field accessors of a register or the variants of
+//! an enum, are computed together before they are rendered. Names which give the same
+//! identifier are reported with their SVD paths, or with `--disambiguate-idents` all but the
+//! first of them get a number appended.
+
+use std::borrow::Cow;
+use std::collections::{hash_map::Entry, HashMap, HashSet};
+use std::fmt::Display;
+
+use anyhow::{anyhow, bail, Result};
+use proc_macro2::{Ident, Span};
+
+use crate::config::{Config, IdentFormat};
+use crate::svd::{self, Device, EnumeratedValues, Field, RegisterCluster};
+use crate::util::{DimSuffix, FullName};
+
+/// Methods of `R` and `W` next to the field accessors
+const FIELD_ACCESSOR_RESERVED: &[&str] = &["bits", "set"];
+
+/// Methods of field readers and writers next to the enumerated value accessors
+const ENUM_VALUE_ACCESSOR_RESERVED: &[&str] = &[
+    "bit",
+    "bits",
+    "clear_bit",
+    "set",
+    "set_bit",
+    "variant",
+    "offset",
+];
+
+/// Identifiers of one type generated in one scope
+///
+/// Names clashing with methods `svd2rust` generates itself get a `_` appended. Names
+/// disambiguated by `--disambiguate-idents` get `_1`, `_2`, ... appended before formatting.
+pub struct Scope {
+    format: &'static str,
+    reserved: &'static [&'static str],
+    numbers: HashMap<String, usize>,
+}
+
+impl Scope {
+    /// Computes identifiers of `format` for `(name, path)` pairs, in order
+    fn new(
+        format: &'static str,
+        reserved: &'static [&'static str],
+        names: &[(String, String)],
+        config: &Config,
+    ) -> Result<Self> {
+        let mut scope = Self {
+            format,
+            reserved,
+            numbers: HashMap::new(),
+        };
+        let mut used: HashSet<String> = names.iter().map(|(n, _)| scope.base(n, config)).collect();
+        let mut first: HashMap<String, (&str, &str)> = HashMap::new();
+        for (name, path) in names {
+            let ident = scope.base(name, config);
+            let (other, other_path) = *first.entry(ident.clone()).or_insert((name, path));
+            if other == name || scope.numbers.contains_key(name) {
+                continue;
+            }
+            if !config.disambiguate_idents {
+                bail!("`{other_path}` and `{path}` both give the {format} `{ident}`");
+            }
+            let (number, ident) = (1..)
+                .map(|n| (n, scope.base(&format!("{name}_{n}"), config)))
+                .find(|(_, ident)| !used.contains(ident))
+                .unwrap();
+            used.insert(ident);
+            scope.numbers.insert(name.clone(), number);
+        }
+        Ok(scope)
+    }
+
+    fn base(&self, name: &str, config: &Config) -> String {
+        let ident = config
+            .ident_formats
+            .get(self.format)
+            .expect("Missing {format} entry")
+            .sanitize(name);
+        if self.reserved.contains(&ident.as_ref()) {
+            ident.into_owned() + "_"
+        } else {
+            ident.into_owned()
+        }
+    }
+
+    /// `name` with the number disambiguating it, if any
+    pub fn name<'a>(&self, name: &'a str) -> Cow<'a, str> {
+        match self.numbers.get(name) {
+            Some(n) => format!("{name}_{n}").into(),
+            None => name.into(),
+        }
+    }
+
+    /// Identifier of `name`, which must be one of the names of the scope
+    pub fn ident(&self, name: &str, config: &Config, span: Span) -> Ident {
+        Ident::new(&self.base(&self.name(name), config), span)
+    }
+}
+
+/// Accessors of `fields` in the `R` and `W` impls of the register at `rpath`
+///
+/// Contains the names of the fields without dim and the names of the elements of field arrays.
+pub fn field_accessors(fields: &[&Field], rpath: &impl Display, config: &Config) -> Result<Scope> {
+    let mut names = Vec::new();
+    for f in fields {
+        let path = format!("{rpath}.{}", f.name);
+        match f {
+            Field::Single(_) => names.push((f.name.remove_dim().into_owned(), path)),
+            Field::Array(info, de) => {
+                let name = de
+                    .dim_name
+                    .clone()
+                    .unwrap_or_else(|| f.name.remove_dim().into_owned());
+                names.push((name, path.clone()));
+                names.extend(svd::field::expand(info, de).map(|fi| (fi.name, path.clone())));
+            }
+        }
+    }
+    Scope::new("field_accessor", FIELD_ACCESSOR_RESERVED, &names, config)
+}
+
+/// Variants and accessors of the enum generated for `evs` of the field at `fpath`
+pub fn enum_values(
+    evs: &EnumeratedValues,
+    fpath: &impl Display,
+    config: &Config,
+) -> Result<(Scope, Scope)> {
+    let names: Vec<_> = evs
+        .values
+        .iter()
+        .filter(|ev| ev.name.to_lowercase() != "reserved")
+        .map(|ev| (ev.name.clone(), format!("{fpath}.{}", ev.name)))
+        .collect();
+    Ok((
+        Scope::new("enum_value", &[], &names, config)?,
+        Scope::new(
+            "enum_value_accessor",
+            ENUM_VALUE_ACCESSOR_RESERVED,
+            &names,
+            config,
+        )?,
+    ))
+}
+
+/// Reports all identifier collisions of register blocks, `R`/`W` impls and enums of `device`
+pub fn check_collisions(device: &Device, config: &Config) -> Result<()> {
+    let errors = collisions(device, config);
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        let errors: Vec<_> = errors.into_iter().map(|(_, e)| e).collect();
+        Err(anyhow!("Identifier collisions:\n{}", errors.join("\n")))
+    }
+}
+
+/// Identifier collisions of `device` with the paths of the scopes they are in
+pub(crate) fn collisions(device: &Device, config: &Config) -> Vec<(String, String)> {
+    let mut errors = Vec::new();
+    for p in &device.peripherals {
+        if let Some(registers) = p.registers.as_ref() {
+            check_scopes(registers, &p.name, config, &mut errors);
+        }
+    }
+    errors
+}
+
+fn check_scopes(
+    children: &[RegisterCluster],
+    path: &str,
+    config: &Config,
+    errors: &mut Vec<(String, String)>,
+) {
+    // register and cluster accessors are methods of the same register block
+    let mut accessors: HashMap<String, (String, String)> = HashMap::new();
+    for child in children {
+        let (name, format) = match child {
+            RegisterCluster::Register(r) => (r.fullname(config.ignore_groups), "register_accessor"),
+            RegisterCluster::Cluster(c) => (c.name.as_str().into(), "cluster_accessor"),
+        };
+        let name = name.remove_dim().into_owned();
+        let cpath = format!("{path}.{}", child.name());
+        let ident = config.ident_formats.get(format).unwrap().sanitize(&name);
+        match accessors.entry(ident.into_owned()) {
+            Entry::Occupied(e) if e.get().0 != name => errors.push((
+                path.to_string(),
+                format!(
+                    "`{}` and `{cpath}` both give the accessor `{}`",
+                    e.get().1,
+                    e.key()
+                ),
+            )),
+            Entry::Occupied(_) => {}
+            Entry::Vacant(e) => {
+                e.insert((name, cpath.clone()));
+            }
+        }
+
+        match child {
+            RegisterCluster::Cluster(c) => check_scopes(&c.children, &cpath, config, errors),
+            RegisterCluster::Register(r) if r.derived_from.is_none() => {
+                let fields: Vec<_> = r
+                    .fields()
+                    .filter(|f| f.name.to_lowercase() != "reserved")
+                    .collect();
+                if let Err(e) = field_accessors(&fields, &cpath, config) {
+                    errors.push((cpath.clone(), e.to_string()));
+                }
+                for f in fields.iter().filter(|f| f.derived_from.is_none()) {
+                    let fpath = format!("{cpath}.{}", f.name);
+                    for evs in f.enumerated_values.iter() {
+                        if evs.derived_from.is_none() {
+                            if let Err(e) = enum_values(evs, &fpath, config) {
+                                errors.push((fpath.clone(), e.to_string()));
+                            }
+                        }
+                    }
+                }
+            }
+            RegisterCluster::Register(_) => {}
+        }
+    }
+}
+
+/// Checks that the `ident_renames` rewrites don't give different elements of one scope the
+/// same identifier
+pub fn check_renames(device: &Device, config: &Config) -> Result<()> {
+    if config.ident_formats.values().all(|f| f.rename.is_empty()) {
+        return Ok(());
+    }
+
+    let peripherals: Vec<_> = device
+        .peripherals
+        .iter()
+        .map(|p| (p.name.as_str(), p.name.clone()))
+        .collect();
+    check(
+        config,
+        &[
+            "peripheral",
+            "peripheral_mod",
+            "peripheral_singleton",
+            "peripheral_spec",
+            "peripheral_feature",
+        ],
+        &peripherals,
+    )?;
+    let interrupts: Vec<_> = device
+        .peripherals
+        .iter()
+        .flat_map(|p| &p.interrupt)
+        .map(|i| (i.name.as_str(), i.name.clone()))
+        .collect();
+    check(config, &["interrupt"], &interrupts)?;
+
+    for p in &device.peripherals {
+        if let Some(registers) = p.registers.as_ref() {
+            check_block(registers, &p.name, config)?;
+        }
+    }
+    Ok(())
+}
+
+fn check_block(children: &[RegisterCluster], path: &str, config: &Config) -> Result<()> {
+    let mut registers = Vec::new();
+    let mut clusters = Vec::new();
+    for child in children {
+        match child {
+            RegisterCluster::Register(r) => {
+                let rpath = format!("{path}.{}", r.name);
+                let fields: Vec<_> = r
+                    .fields()
+                    .map(|f| (f.name.as_str(), format!("{rpath}.{}", f.name)))
+                    .collect();
+                check(
+                    config,
+                    &["field_reader", "field_writer", "field_accessor"],
+                    &fields,
+                )?;
+                for f in r.fields() {
+                    for evs in &f.enumerated_values {
+                        let values: Vec<_> = evs
+                            .values
+                            .iter()
+                            .map(|ev| (ev.name.as_str(), format!("{rpath}.{}.{}", f.name, ev.name)))
+                            .collect();
+                        check(config, &["enum_value", "enum_value_accessor"], &values)?;
+                    }
+                }
+                registers.push((r.name.as_str(), rpath));
+            }
+            RegisterCluster::Cluster(c) => {
+                let cpath = format!("{path}.{}", c.name);
+                check_block(&c.children, &cpath, config)?;
+                clusters.push((c.name.as_str(), cpath));
+            }
+        }
+    }
+    check(
+        config,
+        &[
+            "register",
+            "register_mod",
+            "register_accessor",
+            "register_spec",
+        ],
+        &registers,
+    )?;
+    check(
+        config,
+        &["cluster", "cluster_mod", "cluster_accessor"],
+        &clusters,
+    )
+}
+
+/// Fails when two `(name, path)` elements get the same identifier only because of renaming
+fn check(config: &Config, kinds: &[&str], elements: &[(&str, String)]) -> Result<()> {
+    for kind in kinds {
+        let Some(format) = config.ident_formats.get(*kind) else {
+            continue;
+        };
+        if format.rename.is_empty() {
+            continue;
+        }
+        let unrenamed = IdentFormat {
+            rename: Vec::new(),
+            ..format.clone()
+        };
+        let mut idents = HashMap::new();
+        for (name, path) in elements {
+            let name = name.remove_dim();
+            let ident = format.sanitize(&name).into_owned();
+            let original = unrenamed.sanitize(&name).into_owned();
+            match idents.entry(ident) {
+                Entry::Occupied(e) => {
+                    let (other_original, other_path): &(String, &String) = e.get();
+                    if *other_original != original {
+                        bail!(
+                            "Renaming {kind} `{other_path}` and `{path}` gives both the identifier `{}`",
+                            e.key()
+                        );
+                    }
+                }
+                Entry::Vacant(e) => {
+                    e.insert((original, path));
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+fn test_config(disambiguate_idents: bool) -> Config {
+    Config {
+        ident_formats: crate::config::IdentFormats::default_theme(),
+        disambiguate_idents,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn disambiguation_numbering() {
+    let names: Vec<_> = ["EN", "en", "EN_1", "En", "BITS"]
+        .iter()
+        .map(|n| (n.to_string(), format!("CR.{n}")))
+        .collect();
+    let err = Scope::new(
+        "field_accessor",
+        FIELD_ACCESSOR_RESERVED,
+        &names,
+        &test_config(false),
+    )
+    .err()
+    .unwrap();
+    assert_eq!(
+        err.to_string(),
+        "`CR.EN` and `CR.en` both give the field_accessor `en`"
+    );
+
+    let config = test_config(true);
+    let scope = Scope::new("field_accessor", FIELD_ACCESSOR_RESERVED, &names, &config).unwrap();
+    let idents: Vec<_> = names
+        .iter()
+        .map(|(n, _)| scope.ident(n, &config, Span::call_site()).to_string())
+        .collect();
+    // `en_1` is taken by `EN_1`, so the numbers continue after it
+    assert_eq!(idents, ["en", "en_2", "en_1", "en_3", "bits_"]);
+    assert_eq!(scope.name("en"), "en_2");
+    assert_eq!(scope.name("EN"), "EN");
+}
+
+#[cfg(test)]
+const TEST_SVD: &str = r#"<device><name>CHIP</name><size>32</size><peripherals>
+<peripheral><name>UART</name><baseAddress>0x40000000</baseAddress><registers>
+<register><name>CR1</name><addressOffset>0x0</addressOffset><fields>
+<field><name>TX_EN</name><bitOffset>0</bitOffset><bitWidth>1</bitWidth></field>
+<field><name>TxEn</name><bitOffset>1</bitOffset><bitWidth>1</bitWidth></field>
+</fields></register>
+<register><name>CR2</name><addressOffset>0x4</addressOffset><fields>
+<field><name>MODE</name><bitOffset>0</bitOffset><bitWidth>2</bitWidth><enumeratedValues>
+<enumeratedValue><name>FAST</name><value>0</value></enumeratedValue>
+<enumeratedValue><name>Fast</name><value>1</value></enumeratedValue>
+</enumeratedValues></field>
+</fields></register>
+<register><name>cr2</name><addressOffset>0x8</addressOffset></register>
+</registers></peripheral>
+</peripherals></device>"#;
+
+#[test]
+fn collisions_by_scope() {
+    let device = svd_parser::parse(TEST_SVD).unwrap();
+    let errors = collisions(&device, &test_config(false));
+    let scopes: Vec<_> = errors.iter().map(|(scope, _)| scope.as_str()).collect();
+    assert_eq!(scopes, ["UART.CR1", "UART.CR2.MODE", "UART"]);
+    assert_eq!(
+        errors[2].1,
+        "`UART.CR2` and `UART.cr2` both give the accessor `cr2`"
+    );
+    // field accessors and enum values get numbered, register accessors can't be
+    let errors = collisions(&device, &test_config(true));
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].0, "UART");
+}
+
+#[test]
+fn rename_collisions() {
+    use crate::config::IdentRename;
+
+    let mut device = svd_parser::parse(TEST_SVD).unwrap();
+    device.peripherals[0]
+        .registers
+        .as_mut()
+        .unwrap()
+        .retain(|r| r.name() != "cr2");
+    let config = |from: &str, to: &str| {
+        let mut config = test_config(false);
+        let renames = [(
+            "register_accessor".to_string(),
+            vec![IdentRename::new(from, to).unwrap()],
+        )];
+        config.ident_formats.add_renames(&renames.into()).unwrap();
+        config
+    };
+
+    assert!(check_renames(&device, &config("^CR(\\d)$", "CTRL$1")).is_ok());
+    let err = check_renames(&device, &config("^CR\\d$", "CTRL")).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Renaming register_accessor `UART.CR1` and `UART.CR2` gives both the identifier `ctrl`"
+    );
+}
@@ -1,8 +1,9 @@
 use std::collections::HashMap;
 use std::fmt::Write;
+use std::str::FromStr;
 
 use crate::config::RustEdition;
-use crate::svd::Peripheral;
+use crate::svd::{Interrupt, Peripheral};
 use proc_macro2::{Span, TokenStream};
 use quote::quote;
 
@@ -10,14 +11,10 @@ use crate::util::{self, ident};
 use crate::{Config, Target};
 use anyhow::Result;
 
-/// Generates code for `src/interrupt.rs`
-pub fn render(
-    target: Target,
-    peripherals: &[Peripheral],
-    device_x: &mut String,
-    config: &Config,
-) -> Result<TokenStream> {
-    let interrupts = peripherals
+/// Interrupts of the device by number, along with the group and the name of
+/// the peripheral which owns the enum variant
+fn collect(peripherals: &[Peripheral]) -> HashMap<u32, (&Interrupt, Option<String>, String)> {
+    peripherals
         .iter()
         .flat_map(|p| {
             p.interrupt.iter().map(move |i| {
@@ -32,9 +29,46 @@ pub fn render(
             })
         })
         .map(|i| (i.0.value, (i.0, i.1, i.2)))
-        .collect::<HashMap<_, _>>();
+        .collect()
+}
 
-    let mut interrupts = interrupts.into_values().collect::<Vec<_>>();
+/// Paths to the interrupt enum variants by interrupt number, each preceded by
+/// the `cfg` attributes the variant is gated with
+pub fn variants(peripherals: &[Peripheral], config: &Config) -> HashMap<u32, TokenStream> {
+    let span = Span::call_site();
+    let feature_format = config.ident_formats.get("peripheral_feature").unwrap();
+    let riscv_external = config.target == Target::RISCV && config.settings.riscv_config.is_some();
+    collect(peripherals)
+        .into_iter()
+        .map(|(value, (interrupt, group_name, p_name))| {
+            if riscv_external {
+                // see `riscv::render`, external interrupts keep their SVD names
+                let name = TokenStream::from_str(&interrupt.name).unwrap();
+                return (value, quote! { crate::interrupt::ExternalInterrupt::#name });
+            }
+            let mut feature_attribute = TokenStream::new();
+            if let Some(group_name) = group_name.as_ref().filter(|_| config.feature_group) {
+                let feature_name = feature_format.apply(group_name);
+                feature_attribute.extend(quote! { #[cfg(feature = #feature_name)] });
+            }
+            if config.feature_peripheral {
+                let feature_name = feature_format.apply(&p_name);
+                feature_attribute.extend(quote! { #[cfg(feature = #feature_name)] });
+            }
+            let i_ty = ident(&interrupt.name, config, "interrupt", span);
+            (value, quote! { #feature_attribute crate::Interrupt::#i_ty })
+        })
+        .collect()
+}
+
+/// Generates code for `src/interrupt.rs`
+pub fn render(
+    target: Target,
+    peripherals: &[Peripheral],
+    device_x: &mut String,
+    config: &Config,
+) -> Result<TokenStream> {
+    let mut interrupts = collect(peripherals).into_values().collect::<Vec<_>>();
     interrupts.sort_by_key(|i| i.0.value);
 
     let mut root = TokenStream::new();
@@ -75,8 +109,8 @@ pub fn render(
         let mut feature_attribute_flag = false;
         let mut feature_attribute = TokenStream::new();
         let mut not_feature_attribute = TokenStream::new();
-        if config.feature_group && interrupt.1.is_some() {
-            let feature_name = feature_format.apply(interrupt.1.as_ref().unwrap());
+        if let Some(group_name) = interrupt.1.as_ref().filter(|_| config.feature_group) {
+            let feature_name = feature_format.apply(group_name);
             feature_attribute_flag = true;
             feature_attribute.extend(quote! { #[cfg(feature = #feature_name)] });
             not_feature_attribute.extend(quote! { feature = #feature_name, });
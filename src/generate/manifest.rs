@@ -0,0 +1,250 @@
+//! `Cargo.toml` of the generated crate
+
+use crate::config::{Config, RustEdition, Target};
+use crate::generate::{family, filter};
+use crate::svd::Device;
+use crate::util::{self, Case};
+use anyhow::Result;
+
+const CRITICAL_SECTION: &str = "critical-section = { version = \"1.0\", optional = true }";
+const VCELL: &str = "vcell = \"0.1.2\"";
+const PORTABLE_ATOMIC: &str = "portable-atomic = { version = \"1\", default-features = false }";
+const DEFMT: &str = "defmt = { version = \"0.3\", optional = true }";
+const CORTEX_M: &str = "cortex-m = \"0.7.6\"";
+const CORTEX_M_TYPES: &str = "cortex-m-types = \"0.1\"";
+const CORTEX_M_RT: &str = "cortex-m-rt = { version = \"0.7\", optional = true }";
+const MSP430: &str = "msp430 = \"0.4.0\"";
+const MSP430_RT: &str = "msp430-rt = { version = \"0.4.0\", optional = true }";
+const RISCV: &str = "riscv = \"0.16.1\"";
+const RISCV_PERIPHERAL: &str = "riscv-peripheral = \"0.5.1\"";
+const RISCV_RT: &str = "riscv-rt = { version = \"0.18.0\", optional = true }";
+const MIPS_MCU: &str = "mips-mcu = \"0.1.0\"";
+const MIPS_RT: &str = "mips-rt = { version = \"0.3\", optional = true }";
+
+/// Dependencies the generated code needs, as lines of the `[dependencies]` table
+pub fn dependencies(config: &Config) -> Vec<&'static str> {
+    let mut deps = vec![CRITICAL_SECTION];
+    if !config.pointer_access {
+        deps.push(VCELL);
+    }
+    if config.atomics {
+        deps.push(PORTABLE_ATOMIC);
+    }
+    if config.impl_defmt.is_some() {
+        deps.push(DEFMT);
+    }
+    match config.target {
+        Target::CortexM => {
+            if config.reexport_core_peripherals || config.add_cortex_m_int_num {
+                deps.push(CORTEX_M);
+            }
+            deps.extend([CORTEX_M_TYPES, CORTEX_M_RT]);
+        }
+        Target::Msp430 => deps.extend([MSP430, MSP430_RT]),
+        Target::RISCV => {
+            deps.push(RISCV);
+            if config
+                .settings
+                .riscv_config
+                .as_ref()
+                .is_some_and(|c| c.clint.is_some() || c.plic.is_some())
+            {
+                deps.push(RISCV_PERIPHERAL);
+            }
+            deps.push(RISCV_RT);
+        }
+        Target::Mips => {
+            deps.push(MIPS_MCU);
+            if config.reexport_interrupt {
+                deps.push(MIPS_RT);
+            }
+        }
+        Target::XtensaLX | Target::Avr | Target::None => {}
+    }
+    deps
+}
+
+/// Features the generated code checks, as lines of the `[features]` table
+///
+/// Contains the `rt` feature wiring of the target and the features passed with
+/// `--atomics-feature`, `--impl-debug-feature`, `--impl-defmt`, `--mock` and `--trace`.
+pub fn features(config: &Config) -> Vec<String> {
+    let mut features = Features::default();
+    match config.target {
+        Target::CortexM => features.add("rt", ["cortex-m-rt/device"]),
+        Target::Msp430 => features.add("rt", ["msp430-rt/device"]),
+        Target::RISCV => {
+            features.add("rt", ["riscv-rt"]);
+            if config
+                .settings
+                .riscv_config
+                .as_ref()
+                .is_some_and(|c| c.mtvec_align.is_some())
+            {
+                features.add("v-trap", ["rt", "riscv-rt/v-trap"]);
+            }
+        }
+        Target::Mips if config.reexport_interrupt => features.add("rt", ["mips-rt"]),
+        Target::Mips | Target::XtensaLX | Target::Avr => features.add("rt", []),
+        Target::None => {}
+    }
+    if let Some(feature) = config.atomics_feature.as_ref() {
+        features.add(feature, []);
+    }
+    if let Some(feature) = config.impl_debug_feature.as_ref() {
+        features.add(feature, []);
+    }
+    if let Some(feature) = config.impl_defmt.as_ref() {
+        features.add(feature, ["dep:defmt"]);
+    }
+    if let Some(feature) = config.mock.as_ref() {
+        features.add(feature, []);
+    }
+    if let Some(feature) = config.trace.as_ref() {
+        features.add(feature, ["critical-section"]);
+    }
+    features.lines()
+}
+
+/// Group and peripheral features of `--feature-group` and `--feature-peripheral`,
+/// as lines of the `[features]` table
+pub fn device_features(devices: &[Device], config: &Config) -> Result<Vec<String>> {
+    let filtered;
+    let devices = if filter::is_active(config) {
+        filtered = devices
+            .iter()
+            .map(|d| filter::filter(d, config))
+            .collect::<Result<Vec<_>>>()?;
+        &filtered
+    } else {
+        devices
+    };
+    let feature_format = config.ident_formats.get("peripheral_feature").unwrap();
+    let mut features = Features::default();
+    if config.feature_group {
+        let mut groups: Vec<_> = devices
+            .iter()
+            .flat_map(|d| util::group_names(d, feature_format))
+            .collect();
+        groups.sort();
+        groups.dedup();
+        for group in &groups {
+            features.add(group, []);
+        }
+        features.add("all-groups", groups.iter().map(|s| s.as_ref()));
+    }
+    if config.feature_peripheral {
+        let mut peripherals: Vec<_> = devices
+            .iter()
+            .flat_map(|d| util::peripheral_names(d, feature_format))
+            .collect();
+        peripherals.sort();
+        peripherals.dedup();
+        for peripheral in &peripherals {
+            features.add(peripheral, []);
+        }
+        features.add("all-peripherals", peripherals.iter().map(|s| s.as_str()));
+    }
+    Ok(features.lines())
+}
+
+/// Name of the generated crate, the device name in snake case
+///
+/// For a family it is the common beginning of the device names.
+pub fn crate_name(devices: &[Device]) -> String {
+    let names: Vec<_> = devices
+        .iter()
+        .map(|d| Case::Snake.cow_to_case(d.name.as_str().into()))
+        .collect();
+    let Some((first, rest)) = names.split_first() else {
+        return "pac".into();
+    };
+    let mut len = first.len();
+    for name in rest {
+        len = first
+            .bytes()
+            .zip(name.bytes())
+            .take(len)
+            .take_while(|(a, b)| a == b)
+            .count();
+    }
+    let name = first[..len].trim_end_matches('_');
+    if name.is_empty() {
+        "pac".into()
+    } else {
+        name.into()
+    }
+}
+
+/// Renders `Cargo.toml` of a peripheral access crate for `devices`
+///
+/// With `--family` every device gets a chip feature.
+pub fn render(devices: &[Device], config: &Config) -> Result<String> {
+    let edition = match config.edition {
+        RustEdition::E2021 => "2021",
+        RustEdition::E2024 => "2024",
+    };
+    let names: Vec<_> = devices.iter().map(|d| d.name.as_str()).collect();
+    let description = format!("Peripheral access crate for {}", names.join(", "));
+    let mut toml = format!(
+        "[package]\n\
+        name = \"{}\"\n\
+        version = \"0.1.0\"\n\
+        edition = \"{edition}\"\n\
+        description = {description:?}\n\
+        \n\
+        [dependencies]\n",
+        crate_name(devices)
+    );
+    for dep in dependencies(config) {
+        toml.push_str(dep);
+        toml.push('\n');
+    }
+    toml.push_str("\n[features]\n");
+    let chips = if config.family.is_empty() {
+        Vec::new()
+    } else {
+        family::chips(devices, config)?
+            .into_iter()
+            .map(|chip| format!("{chip} = []"))
+            .collect()
+    };
+    for feature in features(config)
+        .into_iter()
+        .chain(chips)
+        .chain(device_features(devices, config)?)
+    {
+        toml.push_str(&feature);
+        toml.push('\n');
+    }
+    Ok(toml)
+}
+
+/// Feature table keeping the order of insertion, merging duplicates
+#[derive(Default)]
+struct Features(Vec<(String, Vec<String>)>);
+
+impl Features {
+    fn add<'a>(&mut self, name: &str, enables: impl IntoIterator<Item = &'a str>) {
+        let enables = enables.into_iter().map(String::from);
+        if let Some((_, e)) = self.0.iter_mut().find(|(n, _)| n == name) {
+            for enable in enables {
+                if !e.contains(&enable) {
+                    e.push(enable);
+                }
+            }
+        } else {
+            self.0.push((name.into(), enables.collect()));
+        }
+    }
+
+    fn lines(self) -> Vec<String> {
+        self.0
+            .into_iter()
+            .map(|(name, enables)| {
+                let enables: Vec<_> = enables.iter().map(|e| format!("\"{e}\"")).collect();
+                format!("{name} = [{}]", enables.join(", "))
+            })
+            .collect()
+    }
+}
@@ -1,6 +1,15 @@
 pub mod avr;
+pub mod c_header;
 pub mod device;
+pub mod family;
+pub mod filter;
+pub mod gdb;
+pub mod html;
+pub mod idents;
 pub mod interrupt;
+pub mod manifest;
 pub mod peripheral;
 pub mod register;
+pub mod register_map;
 pub mod riscv;
+pub mod split;
@@ -1,17 +1,20 @@
 use regex::Regex;
 use std::borrow::Cow;
 use std::cmp::Ordering;
+use std::collections::HashMap;
 use std::fmt;
 use svd_parser::expand::{
-    derive_cluster, derive_peripheral, derive_register, BlockPath, Index, RegisterPath,
+    derive_cluster, derive_enumerated_values, derive_field, derive_peripheral, derive_register,
+    BlockPath, Index, RegisterPath,
 };
 use syn::LitInt;
 
-use crate::config::Config;
+use crate::config::{Config, Target};
 use crate::svd::{
-    self, Cluster, ClusterInfo, MaybeArray, Peripheral, Register, RegisterCluster, RegisterInfo,
+    self, Cluster, ClusterInfo, Device, MaybeArray, Peripheral, Register, RegisterCluster,
+    RegisterInfo,
 };
-use log::{debug, trace, warn};
+use log::{debug, info, trace, warn};
 use proc_macro2::{Delimiter, Group, Ident, Punct, Spacing, Span, TokenStream};
 use quote::{quote, ToTokens};
 use syn::{punctuated::Punctuated, Token};
@@ -22,12 +25,86 @@ use crate::util::{
 };
 use anyhow::{anyhow, bail, Context, Result};
 
-use crate::generate::register;
+use crate::generate::{interrupt, register, register::EnumSets};
+use crate::hooks;
 
 mod accessor;
 use accessor::*;
 
-pub fn render(p_original: &Peripheral, index: &Index, config: &Config) -> Result<TokenStream> {
+mod layout;
+pub(crate) use layout::{layout, place_registers, resolve_fields, LayoutKind, LayoutMember};
+
+/// Family information of the peripheral instances, used to implement the `Instance` traits
+#[derive(Debug, Default)]
+pub struct Instances {
+    /// Type of the interrupt enumeration, if the device has one
+    interrupt_ty: Option<TokenStream>,
+    /// Position of each instance among the peripherals sharing its register block
+    index: HashMap<String, usize>,
+    /// Interrupts raised by each instance
+    interrupts: HashMap<String, TokenStream>,
+}
+
+/// Collects the family index and the interrupts of every peripheral instance
+pub fn instances(peripherals: &[Peripheral], index: &Index, config: &Config) -> Result<Instances> {
+    let mut instances = Instances::default();
+    if !config.instance_traits {
+        return Ok(instances);
+    }
+
+    let variants = interrupt::variants(peripherals, config);
+    instances.interrupt_ty =
+        if config.target == Target::RISCV && config.settings.riscv_config.is_some() {
+            (!variants.is_empty()).then(|| quote!(crate::interrupt::ExternalInterrupt))
+        } else {
+            (matches!(
+                config.target,
+                Target::CortexM | Target::Msp430 | Target::XtensaLX
+            ) || !variants.is_empty())
+            .then(|| quote!(crate::Interrupt))
+        };
+
+    let mut families = HashMap::<String, usize>::new();
+    for p in peripherals {
+        // the family is the peripheral which renders the register block
+        let mut family = p.name.clone();
+        if let Some(dpath) = p.derived_from.as_ref() {
+            let mut p = p.clone();
+            p.derived_from = None;
+            if let Some(path) = derive_peripheral(&mut p, dpath, index)? {
+                family = path.peripheral;
+            }
+        }
+
+        let mut interrupts = TokenStream::new();
+        for i in &p.interrupt {
+            if let Some(variant) = variants.get(&i.value) {
+                interrupts.extend(quote! { #variant, });
+            }
+        }
+
+        let names: Vec<String> = match p {
+            Peripheral::Single(info) => vec![info.name.clone()],
+            Peripheral::Array(info, dim) => svd_rs::array::names(info, dim).collect(),
+        };
+        let next = families.entry(family).or_default();
+        for name in names {
+            instances.index.insert(name.clone(), *next);
+            instances.interrupts.insert(name, interrupts.clone());
+            *next += 1;
+        }
+    }
+
+    Ok(instances)
+}
+
+pub fn render(
+    p_original: &Peripheral,
+    index: &Index,
+    instances: &Instances,
+    enum_sets: &mut EnumSets,
+    config: &Config,
+) -> Result<TokenStream> {
     let mut out = TokenStream::new();
 
     let mut p = p_original.clone();
@@ -58,24 +135,34 @@ pub fn render(p_original: &Peripheral, index: &Index, config: &Config) -> Result
 
     let mut feature_attribute = TokenStream::new();
     let feature_format = config.ident_formats.get("peripheral_feature").unwrap();
-    if config.feature_group && p.group_name.is_some() {
-        let feature_name = feature_format.apply(p.group_name.as_ref().unwrap());
+    if let Some(group_name) = p.group_name.as_ref().filter(|_| config.feature_group) {
+        let feature_name = feature_format.apply(group_name);
         feature_attribute.extend(quote! { #[cfg(feature = #feature_name)] });
     };
 
-    let phtml = config.settings.html_url.as_ref().map(|url| {
-        let doc = format!("See peripheral [structure]({url}#{})", &path.peripheral);
-        quote!(#[doc = ""] #[doc = #doc])
-    });
+    // the HTML reference has a section for each instance
+    let phtml = |svd_name: &str| {
+        config.settings.html_url.as_ref().map(|url| {
+            let anchor = if config.html_reference {
+                svd_name
+            } else {
+                &path.peripheral
+            };
+            let doc = format!("See peripheral [structure]({url}#{anchor})");
+            quote!(#[doc = ""] #[doc = #doc])
+        })
+    };
 
     let per_to_tokens = |out: &mut TokenStream,
                          feature_attribute: &TokenStream,
                          doc: &str,
                          p_ty: &Ident,
                          name_str: &str,
+                         svd_name: &str,
                          doc_alias: Option<TokenStream>,
                          address: LitInt| {
         let pspec = ident(name_str, config, "peripheral_spec", Span::call_site());
+        let phtml = phtml(svd_name);
         out.extend(quote! {
             #[doc = #doc]
             #phtml
@@ -93,6 +180,28 @@ pub fn render(p_original: &Peripheral, index: &Index, config: &Config) -> Result
                 const NAME: &'static str = #name_str;
             }
         });
+        if config.pointer_access {
+            out.extend(quote! {
+                #feature_attribute
+                impl crate::PeripheralBlock for #pspec {
+                    const BLOCK: &'static Self::RB = &unsafe { #base::RegisterBlock::from_ptr(#address as *mut u8) };
+                }
+            });
+        }
+        if let Some(index) = instances.index.get(svd_name) {
+            let index = unsuffixed(*index as u64);
+            let interrupts = instances.interrupt_ty.as_ref().map(|ty| {
+                let interrupts = &instances.interrupts[svd_name];
+                quote!(const INTERRUPTS: &'static [#ty] = &[#interrupts];)
+            });
+            out.extend(quote! {
+                #feature_attribute
+                impl #base::Instance for #pspec {
+                    const INDEX: usize = #index;
+                    #interrupts
+                }
+            });
+        }
     };
 
     match &p {
@@ -119,9 +228,17 @@ pub fn render(p_original: &Peripheral, index: &Index, config: &Config) -> Result
                     &doc,
                     &p_ty,
                     &name_str,
+                    name,
                     doc_alias,
                     address,
                 );
+                out.extend(hooks::peripheral(
+                    &pi,
+                    &BlockPath::new(name),
+                    &p_ty,
+                    &feature_attribute_n,
+                    config,
+                )?);
             }
 
             let feature_any_attribute = quote! {#[cfg(any(#(feature = #feature_names),*))]};
@@ -150,9 +267,17 @@ pub fn render(p_original: &Peripheral, index: &Index, config: &Config) -> Result
                 &doc,
                 &p_ty,
                 &name_str,
+                &p.name,
                 None,
                 address,
             );
+            out.extend(hooks::peripheral(
+                &p,
+                &BlockPath::new(&p.name),
+                &p_ty,
+                &feature_attribute,
+                config,
+            )?);
 
             // Derived peripherals may not require re-implementation, and will instead
             // use a single definition of the non-derived version.
@@ -195,7 +320,7 @@ pub fn render(p_original: &Peripheral, index: &Index, config: &Config) -> Result
     debug!("Pushing cluster & register information into output");
     // Push all cluster & register related information into the peripheral module
 
-    let mod_items = render_ercs(&mut ercs, &derive_infos, &path, index, config)?;
+    let mod_items = render_ercs(&mut ercs, &derive_infos, &path, index, enum_sets, config)?;
 
     // Push any register or cluster blocks into the output
     debug!(
@@ -220,6 +345,28 @@ pub fn render(p_original: &Peripheral, index: &Index, config: &Config) -> Result
 
     let mut out_items = TokenStream::new();
     out_items.extend(reg_block);
+    out_items.extend(hooks::register_block(
+        &ercs,
+        &BlockPath::new(&p.name),
+        &Ident::new("RegisterBlock", span),
+        config,
+    )?);
+    if config.instance_traits {
+        let interrupts = instances.interrupt_ty.as_ref().map(|ty| {
+            quote! {
+                /// Interrupts raised by the instance
+                const INTERRUPTS: &'static [#ty];
+            }
+        });
+        out_items.extend(quote! {
+            /// Implemented by every instance of the peripheral sharing this register block
+            pub trait Instance: crate::PeripheralSpec<RB = RegisterBlock> {
+                /// Position of the instance in the family, in the order of the device description
+                const INDEX: usize;
+                #interrupts
+            }
+        });
+    }
     out_items.extend(mod_items);
 
     let out_group = Group::new(Delimiter::Brace, out_items);
@@ -252,6 +399,8 @@ struct RegisterBlockField {
     syn_field: syn::Field,
     offset: u32,
     size: u32,
+    /// Size of the registers in bits, larger than `size` for arrays accessed by offset
+    span: u32,
     accessors: Vec<AccessType>,
 }
 
@@ -370,6 +519,16 @@ struct FieldRegions {
 }
 
 impl FieldRegions {
+    /// End of the registers of the block, arrays accessed by offset included
+    fn end(&self) -> u32 {
+        self.regions
+            .iter()
+            .flat_map(|r| &r.rbfs)
+            .map(|rbf| rbf.offset + rbf.span.div_ceil(BITS_PER_BYTE))
+            .max()
+            .unwrap_or(0)
+    }
+
     /// Track a field.  If the field overlaps with 1 or more existing
     /// entries, they will be merged together.
     fn add(&mut self, rbf: &RegisterBlockField) -> Result<()> {
@@ -512,10 +671,26 @@ fn register_or_cluster_block(
 
     // We need to compute the idents of each register/union block first to make sure no conflicts exists.
     regions.resolve_idents()?;
+
+    let span = Span::call_site();
+    let mut doc_alias = None;
+    let block_ty = if let Some(name) = name {
+        let ty = ident(name, config, "cluster", span);
+        if ty != name {
+            doc_alias = Some(quote!(#[doc(alias = #name)]));
+        }
+        ty
+    } else {
+        Ident::new("RegisterBlock", span)
+    };
+
+    if config.pointer_access {
+        return Ok(pointer_block(&regions, &block_ty, doc_alias, doc, config));
+    }
+
     // The end of the region for which we previously emitted a rbf into `rbfs`
     let mut last_end = 0;
 
-    let span = Span::call_site();
     for (i, region) in regions.regions.iter().enumerate() {
         // Check if we need padding
         let pad = region.offset - last_end;
@@ -598,17 +773,6 @@ fn register_or_cluster_block(
         }
     });
 
-    let mut doc_alias = None;
-    let block_ty = if let Some(name) = name {
-        let ty = ident(name, config, "cluster", span);
-        if ty != name {
-            doc_alias = Some(quote!(#[doc(alias = #name)]));
-        }
-        ty
-    } else {
-        Ident::new("RegisterBlock", span)
-    };
-
     let accessors = (!accessors.is_empty()).then(|| {
         quote! {
             impl #block_ty {
@@ -617,6 +781,31 @@ fn register_or_cluster_block(
         }
     });
 
+    // arrays accessed by offset take no room in the struct, so the simulated register block
+    // and the address ranges of the trace need the size of the registers
+    let size = unsuffixed(regions.end());
+    let mock_block = config.mock.as_ref().filter(|_| name.is_none()).map(|mock| {
+        quote! {
+            #[cfg(feature = #mock)]
+            impl crate::MockBlock for #block_ty {
+                const SIZE: usize = #size;
+            }
+        }
+    });
+    let trace_size = config
+        .trace
+        .as_ref()
+        .filter(|_| name.is_none())
+        .map(|trace| {
+            quote! {
+                #[cfg(feature = #trace)]
+                impl #block_ty {
+                    /// Size of the registers of the block in bytes.
+                    pub(crate) const SIZE: usize = #size;
+                }
+            }
+        });
+
     Ok(quote! {
         #[repr(C)]
         #derive_debug
@@ -627,9 +816,111 @@ fn register_or_cluster_block(
         }
 
         #accessors
+
+        #mock_block
+
+        #trace_size
     })
 }
 
+/// Render register block as a copyable pointer to the registers
+fn pointer_block(
+    regions: &FieldRegions,
+    block_ty: &Ident,
+    doc_alias: Option<TokenStream>,
+    doc: &str,
+    config: &Config,
+) -> TokenStream {
+    let mut accessors = TokenStream::new();
+    let mut debug_fields = TokenStream::new();
+    for rbf in regions.regions.iter().flat_map(|region| &region.rbfs) {
+        for a in &rbf.accessors {
+            a.clone().ptr().to_tokens(&mut accessors);
+        }
+        match rbf.accessors[0].accessor() {
+            Accessor::Reg(RegAccessor { name, .. }) => {
+                let name_str = name.to_string();
+                debug_fields.extend(quote!(.field(#name_str, &self.#name())));
+            }
+            Accessor::Array(ArrayAccessor { name, dim, .. }) => {
+                let name_str = name.to_string();
+                let dim = unsuffixed(*dim);
+                debug_fields.extend(quote! {
+                    .field(#name_str, &core::array::from_fn::<_, #dim, _>(|n| self.#name(n)))
+                });
+            }
+            Accessor::ArrayElem(_) => {}
+        }
+    }
+
+    let size = unsuffixed(regions.end());
+
+    let debug_impl = config.impl_debug.then(|| {
+        let debug_feature = config
+            .impl_debug_feature
+            .as_ref()
+            .map(|feature| quote!(#[cfg(feature = #feature)]));
+        let block_name = block_ty.to_string();
+        quote! {
+            #debug_feature
+            impl core::fmt::Debug for #block_ty {
+                fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+                    f.debug_struct(#block_name)
+                        #debug_fields
+                        .finish()
+                }
+            }
+        }
+    });
+
+    quote! {
+        #[doc = #doc]
+        #doc_alias
+        #[derive(Clone, Copy)]
+        pub struct #block_ty {
+            ptr: *mut u8,
+        }
+
+        unsafe impl Send for #block_ty {}
+
+        impl crate::PointerBlock for #block_ty {
+            #[inline(always)]
+            unsafe fn from_ptr(ptr: *mut u8) -> Self {
+                Self { ptr }
+            }
+            #[inline(always)]
+            fn as_ptr(&self) -> *mut u8 {
+                self.ptr
+            }
+        }
+
+        impl #block_ty {
+            /// Creates register block from its base address.
+            ///
+            /// # Safety
+            ///
+            /// `ptr` must point to the registers of this block.
+            #[inline(always)]
+            pub const unsafe fn from_ptr(ptr: *mut u8) -> Self {
+                Self { ptr }
+            }
+
+            /// Returns base address of the register block.
+            #[inline(always)]
+            pub const fn as_ptr(&self) -> *mut u8 {
+                self.ptr
+            }
+
+            /// Size of the registers of the block in bytes.
+            pub const SIZE: usize = #size;
+
+            #accessors
+        }
+
+        #debug_impl
+    }
+}
+
 /// Expand a list of parsed `Register`s or `Cluster`s, and render them to
 /// `RegisterBlockField`s containing `Field`s.
 fn expand(
@@ -666,6 +957,192 @@ fn expand(
     Ok(ercs_expanded)
 }
 
+/// Turns peripherals and clusters which are structurally identical to a previous one into
+/// derived ones, so they are rendered as re-exports of a single module
+///
+/// Like the implicit register derivation of `check_erc_derive_infos` this is done only for
+/// blocks which have no `derivedFrom` of their own. Blocks that are referenced by a
+/// `derivedFrom` path of another element are kept as is, and blocks are only derived from
+/// blocks behind the same feature gate.
+pub fn derive_identical(device: &Device, config: &Config) -> Result<Device> {
+    let expanded =
+        svd_parser::expand::expand(device).context("can't expand device to compare peripherals")?;
+    let index = Index::create(device);
+    let mut device = device.clone();
+
+    let mut dpaths = Vec::new();
+    for p in &device.peripherals {
+        dpaths.extend(p.derived_from.clone());
+        collect_derived_from(p.registers.as_deref().unwrap_or_default(), &mut dpaths);
+    }
+    // `derivedFrom` paths pointing inside of the block
+    let is_referenced = |name: &str| {
+        dpaths.iter().any(|dpath| {
+            let mut segments = dpath.split('.');
+            segments.next_back();
+            segments.any(|s| s == name)
+        })
+    };
+
+    let mut roots: Vec<(Option<String>, &svd::PeripheralInfo)> = Vec::new();
+    for p in device.peripherals.iter_mut() {
+        let scope = feature_scope(p, config);
+        let Peripheral::Single(info) = p else {
+            continue;
+        };
+        if info.derived_from.is_some() || info.registers.as_ref().map_or(true, Vec::is_empty) {
+            continue;
+        }
+        let Some(this) = expanded.get_peripheral(&info.name) else {
+            continue;
+        };
+        match roots
+            .iter()
+            .find(|(s, root)| s == &scope && root.registers == this.registers)
+        {
+            Some((_, root)) if !is_referenced(&info.name) => {
+                info!("Peripheral {} is identical to {}", info.name, root.name);
+                info.derived_from = Some(root.name.clone());
+                info.registers = None;
+            }
+            _ => roots.push((scope, this)),
+        }
+    }
+
+    let mut roots = Vec::new();
+    for p in device.peripherals.iter_mut() {
+        if p.derived_from.is_none() {
+            let scope = feature_scope(p, config);
+            let path = BlockPath::new(&p.name);
+            if let Some(ercs) = p.registers.as_mut() {
+                derive_identical_clusters(ercs, &path, &scope, &index, &mut roots, &is_referenced)?;
+            }
+        }
+    }
+
+    Ok(device)
+}
+
+/// Feature gate of the module of a peripheral, `None` if it isn't gated
+///
+/// Re-exports of modules behind other gates may be disabled, like the shared enum types of
+/// `enum_set_scope`.
+fn feature_scope(p: &Peripheral, config: &Config) -> Option<String> {
+    if config.feature_peripheral {
+        return Some(p.name.clone());
+    }
+    p.group_name
+        .as_ref()
+        .filter(|_| config.feature_group)
+        .map(|group_name| format!("group {group_name}"))
+}
+
+fn collect_derived_from(ercs: &[RegisterCluster], dpaths: &mut Vec<String>) {
+    for erc in ercs {
+        match erc {
+            RegisterCluster::Cluster(c) => {
+                dpaths.extend(c.derived_from.clone());
+                collect_derived_from(&c.children, dpaths);
+            }
+            RegisterCluster::Register(r) => {
+                dpaths.extend(r.derived_from.clone());
+                for f in r.fields() {
+                    dpaths.extend(f.derived_from.clone());
+                    for ev in &f.enumerated_values {
+                        dpaths.extend(ev.derived_from.clone());
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// `roots` holds the feature gate, the path and the resolved children of the clusters seen so far
+fn derive_identical_clusters(
+    ercs: &mut [RegisterCluster],
+    path: &BlockPath,
+    scope: &Option<String>,
+    index: &Index,
+    roots: &mut Vec<(Option<String>, BlockPath, Vec<RegisterCluster>)>,
+    is_referenced: &impl Fn(&str) -> bool,
+) -> Result<()> {
+    for erc in ercs {
+        let RegisterCluster::Cluster(c) = erc else {
+            continue;
+        };
+        if c.derived_from.is_some() || c.children.is_empty() {
+            continue;
+        }
+        let cpath = path.new_cluster(&c.name);
+        let children = resolve_children(&c.children, &cpath, index)?;
+        match roots
+            .iter()
+            .find(|(s, _, root)| s == scope && root == &children)
+        {
+            Some((_, root, _)) if !is_referenced(&c.name) => {
+                info!("Cluster {cpath} is identical to {root}");
+                c.derived_from = Some(root.to_string());
+                c.children.clear();
+            }
+            _ => {
+                roots.push((scope.clone(), cpath.clone(), children));
+                derive_identical_clusters(
+                    &mut c.children,
+                    &cpath,
+                    scope,
+                    index,
+                    roots,
+                    is_referenced,
+                )?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Children of the cluster at `cpath` with their `derivedFrom` references resolved
+fn resolve_children(
+    children: &[RegisterCluster],
+    cpath: &BlockPath,
+    index: &Index,
+) -> Result<Vec<RegisterCluster>> {
+    let mut resolved = Vec::with_capacity(children.len());
+    for erc in children {
+        let mut erc = erc.clone();
+        match &mut erc {
+            RegisterCluster::Register(reg) => {
+                let mut rpath = None;
+                if let Some(dpath) = reg.derived_from.take() {
+                    rpath = derive_register(reg, &dpath, cpath, index)?;
+                }
+                let rpath = rpath.unwrap_or_else(|| cpath.new_register(&reg.name));
+                for f in reg.fields.iter_mut().flatten() {
+                    let mut fpath = None;
+                    if let Some(dpath) = f.derived_from.take() {
+                        fpath = derive_field(f, &dpath, &rpath, index)?;
+                    }
+                    let fpath = fpath.unwrap_or_else(|| rpath.new_field(&f.name));
+                    for evs in &mut f.enumerated_values {
+                        if let Some(dpath) = evs.derived_from.take() {
+                            derive_enumerated_values(evs, &dpath, &fpath, index)?;
+                        }
+                    }
+                }
+            }
+            RegisterCluster::Cluster(c) => {
+                let mut ccpath = None;
+                if let Some(dpath) = c.derived_from.take() {
+                    ccpath = derive_cluster(c, &dpath, cpath, index)?;
+                }
+                let ccpath = ccpath.unwrap_or_else(|| cpath.new_cluster(&c.name));
+                c.children = resolve_children(&c.children, &ccpath, index)?;
+            }
+        }
+        resolved.push(erc);
+    }
+    Ok(resolved)
+}
+
 /// Searches types using regex to find disjoint arrays which should implicitly derive from
 /// another register. Returns a vector of `DeriveInfo` which should be `zip`ped with ercs when rendering.
 fn check_erc_derive_infos(
@@ -868,15 +1345,10 @@ fn compare_prev_against_this(
             }
             if let DeriveInfo::Implicit(my_rpath) = &my_derive_info {
                 match prev_derive_info {
-                    DeriveInfo::Implicit(their_rpath) => {
-                        if their_rpath.name == ty_name {
-                            (_, *their_rpath) = find_root(&my_rpath.name, path, index)?;
-                        }
-                    }
-                    DeriveInfo::Explicit(their_rpath) => {
-                        if their_rpath.name == ty_name {
-                            (_, *their_rpath) = find_root(&my_rpath.name, path, index)?;
-                        }
+                    DeriveInfo::Implicit(their_rpath) | DeriveInfo::Explicit(their_rpath)
+                        if their_rpath.name == ty_name =>
+                    {
+                        (_, *their_rpath) = find_root(&my_rpath.name, path, index)?;
                     }
                     _ => {}
                 }
@@ -979,6 +1451,7 @@ fn expand_cluster(
                 syn_field,
                 offset: info.address_offset,
                 size: cluster_size,
+                span: cluster_size,
                 accessors: vec![accessor],
             })
         }
@@ -1076,6 +1549,8 @@ fn expand_cluster(
                     } else {
                         0
                     },
+                    span: array_info.dim_increment * BITS_PER_BYTE * (array_info.dim - 1)
+                        + cluster_size,
                     accessors,
                 });
             } else {
@@ -1092,13 +1567,14 @@ fn expand_cluster(
                         doc,
                         name,
                         ty: ty.clone(),
-                        offset: info.address_offset,
+                        offset: ci.address_offset,
                     })
                     .raw_if(false);
                     cluster_expanded.push(RegisterBlockField {
                         syn_field,
                         offset: ci.address_offset,
                         size: cluster_size,
+                        span: cluster_size,
                         accessors: vec![accessor],
                     });
                 }
@@ -1152,6 +1628,7 @@ fn expand_register(
                 syn_field,
                 offset: info.address_offset,
                 size: register_size,
+                span: register_size,
                 accessors: vec![accessor],
             })
         }
@@ -1268,6 +1745,8 @@ fn expand_register(
                     } else {
                         0
                     },
+                    span: array_info.dim_increment * BITS_PER_BYTE * (array_info.dim - 1)
+                        + register_size,
                     accessors,
                 });
             } else {
@@ -1284,13 +1763,14 @@ fn expand_register(
                         doc,
                         name,
                         ty: ty.clone(),
-                        offset: info.address_offset,
+                        offset: ri.address_offset,
                     })
                     .raw_if(false);
                     register_expanded.push(RegisterBlockField {
                         syn_field,
                         offset: ri.address_offset,
                         size: register_size,
+                        span: register_size,
                         accessors: vec![accessor],
                     });
                 }
@@ -1306,6 +1786,7 @@ fn render_ercs(
     derive_infos: &[DeriveInfo],
     path: &BlockPath,
     index: &Index,
+    enum_sets: &mut EnumSets,
     config: &Config,
 ) -> Result<TokenStream> {
     let mut mod_items = TokenStream::new();
@@ -1321,7 +1802,11 @@ fn render_ercs(
                 if let Some(dpath) = dpath {
                     cpath = derive_cluster(c, &dpath, path, index)?;
                 }
-                mod_items.extend(cluster_block(c, path, cpath, index, config)?);
+                if let Some(cpath) = cpath.as_ref() {
+                    // the parent block needs the size of the registers taken from the base
+                    c.children = resolve_children(&c.children, cpath, index)?;
+                }
+                mod_items.extend(cluster_block(c, path, cpath, index, enum_sets, config)?);
             }
 
             // Generate definition for each of the registers.
@@ -1337,7 +1822,7 @@ fn render_ercs(
                     }
                 }
                 let reg_name = &reg.name;
-                let rendered_reg = register::render(reg, path, rpath, index, config)
+                let rendered_reg = register::render(reg, path, rpath, index, enum_sets, config)
                     .with_context(|| format!("can't render register '{reg_name}'"))?;
                 mod_items.extend(rendered_reg)
             }
@@ -1352,6 +1837,7 @@ fn cluster_block(
     path: &BlockPath,
     dpath: Option<BlockPath>,
     index: &Index,
+    enum_sets: &mut EnumSets,
     config: &Config,
 ) -> Result<TokenStream> {
     let doc = c.description.as_ref().unwrap_or(&c.name);
@@ -1390,7 +1876,14 @@ fn cluster_block(
     } else {
         let cpath = path.new_cluster(&c.name);
         let mod_derive_infos = check_erc_derive_infos(&mut c.children, &cpath, index, config)?;
-        let mod_items = render_ercs(&mut c.children, &mod_derive_infos, &cpath, index, config)?;
+        let mod_items = render_ercs(
+            &mut c.children,
+            &mod_derive_infos,
+            &cpath,
+            index,
+            enum_sets,
+            config,
+        )?;
 
         // Generate the register block.
         let cluster_size = match c {
@@ -1409,9 +1902,13 @@ fn cluster_block(
             config,
         )?;
 
+        let block_items = hooks::register_block(&c.children, &cpath, &block_ty, config)?;
+
         let mod_items = quote! {
             #reg_block
 
+            #block_items
+
             #mod_items
         };
 
@@ -1445,3 +1942,77 @@ fn new_syn_array(ty: syn::Type, len: u32) -> syn::Type {
     let len = unsuffixed(len);
     syn::parse_quote_spanned!( span => [#ty; #len] )
 }
+
+#[cfg(test)]
+const TEST_SVD: &str = r#"<device><name>CHIP</name><size>32</size><peripherals>
+<peripheral><name>TIM2</name><groupName>TIM</groupName><baseAddress>0x40000000</baseAddress><registers>
+<register><name>CR1</name><addressOffset>0x0</addressOffset></register>
+</registers></peripheral>
+<peripheral><name>TIM3</name><groupName>TIMB</groupName><baseAddress>0x40000400</baseAddress><registers>
+<register><name>CR1</name><addressOffset>0x0</addressOffset></register>
+</registers></peripheral>
+<peripheral><name>TIM4</name><groupName>TIMB</groupName><baseAddress>0x40000800</baseAddress><registers>
+<register><name>CR1</name><addressOffset>0x0</addressOffset></register>
+</registers></peripheral>
+<peripheral><name>DMA1</name><groupName>DMA</groupName><baseAddress>0x40020000</baseAddress><registers>
+<cluster><name>CH</name><addressOffset>0x0</addressOffset>
+<register><name>CR</name><addressOffset>0x0</addressOffset></register>
+<register derivedFrom="CR"><name>NDTR</name><addressOffset>0x4</addressOffset></register>
+</cluster>
+</registers></peripheral>
+<peripheral><name>DMA2</name><groupName>DMAB</groupName><baseAddress>0x40020400</baseAddress><registers>
+<cluster><name>CH</name><addressOffset>0x0</addressOffset>
+<register><name>CR</name><addressOffset>0x0</addressOffset></register>
+<register derivedFrom="CR"><name>NDTR</name><addressOffset>0x4</addressOffset></register>
+</cluster>
+<cluster><name>STREAM</name><addressOffset>0x10</addressOffset>
+<register><name>CR</name><addressOffset>0x0</addressOffset></register>
+<register><name>NDTR</name><addressOffset>0x4</addressOffset></register>
+</cluster>
+</registers></peripheral>
+</peripherals></device>"#;
+
+#[test]
+fn derive_identical_within_feature_gates() {
+    let device = svd_parser::parse(TEST_SVD).unwrap();
+    let derived_from = |config: &Config| {
+        let device = derive_identical(&device, config).unwrap();
+        let mut paths = Vec::new();
+        for p in &device.peripherals {
+            paths.extend(p.derived_from.iter().map(|d| format!("{} {d}", p.name)));
+            for c in p.clusters() {
+                paths.extend(
+                    c.derived_from
+                        .iter()
+                        .map(|d| format!("{}.{} {d}", p.name, c.name)),
+                );
+            }
+        }
+        paths
+    };
+    assert_eq!(
+        derived_from(&Config::default()),
+        [
+            "TIM3 TIM2",
+            "TIM4 TIM2",
+            "DMA2.CH DMA1.CH",
+            "DMA2.STREAM DMA1.CH"
+        ]
+    );
+    let config = Config {
+        feature_group: true,
+        ..Default::default()
+    };
+    // `STREAM` has the resolved registers of `CH`
+    assert_eq!(derived_from(&config), ["TIM4 TIM3", "DMA2.STREAM DMA2.CH"]);
+
+    let config = Config {
+        derive_identical: true,
+        feature_group: true,
+        ..Default::default()
+    };
+    let lib_rs = crate::generate(TEST_SVD, &config).unwrap().lib_rs;
+    assert!(lib_rs.contains(r#"#[cfg (feature = "timb")] pub use self :: tim3 as tim4 ;"#));
+    assert!(!lib_rs.contains("pub use self :: tim2"));
+    assert!(!lib_rs.contains("dma1 :: Ch"));
+}
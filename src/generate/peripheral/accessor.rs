@@ -14,6 +14,7 @@ pub enum Accessor {
 pub enum AccessType {
     Ref(Accessor),
     RawRef(Accessor),
+    Ptr(Accessor),
 }
 
 impl Accessor {
@@ -29,10 +30,21 @@ impl Accessor {
 impl AccessType {
     pub fn raw(self) -> Self {
         match self {
-            Self::RawRef(_) => self,
+            Self::RawRef(_) | Self::Ptr(_) => self,
             Self::Ref(a) => Self::RawRef(a),
         }
     }
+    pub fn ptr(self) -> Self {
+        match self {
+            Self::Ptr(_) => self,
+            Self::Ref(a) | Self::RawRef(a) => Self::Ptr(a),
+        }
+    }
+    pub fn accessor(&self) -> &Accessor {
+        match self {
+            Self::Ref(a) | Self::RawRef(a) | Self::Ptr(a) => a,
+        }
+    }
 }
 
 impl ToTokens for AccessType {
@@ -118,7 +130,59 @@ impl ToTokens for AccessType {
                     }
                 }
             }
-            Self::RawRef(Accessor::ArrayElem(elem)) | Self::Ref(Accessor::ArrayElem(elem)) => {
+            Self::Ptr(Accessor::Reg(RegAccessor {
+                doc,
+                name,
+                ty,
+                offset,
+            })) => {
+                let offset = (*offset != 0).then(|| unsuffixed(*offset)).map(|o| quote!(.add(#o)));
+                quote! {
+                    #[doc = #doc]
+                    #[inline(always)]
+                    pub const fn #name(&self) -> #ty {
+                        unsafe { <#ty>::from_ptr(self.ptr #offset .cast()) }
+                    }
+                }
+            }
+            Self::Ptr(Accessor::Array(ArrayAccessor {
+                doc,
+                name,
+                ty,
+                offset,
+                dim,
+                increment,
+                note,
+            })) => {
+                let name_iter = Ident::new(&format!("{name}_iter"), Span::call_site());
+                let offset = (*offset != 0).then(|| unsuffixed(*offset)).map(|o| quote!(.add(#o)));
+                let dim = unsuffixed(*dim);
+                let increment = (*increment != 1).then(|| unsuffixed(*increment)).map(|i| quote!(#i *));
+                let note = note.as_ref().map(|note| quote! {
+                    #[doc = ""]
+                    #[doc = #note]
+                });
+                quote! {
+                    #[doc = #doc]
+                    #note
+                    #[inline(always)]
+                    pub const fn #name(&self, n: usize) -> #ty {
+                        #[allow(clippy::no_effect)]
+                        [(); #dim][n];
+                        unsafe { <#ty>::from_ptr(self.ptr #offset .add(#increment n).cast()) }
+                    }
+                    #[doc = "Iterator for array of:"]
+                    #[doc = #doc]
+                    #[inline(always)]
+                    pub fn #name_iter(&self) -> impl Iterator<Item=#ty> {
+                        let block = *self;
+                        (0..#dim).map(move |n| block.#name(n))
+                    }
+                }
+            }
+            Self::RawRef(Accessor::ArrayElem(elem))
+            | Self::Ref(Accessor::ArrayElem(elem))
+            | Self::Ptr(Accessor::ArrayElem(elem)) => {
                 let ArrayElemAccessor {
                     doc,
                     name,
@@ -127,10 +191,15 @@ impl ToTokens for AccessType {
                     i,
                 } = elem;
                 let i = unsuffixed(*i as u64);
+                let ty = if let Self::Ptr(_) = self {
+                    quote!(#ty)
+                } else {
+                    quote!(&#ty)
+                };
                 quote! {
                     #[doc = #doc]
                     #[inline(always)]
-                    pub const fn #name(&self) -> &#ty {
+                    pub const fn #name(&self) -> #ty {
                         self.#basename(#i)
                     }
                 }
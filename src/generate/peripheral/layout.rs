@@ -0,0 +1,272 @@
+//! Addresses of the registers in the generated register blocks
+
+use anyhow::{bail, Context, Result};
+use svd_parser::expand::{
+    derive_cluster, derive_enumerated_values, derive_field, derive_peripheral, derive_register,
+    BlockPath, Index, RegisterPath,
+};
+
+use super::{
+    check_erc_derive_infos, cluster_info_size_in_bits, expand_cluster, expand_register, Accessor,
+    DeriveInfo, RegisterBlockField,
+};
+use crate::config::Config;
+use crate::svd::{self, Cluster, Peripheral, Register, RegisterCluster, RegisterInfo};
+use crate::util::{self, ident, DimSuffix, BITS_PER_BYTE};
+use proc_macro2::Span;
+
+/// Register or cluster accessor of a generated register block
+#[derive(Clone)]
+pub(crate) struct LayoutMember {
+    /// Name of the accessor
+    pub ident: String,
+    /// Offset in bytes from the start of the block
+    pub offset: u32,
+    /// Length and increment in bytes of an array accessor
+    pub array: Option<(u32, u32)>,
+    pub kind: LayoutKind,
+}
+
+#[derive(Clone)]
+pub(crate) enum LayoutKind {
+    Register {
+        /// Module of the register
+        module: String,
+        /// Registers the accessor gives access to, with resolved `derivedFrom` references and
+        /// expanded field arrays
+        elements: Vec<RegisterInfo>,
+    },
+    Cluster {
+        /// Module and type of the cluster
+        module: String,
+        ty: String,
+        /// SVD names of the clusters the accessor gives access to
+        names: Vec<String>,
+        description: Option<String>,
+        /// Size in bytes of one cluster
+        size: u32,
+        members: Vec<LayoutMember>,
+    },
+}
+
+impl LayoutMember {
+    /// Offset of the element `i` of the accessor
+    pub fn element_offset(&self, i: u32) -> u32 {
+        match self.array {
+            Some((_, increment)) => self.offset + i * increment,
+            None => self.offset,
+        }
+    }
+}
+
+/// Register at the offset it has in the generated register block of its peripheral
+pub(crate) struct PlacedRegister {
+    /// Names of the enclosing clusters and of the register, with array indexes
+    pub path: Vec<String>,
+    /// Offset in bytes from the start of the peripheral
+    pub offset: u32,
+    /// Register with resolved `derivedFrom` references and expanded field arrays
+    pub info: RegisterInfo,
+}
+
+/// Accessors of the `RegisterBlock` rendered for a peripheral, sorted by offset
+///
+/// Derived peripherals are laid out like the peripheral they derive from.
+pub(crate) fn layout(p: &Peripheral, index: &Index, config: &Config) -> Result<Vec<LayoutMember>> {
+    let mut p = p.clone();
+    let mut path = None;
+    if let Some(dpath) = p.derived_from.take() {
+        path = derive_peripheral(&mut p, &dpath, index)?;
+    }
+    let path = path.unwrap_or_else(|| BlockPath::new(&p.name));
+    let mut ercs = p.registers.take().unwrap_or_default();
+    layout_block(&mut ercs, &path, index, config)
+}
+
+/// Lays out the registers of a peripheral like its rendered `RegisterBlock`
+///
+/// Register and cluster arrays are expanded to their elements, at the offsets of the generated
+/// accessors.
+pub(crate) fn place_registers(
+    p: &Peripheral,
+    index: &Index,
+    config: &Config,
+) -> Result<Vec<PlacedRegister>> {
+    let mut placed = Vec::new();
+    place(&layout(p, index, config)?, &[], 0, &mut placed);
+    placed.sort_by_key(|r| r.offset);
+    Ok(placed)
+}
+
+fn place(members: &[LayoutMember], prefix: &[String], base: u32, placed: &mut Vec<PlacedRegister>) {
+    for m in members {
+        match &m.kind {
+            LayoutKind::Register { elements, .. } => {
+                for (i, info) in (0..).zip(elements) {
+                    let mut path = prefix.to_vec();
+                    path.push(info.name.clone());
+                    placed.push(PlacedRegister {
+                        path,
+                        offset: base + m.element_offset(i),
+                        info: info.clone(),
+                    });
+                }
+            }
+            LayoutKind::Cluster { names, members, .. } => {
+                for (i, name) in (0..).zip(names) {
+                    let mut path = prefix.to_vec();
+                    path.push(name.clone());
+                    place(members, &path, base + m.element_offset(i), placed);
+                }
+            }
+        }
+    }
+}
+
+fn layout_block(
+    ercs: &mut [RegisterCluster],
+    path: &BlockPath,
+    index: &Index,
+    config: &Config,
+) -> Result<Vec<LayoutMember>> {
+    let derive_infos = check_erc_derive_infos(ercs, path, index, config)?;
+    let span = Span::call_site();
+    let mut members = Vec::new();
+    for (erc, derive_info) in ercs.iter_mut().zip(derive_infos.iter()) {
+        match erc {
+            RegisterCluster::Register(reg) => {
+                let mut rpath = None;
+                if !matches!(derive_info, DeriveInfo::Implicit(_)) {
+                    if let Some(dpath) = reg.derived_from.take() {
+                        rpath = derive_register(reg, &dpath, path, index)?;
+                    }
+                }
+                let rpath = rpath.unwrap_or_else(|| path.new_register(&reg.name));
+                resolve_fields(reg, &rpath, index)?;
+
+                let rbfs = expand_register(reg, path, derive_info, config)
+                    .with_context(|| format!("can't expand register '{}'", reg.name))?;
+                let module = ident(
+                    &util::name_of(reg, config.ignore_groups),
+                    config,
+                    "register_mod",
+                    span,
+                )
+                .to_string();
+                let mut elements = match reg {
+                    Register::Single(info) => vec![info.clone()],
+                    Register::Array(info, dim) => svd::register::expand(info, dim).collect(),
+                }
+                .into_iter();
+                for (ident, offset, array) in accessors(&rbfs) {
+                    let len = array.map_or(1, |(dim, _)| dim);
+                    let elements: Vec<_> = elements.by_ref().take(len as usize).collect();
+                    if elements.len() != len as usize {
+                        bail!("Register {} has less elements than accessors", reg.name);
+                    }
+                    members.push(LayoutMember {
+                        ident,
+                        offset,
+                        array,
+                        kind: LayoutKind::Register {
+                            module: module.clone(),
+                            elements,
+                        },
+                    });
+                }
+            }
+            RegisterCluster::Cluster(c) => {
+                let mut cpath = None;
+                if let Some(dpath) = c.derived_from.take() {
+                    cpath = derive_cluster(c, &dpath, path, index)?;
+                }
+                let cpath = cpath.unwrap_or_else(|| path.new_cluster(&c.name));
+                let children = layout_block(&mut c.children, &cpath, index, config)?;
+
+                let rbfs = expand_cluster(c, path, config)
+                    .with_context(|| format!("can't expand cluster '{}'", c.name))?;
+                let size = match c {
+                    Cluster::Array(_, dim) if config.max_cluster_size => dim.dim_increment,
+                    _ => cluster_info_size_in_bits(c, path, config)? / BITS_PER_BYTE,
+                };
+                let name = c.name.remove_dim();
+                let module = ident(&name, config, "cluster_mod", span).to_string();
+                let ty = ident(&name, config, "cluster", span).to_string();
+                let mut names = match c {
+                    Cluster::Single(info) => vec![info.name.clone()],
+                    Cluster::Array(info, dim) => svd::array::names(info, dim).collect(),
+                }
+                .into_iter();
+                for (ident, offset, array) in accessors(&rbfs) {
+                    let len = array.map_or(1, |(dim, _)| dim);
+                    let names: Vec<_> = names.by_ref().take(len as usize).collect();
+                    if names.len() != len as usize {
+                        bail!("Cluster {} has less elements than accessors", c.name);
+                    }
+                    members.push(LayoutMember {
+                        ident,
+                        offset,
+                        array,
+                        kind: LayoutKind::Cluster {
+                            module: module.clone(),
+                            ty: ty.clone(),
+                            names,
+                            description: c.description.clone(),
+                            size,
+                            members: children.clone(),
+                        },
+                    });
+                }
+            }
+        }
+    }
+    members.sort_by_key(|m| m.offset);
+    Ok(members)
+}
+
+/// Names, offsets and array lengths and increments of the accessors of `rbfs`
+///
+/// Accessors of single array elements are skipped, the array accessor covers them.
+fn accessors(
+    rbfs: &[RegisterBlockField],
+) -> impl Iterator<Item = (String, u32, Option<(u32, u32)>)> + '_ {
+    rbfs.iter()
+        .flat_map(|rbf| &rbf.accessors)
+        .filter_map(|accessor| match accessor.accessor() {
+            Accessor::Reg(a) => Some((a.name.to_string(), a.offset, None)),
+            Accessor::Array(a) => Some((a.name.to_string(), a.offset, Some((a.dim, a.increment)))),
+            Accessor::ArrayElem(_) => None,
+        })
+}
+
+/// Resolves the `derivedFrom` references of the fields and their enumerated values
+pub(crate) fn resolve_fields(
+    reg: &mut Register,
+    rpath: &RegisterPath,
+    index: &Index,
+) -> Result<()> {
+    let Some(fields) = reg.fields.take() else {
+        return Ok(());
+    };
+    let mut resolved = Vec::with_capacity(fields.len());
+    for mut f in fields {
+        let mut fpath = None;
+        if let Some(dpath) = f.derived_from.take() {
+            fpath = derive_field(&mut f, &dpath, rpath, index)?;
+        }
+        let fpath = fpath.unwrap_or_else(|| rpath.new_field(&f.name));
+        for evs in &mut f.enumerated_values {
+            if let Some(dpath) = evs.derived_from.take() {
+                derive_enumerated_values(evs, &dpath, &fpath, index)?;
+            }
+        }
+        match f {
+            svd::Field::Single(_) => resolved.push(f),
+            svd::Field::Array(info, dim) => {
+                resolved.extend(svd::field::expand(&info, &dim).map(svd::Field::Single))
+            }
+        }
+    }
+    reg.fields = Some(resolved);
+    Ok(())
+}
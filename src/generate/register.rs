@@ -1,7 +1,6 @@
 use crate::svd::{
     self, Access, BitRange, DimElement, EnumeratedValue, EnumeratedValues, Field, MaybeArray,
     ModifiedWriteValues, ReadAction, Register, RegisterProperties, Usage, WriteConstraint,
-    WriteConstraintRange,
 };
 use log::warn;
 use proc_macro2::{Delimiter, Group, Ident, Span, TokenStream};
@@ -13,9 +12,13 @@ use svd_parser::expand::{
     derive_enumerated_values, derive_field, BlockPath, EnumPath, FieldPath, Index, RegisterPath,
 };
 
-use crate::config::Config;
+use crate::config::{Config, DeriveScope};
+use crate::generate::{html, idents, peripheral};
+use crate::hooks;
+use crate::ir::{self, Safety};
 use crate::util::{
-    self, ident, ident_to_path, path_segment, type_path, unsuffixed, DimSuffix, FullName, U32Ext,
+    self, escape_html, ident, ident_to_path, path_segment, type_path, unsuffixed, DimSuffix,
+    FullName, U32Ext,
 };
 use anyhow::{anyhow, Result};
 use syn::punctuated::Punctuated;
@@ -24,28 +27,12 @@ fn regspec(name: &str, config: &Config, span: Span) -> Ident {
     ident(name, config, "register_spec", span)
 }
 
-fn field_accessor(name: &str, config: &Config, span: Span) -> Ident {
-    const INTERNALS: [&str; 2] = ["bits", "set"];
-    let sc = config
-        .ident_formats
-        .get("field_accessor")
-        .unwrap()
-        .sanitize(name);
-    Ident::new(
-        &(if INTERNALS.contains(&sc.as_ref()) {
-            sc + "_"
-        } else {
-            sc
-        }),
-        span,
-    )
-}
-
 pub fn render(
     register: &Register,
     path: &BlockPath,
     dpath: Option<RegisterPath>,
     index: &Index,
+    enum_sets: &mut EnumSets,
     config: &Config,
 ) -> Result<TokenStream> {
     let mut name = util::name_of(register, config.ignore_groups);
@@ -118,6 +105,7 @@ pub fn render(
                 false,
                 register,
                 &rpath,
+                index,
                 config,
             )?,
             read_action_docs(access.can_read(), register.read_action),
@@ -131,10 +119,16 @@ pub fn render(
             #doc_alias
             pub type #reg_ty = crate::Reg<#mod_ty::#regspec_ty>;
         });
-        let mod_items = render_register_mod(register, access, &rpath, index, config)?;
+        let mut mod_items =
+            render_register_mod(register, access, &rpath, index, enum_sets, config)?;
+        mod_items.extend(hooks::register_mod(register, &rpath, &regspec_ty, config)?);
 
+        let mut mod_doc = description.into_owned();
+        if config.bit_layout_docs {
+            mod_doc += &bit_layout_docs(register, &rpath, index, config)?;
+        }
         out.extend(quote! {
-            #[doc = #description]
+            #[doc = #mod_doc]
             pub mod #mod_ty {
                 #mod_items
             }
@@ -170,6 +164,7 @@ fn api_docs(
     inmodule: bool,
     register: &Register,
     rpath: &RegisterPath,
+    index: &Index,
     config: &Config,
 ) -> Result<String, std::fmt::Error> {
     fn method(s: &str) -> String {
@@ -212,7 +207,15 @@ fn api_docs(
 
     doc.push_str("See [API](https://docs.rs/svd2rust/#read--modify--write-api).");
 
-    if let Some(url) = config.settings.html_url.as_ref() {
+    if let Some(url) = config
+        .settings
+        .html_url
+        .as_ref()
+        .filter(|_| config.html_reference)
+    {
+        let anchor = html::register_anchor(register, rpath, index);
+        doc.push_str(&format!("\n\nSee register [structure]({url}#{anchor})"));
+    } else if let Some(url) = config.settings.html_url.as_ref() {
         let first_idx = if let Register::Array(_, dim) = &register {
             dim.indexes().next()
         } else {
@@ -224,7 +227,7 @@ fn api_docs(
         } else {
             rpath.name.clone()
         };
-        // TODO: support html_urls for registers in cluster
+        // svdtools HTML has no anchors for registers in clusters
         if rpath.block.path.is_empty() {
             doc.push_str(&format!(
                 "\n\nSee register [structure]({url}#{}:{})",
@@ -237,11 +240,144 @@ fn api_docs(
     Ok(doc)
 }
 
+/// Table of the field, access and reset value of each bit of the register, followed by tables of
+/// the enumerated values of its fields
+fn bit_layout_docs(
+    register: &Register,
+    rpath: &RegisterPath,
+    index: &Index,
+    config: &Config,
+) -> Result<String> {
+    let mut resolved = register.clone();
+    peripheral::resolve_fields(&mut resolved, rpath, index)?;
+    let reg = ir::register(&resolved, &rpath.to_string(), 0, config)?;
+    let reset = register
+        .properties
+        .reset_value
+        .map(|_| (reg.reset_value, reg.reset_mask));
+
+    let mut doc = String::from("\n\n# Bit layout\n\n<table>\n");
+    // up to 16 bits per row, most significant first
+    let mut high = reg.size;
+    while high > 0 {
+        let low = high.saturating_sub(16);
+        let bits = (low..high).rev();
+
+        doc.push_str("<tr><th>Bit</th>");
+        for bit in bits.clone() {
+            write!(doc, "<th>{bit}</th>")?;
+        }
+        doc.push_str("</tr>\n");
+
+        // consecutive bits covered by the same fields
+        let mut spans: Vec<(Vec<&ir::Field>, u32)> = Vec::new();
+        for bit in bits.clone() {
+            let owners: Vec<_> = reg
+                .fields
+                .iter()
+                .filter(|f| (f.bit_offset..f.bit_offset + f.bit_width).contains(&bit))
+                .collect();
+            match spans.last_mut() {
+                Some((last, width)) if *last == owners => *width += 1,
+                _ => spans.push((owners, 1)),
+            }
+        }
+        doc.push_str("<tr><th>Field</th>");
+        for (owners, width) in &spans {
+            let name = if owners.is_empty() && reg.fields.is_empty() {
+                format!("<code>{}</code>", escape_html(&reg.name))
+            } else if owners.is_empty() {
+                "<i>Res.</i>".to_string()
+            } else {
+                owners
+                    .iter()
+                    .map(|f| format!("<code>{}</code>", escape_html(&f.name)))
+                    .collect::<Vec<_>>()
+                    .join("/")
+            };
+            write!(doc, "<td colspan=\"{width}\">{name}</td>")?;
+        }
+        doc.push_str("</tr>\n<tr><th>Access</th>");
+        for (owners, width) in &spans {
+            let access = match owners.first() {
+                Some(f) => short_access(f.access),
+                None if reg.fields.is_empty() => short_access(reg.access),
+                None => "-",
+            };
+            write!(doc, "<td colspan=\"{width}\">{access}</td>")?;
+        }
+        doc.push_str("</tr>\n");
+
+        if let Some((value, mask)) = reset {
+            doc.push_str("<tr><th>Reset</th>");
+            for bit in bits {
+                let bit = if mask >> bit & 1 == 0 {
+                    "x"
+                } else if value >> bit & 1 == 0 {
+                    "0"
+                } else {
+                    "1"
+                };
+                write!(doc, "<td>{bit}</td>")?;
+            }
+            doc.push_str("</tr>\n");
+        }
+        high = low;
+    }
+    doc.push_str("</table>");
+
+    let mut documented: Vec<&[ir::Enum]> = Vec::new();
+    for f in &reg.fields {
+        let enums = f.enums.as_slice();
+        // elements of field arrays share their enumerated values
+        if enums.is_empty() || documented.contains(&enums) {
+            continue;
+        }
+        documented.push(enums);
+        for e in enums {
+            write!(doc, "\n\n## `{}` values", f.name)?;
+            match e.usage {
+                Usage::Read if f.access.can_write() => doc.push_str(" on read"),
+                Usage::Write if f.access.can_read() => doc.push_str(" on write"),
+                _ => {}
+            }
+            doc.push_str("\n\n| Value | Variant | Description |\n|---|---|---|");
+            for v in &e.values {
+                let value = match v.value {
+                    Some(value) if f.bit_width <= 8 => {
+                        format!("`0b{value:0width$b}`", width = f.bit_width as usize)
+                    }
+                    Some(value) => format!("`{value:#x}`"),
+                    None => "other".to_string(),
+                };
+                let description = v
+                    .description
+                    .as_deref()
+                    .map(|d| util::escape_special_chars(&util::respace(d)).replace('|', "\\|"))
+                    .unwrap_or_default();
+                write!(doc, "\n| {value} | `{}` | {description} |", v.ident)?;
+            }
+        }
+    }
+    Ok(doc)
+}
+
+fn short_access(access: Access) -> &'static str {
+    match access {
+        Access::ReadOnly => "r",
+        Access::WriteOnly => "w",
+        Access::ReadWrite => "rw",
+        Access::WriteOnce => "w1",
+        Access::ReadWriteOnce => "rw1",
+    }
+}
+
 pub fn render_register_mod(
     register: &Register,
     access: Access,
     rpath: &RegisterPath,
     index: &Index,
+    enum_sets: &mut EnumSets,
     config: &Config,
 ) -> Result<TokenStream> {
     let properties = &register.properties;
@@ -309,11 +445,13 @@ pub fn render_register_mod(
             .collect();
 
         if !cur_fields.is_empty() {
+            let accessors = idents::field_accessors(&cur_fields, rpath, config)?;
             if config.impl_debug {
                 r_debug_impl.extend(render_register_mod_debug(
                     register,
                     &access,
                     &cur_fields,
+                    &accessors,
                     config,
                 ))
             }
@@ -331,7 +469,9 @@ pub fn render_register_mod(
                 properties,
                 &mut mod_items,
                 rpath,
+                &accessors,
                 index,
+                enum_sets,
                 config,
             )?;
         }
@@ -371,16 +511,21 @@ pub fn render_register_mod(
 
     let doc = format!(
         "{description}{}{}",
-        api_docs(can_read, can_write, can_reset, &mod_ty, true, register, rpath, config)?,
+        api_docs(can_read, can_write, can_reset, &mod_ty, true, register, rpath, index, config)?,
         read_action_docs(access.can_read(), register.read_action),
     );
 
+    let name_const = config
+        .trace
+        .is_some()
+        .then(|| quote!(const NAME: &'static str = #name;));
     mod_items.extend(quote! {
         #[doc = #doc]
         pub struct #regspec_ty;
 
         impl crate::RegisterSpec for #regspec_ty {
             type Ux = #rty;
+            #name_const
         }
     });
 
@@ -392,26 +537,7 @@ pub fn render_register_mod(
         });
     }
     if can_write {
-        // the writer can be safe if:
-        // * there is a single field that covers the entire register
-        // * that field can represent all values
-        // * the write constraints of the register allow full range of values
-        let safe_ty = if let Safety::Safe = Safety::get(
-            register
-                .fields
-                .as_ref()
-                .and_then(|fields| fields.first())
-                .and_then(|field| field.write_constraint)
-                .as_ref(),
-            rsize,
-        ) {
-            Safety::Safe
-        } else if let Safety::Safe = Safety::get(register.write_constraint.as_ref(), rsize) {
-            Safety::Safe
-        } else {
-            Safety::Unsafe
-        };
-        let safe_ty = safe_ty.ident(rsize);
+        let safe_ty = Safety::of_register(register, rsize).ident(rsize);
 
         let doc = format!("`write(|w| ..)` method takes [`{mod_ty}::W`](W) writer structure",);
 
@@ -443,13 +569,101 @@ pub fn render_register_mod(
             }
         });
     }
+    if let Some(mock) = config.mock.as_ref() {
+        mod_items.extend(render_register_mock(
+            register,
+            access,
+            &regspec_ty,
+            &rty,
+            rsize,
+            mock,
+        ));
+    }
     Ok(mod_items)
 }
 
+fn render_register_mock(
+    register: &Register,
+    access: Access,
+    regspec_ty: &Ident,
+    rty: &Ident,
+    rsize: u32,
+    mock: &str,
+) -> TokenStream {
+    let mut read_clear_bitmap = 0;
+    let mut read_set_bitmap = 0;
+    let mut one_to_set_bitmap = 0;
+    let mut one_to_toggle_bitmap = 0;
+    let mut zero_to_set_bitmap = 0;
+    let mut zero_to_toggle_bitmap = 0;
+    let mut read_only_bitmap = 0;
+
+    match register.read_action {
+        Some(ReadAction::Clear) => read_clear_bitmap = u64::MAX >> (64 - rsize),
+        Some(ReadAction::Set) => read_set_bitmap = u64::MAX >> (64 - rsize),
+        _ => {}
+    }
+    for f in register
+        .fields()
+        .filter(|field| field.name.to_lowercase() != "reserved")
+    {
+        let bitmask = f.bitmask();
+        match f.read_action {
+            Some(ReadAction::Clear) => read_clear_bitmap |= bitmask,
+            Some(ReadAction::Set) => read_set_bitmap |= bitmask,
+            _ => {}
+        }
+        if !access.can_write() {
+            continue;
+        }
+        if f.access == Some(Access::ReadOnly) {
+            // writes keep what the hardware put there
+            read_only_bitmap |= bitmask;
+        } else {
+            use ModifiedWriteValues::*;
+            match f
+                .modified_write_values
+                .or(register.modified_write_values)
+                .unwrap_or_default()
+            {
+                OneToSet => one_to_set_bitmap |= bitmask,
+                OneToToggle => one_to_toggle_bitmap |= bitmask,
+                ZeroToSet => zero_to_set_bitmap |= bitmask,
+                ZeroToToggle => zero_to_toggle_bitmap |= bitmask,
+                _ => {}
+            }
+        }
+    }
+
+    let consts = [
+        ("RESET_VALUE", register.properties.reset_value.unwrap_or(0)),
+        ("READ_CLEAR_BITMAP", read_clear_bitmap),
+        ("READ_SET_BITMAP", read_set_bitmap),
+        ("ONE_TO_SET_BITMAP", one_to_set_bitmap),
+        ("ONE_TO_TOGGLE_BITMAP", one_to_toggle_bitmap),
+        ("ZERO_TO_SET_BITMAP", zero_to_set_bitmap),
+        ("ZERO_TO_TOGGLE_BITMAP", zero_to_toggle_bitmap),
+        ("READ_ONLY_BITMAP", read_only_bitmap),
+    ]
+    .into_iter()
+    .filter_map(|(name, value)| {
+        let name = Ident::new(name, Span::call_site());
+        util::hex_nonzero(value).map(|value| quote!(const #name: #rty = #value;))
+    });
+
+    quote! {
+        #[cfg(feature = #mock)]
+        impl crate::MockSpec for #regspec_ty {
+            #(#consts)*
+        }
+    }
+}
+
 fn render_register_mod_debug(
     register: &Register,
     access: &Access,
     cur_fields: &[&Field],
+    accessors: &idents::Scope,
     config: &Config,
 ) -> Result<TokenStream> {
     let name = util::name_of(register, config.ignore_groups);
@@ -484,7 +698,7 @@ fn render_register_mod_debug(
             if field_access.can_read() && f.read_action.is_none() {
                 if let Field::Array(_, de) = &f {
                     for suffix in de.indexes() {
-                        let f_name_n = field_accessor(&f.name.expand_dim(&suffix), config, span);
+                        let f_name_n = accessors.ident(&f.name.expand_dim(&suffix), config, span);
                         let f_name_n_s = format!("{f_name_n}");
                         fmt_inner_impl.extend(quote! {
                             .field(#f_name_n_s, &self.#f_name_n())
@@ -492,7 +706,7 @@ fn render_register_mod_debug(
                     }
                 } else {
                     let f_name = f.name.remove_dim();
-                    let f_name = field_accessor(&f_name, config, span);
+                    let f_name = accessors.ident(&f_name, config, span);
                     let f_name_s = format!("{f_name}");
                     fmt_inner_impl.extend(quote! {
                         .field(#f_name_s, &self.#f_name())
@@ -522,6 +736,48 @@ fn render_register_mod_debug(
     Ok(r_debug_impl)
 }
 
+/// Enumerated value sets rendered so far, used to derive identical ones
+#[derive(Debug, Default)]
+pub struct EnumSets(Vec<(EnumSetKey, Vec<EnumPath>)>);
+
+/// Everything what the generated enum, reader and writer types of a field depend on
+#[derive(Debug, PartialEq)]
+struct EnumSetKey {
+    /// Peripheral or feature gate the types can be shared in, `None` for the whole crate
+    scope: Option<String>,
+    width: u32,
+    can_read: bool,
+    can_write: bool,
+    modified_write_values: ModifiedWriteValues,
+    write_constraint: Option<WriteConstraint>,
+    read_action: Option<ReadAction>,
+    evs: Vec<EnumeratedValues>,
+}
+
+/// Where the enum types of a register of `rpath` can be shared
+///
+/// Peripherals gated by a cargo feature only share them with peripherals of the same feature, the
+/// types of others may be disabled.
+fn enum_set_scope(
+    scope: DeriveScope,
+    rpath: &RegisterPath,
+    index: &Index,
+    config: &Config,
+) -> Option<String> {
+    let peripheral = &rpath.block.peripheral;
+    if scope == DeriveScope::Peripheral || config.feature_peripheral {
+        return Some(peripheral.clone());
+    }
+    let group_name = index
+        .peripherals
+        .get(&BlockPath::new(peripheral))
+        .and_then(|p| p.group_name.as_ref());
+    match group_name {
+        Some(group_name) if config.feature_group => Some(format!("group {group_name}")),
+        _ => None,
+    }
+}
+
 #[derive(Clone, Copy, Debug)]
 pub enum EV<'a> {
     New(&'a EnumeratedValues),
@@ -617,7 +873,9 @@ pub fn fields(
     properties: &RegisterProperties,
     mod_items: &mut TokenStream,
     rpath: &RegisterPath,
+    accessors: &idents::Scope,
     index: &Index,
+    enum_sets: &mut EnumSets,
     config: &Config,
 ) -> Result<(TokenStream, TokenStream, u64, u64)> {
     let mut r_impl_items = TokenStream::new();
@@ -654,7 +912,8 @@ pub fn fields(
         }
 
         let name = f.name.remove_dim();
-        let name_snake_case = field_accessor(
+        let dname = accessors.name(&name);
+        let name_snake_case = accessors.ident(
             if let Field::Array(
                 _,
                 DimElement {
@@ -670,6 +929,7 @@ pub fn fields(
             config,
             span,
         );
+        mod_items.extend(hooks::field(&f, &fpath, &name_snake_case, config)?);
         let description_raw = f.description.as_deref().unwrap_or(""); // raw description, if absent using empty string
         let description = util::respace(&util::escape_special_chars(description_raw));
 
@@ -713,6 +973,44 @@ pub fn fields(
             lookup_results.push((ev, epath));
         }
 
+        if let Some(scope) = config.derive_identical_enums {
+            // treat the field as derived if the same values were already rendered in scope
+            if !lookup_results.is_empty() && lookup_results.iter().all(|(_, e)| e.is_none()) {
+                let key = EnumSetKey {
+                    scope: enum_set_scope(scope, rpath, index, config),
+                    width,
+                    can_read,
+                    can_write,
+                    modified_write_values: f.modified_write_values.or(rmwv).unwrap_or_default(),
+                    write_constraint: f.write_constraint,
+                    read_action: f.read_action,
+                    evs: lookup_results
+                        .iter()
+                        .map(|(ev, _)| {
+                            let mut ev = ev.clone();
+                            // the docs of the first set are used
+                            ev.name = None;
+                            for v in &mut ev.values {
+                                v.description = None;
+                            }
+                            ev
+                        })
+                        .collect(),
+                };
+                if let Some((_, bases)) = enum_sets.0.iter().find(|(k, _)| k == &key) {
+                    for ((_, epath), base) in lookup_results.iter_mut().zip(bases) {
+                        *epath = Some(base.clone());
+                    }
+                } else {
+                    let epaths = lookup_results
+                        .iter()
+                        .map(|(ev, _)| fpath.new_enum(ev.name.as_deref().unwrap_or(&name)))
+                        .collect();
+                    enum_sets.0.push((key, epaths));
+                }
+            }
+        }
+
         let rwenum = match (
             can_read,
             lookup_filter(&lookup_results, Usage::Read),
@@ -772,9 +1070,9 @@ pub fn fields(
                 // in enumeratedValues if it's an enumeration, or from field name directly if it's not.
                 let value_read_ty = ident(
                     if config.field_names_for_enums {
-                        &name
+                        &dname
                     } else {
-                        ev.values().name.as_deref().unwrap_or(&name)
+                        ev.values().name.as_deref().unwrap_or(&dname)
                     },
                     config,
                     fmt,
@@ -784,14 +1082,15 @@ pub fn fields(
                 match ev {
                     EV::New(evs) => {
                         // parse enum variants from enumeratedValues svd record
-                        let mut variants = Variant::from_enumerated_values(evs, config)?;
+                        let scopes = idents::enum_values(evs, &fpath, config)?;
+                        let mut variants = Variant::from_enumerated_values(evs, &scopes, config)?;
 
                         let map = enums_to_map(evs);
                         let mut def = evs
                             .default_value()
                             .and_then(|def| {
                                 minimal_hole(&map, width)
-                                    .map(|v| Variant::from_value(v, def, config))
+                                    .map(|v| Variant::from_value(v, def, &scopes, config))
                             })
                             .transpose()?;
                         if variants.len() == 1 << width {
@@ -931,7 +1230,12 @@ pub fn fields(
                     }
                     EV::Derived(_, base) => {
                         let base_ident = if config.field_names_for_enums {
-                            ident(&base.field().name.remove_dim(), config, fmt, span)
+                            ident(
+                                &base_field_name(base, rpath, accessors, index, config)?,
+                                config,
+                                fmt,
+                                span,
+                            )
                         } else {
                             ident(&base.name, config, fmt, span)
                         };
@@ -956,7 +1260,7 @@ pub fn fields(
             let field_reader_brief = format!("Field `{name}{brief_suffix}` reader - {description}");
 
             // name of read proxy type
-            let reader_ty = ident(&name, config, "field_reader", span);
+            let reader_ty = ident(&accessors.name(&name), config, "field_reader", span);
 
             match rwenum.read_enum() {
                 Some(EV::New(_)) | None => {
@@ -994,7 +1298,7 @@ pub fn fields(
                     // and value if necessary.
 
                     // generate pub use field_1 reader as field_2 reader
-                    let base_field = base.field.name.remove_dim();
+                    let base_field = base_field_name(base, rpath, accessors, index, config)?;
                     let base_r = ident(&base_field, config, "field_reader", span);
                     if !reader_derives.contains(&reader_ty) {
                         let base_path = base_syn_path(base, &fpath, &base_r, config)?;
@@ -1056,7 +1360,7 @@ pub fn fields(
                     } else {
                         value
                     };
-                    let name_snake_case_n = field_accessor(&fi.name, config, span);
+                    let name_snake_case_n = accessors.ident(&fi.name, config, span);
                     let doc = description_with_bits(
                         fi.description.as_deref().unwrap_or(&fi.name),
                         sub_offset,
@@ -1124,9 +1428,9 @@ pub fn fields(
                 };
                 let value_write_ty = ident(
                     if config.field_names_for_enums {
-                        &name
+                        &dname
                     } else {
-                        ev.values().name.as_deref().unwrap_or(&name)
+                        ev.values().name.as_deref().unwrap_or(&dname)
                     },
                     config,
                     fmt,
@@ -1136,13 +1440,14 @@ pub fn fields(
                 match ev {
                     EV::New(evs) => {
                         // parse variants from enumeratedValues svd record
-                        let mut variants = Variant::from_enumerated_values(evs, config)?;
+                        let scopes = idents::enum_values(evs, &fpath, config)?;
+                        let mut variants = Variant::from_enumerated_values(evs, &scopes, config)?;
                         let map = enums_to_map(evs);
                         let mut def = evs
                             .default_value()
                             .and_then(|def| {
                                 minimal_hole(&map, width)
-                                    .map(|v| Variant::from_value(v, def, config))
+                                    .map(|v| Variant::from_value(v, def, &scopes, config))
                             })
                             .transpose()?;
                         // if the write structure is finite, it can be safely written.
@@ -1194,7 +1499,12 @@ pub fn fields(
                     }
                     EV::Derived(_, base) => {
                         let base_ident = if config.field_names_for_enums {
-                            ident(&base.field().name.remove_dim(), config, fmt, span)
+                            ident(
+                                &base_field_name(base, rpath, accessors, index, config)?,
+                                config,
+                                fmt,
+                                span,
+                            )
                         } else {
                             ident(&base.name, config, fmt, span)
                         };
@@ -1220,7 +1530,7 @@ pub fn fields(
             let field_writer_brief = format!("Field `{name}{brief_suffix}` writer - {description}");
 
             // name of write proxy type
-            let writer_ty = ident(&name, config, "field_writer", span);
+            let writer_ty = ident(&accessors.name(&name), config, "field_writer", span);
 
             // Generate writer structure by type alias to generic write proxy structure.
             match rwenum.write_enum() {
@@ -1263,7 +1573,7 @@ pub fn fields(
                 }
                 Some(EV::Derived(_, base)) => {
                     // generate pub use field_1 writer as field_2 writer
-                    let base_field = base.field.name.remove_dim();
+                    let base_field = base_field_name(base, rpath, accessors, index, config)?;
                     let base_w = ident(&base_field, config, "field_writer", span);
                     if !writer_derives.contains(&writer_ty) {
                         let base_path = base_syn_path(base, &fpath, &base_w, config)?;
@@ -1322,7 +1632,7 @@ pub fn fields(
 
                 for fi in svd::field::expand(f, de) {
                     let sub_offset = fi.bit_offset() as u64;
-                    let name_snake_case_n = field_accessor(&fi.name, config, span);
+                    let name_snake_case_n = accessors.ident(&fi.name, config, span);
                     let doc = description_with_bits(
                         fi.description.as_deref().unwrap_or(&fi.name),
                         sub_offset,
@@ -1372,52 +1682,6 @@ pub fn fields(
     ))
 }
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
-enum Safety {
-    Unsafe,
-    Range(WriteConstraintRange),
-    Safe,
-}
-
-impl Safety {
-    fn get(write_constraint: Option<&WriteConstraint>, width: u32) -> Self {
-        match &write_constraint {
-            Some(&WriteConstraint::Range(range))
-                if range.min == 0 && range.max == u64::MAX >> (64 - width) =>
-            {
-                // the SVD has acknowledged that it's safe to write
-                // any value that can fit in the field
-                Self::Safe
-            }
-            None if width == 1 => {
-                // the field is one bit wide, so we assume it's legal to write
-                // either value into it or it wouldn't exist; despite that
-                // if a writeConstraint exists then respect it
-                Self::Safe
-            }
-            Some(&WriteConstraint::Range(range)) => Self::Range(range),
-            _ => Self::Unsafe,
-        }
-    }
-    fn ident(&self, width: u32) -> TokenStream {
-        match self {
-            Self::Safe => quote!(Safe),
-            Self::Unsafe => quote!(Unsafe),
-            Self::Range(range) => {
-                let min = unsuffixed(range.min);
-                let max = unsuffixed(range.max);
-                if range.min == 0 {
-                    quote!(RangeTo<#max>)
-                } else if range.max == u64::MAX >> (64 - width) {
-                    quote!(RangeFrom<#min>)
-                } else {
-                    quote!(Range<#min, #max>)
-                }
-            }
-        }
-    }
-}
-
 struct Variant {
     doc: String,
     pc: Ident,
@@ -1427,7 +1691,11 @@ struct Variant {
 }
 
 impl Variant {
-    fn from_enumerated_values(evs: &EnumeratedValues, config: &Config) -> Result<Vec<Self>> {
+    fn from_enumerated_values(
+        evs: &EnumeratedValues,
+        scopes: &(idents::Scope, idents::Scope),
+        config: &Config,
+    ) -> Result<Vec<Self>> {
         evs.values
             .iter()
             // filter out all reserved variants, as we should not
@@ -1437,14 +1705,20 @@ impl Variant {
                 let value = ev
                     .value
                     .ok_or_else(|| anyhow!("EnumeratedValue {} has no `<value>` entry", ev.name))?;
-                Self::from_value(value, ev, config)
+                Self::from_value(value, ev, scopes, config)
             })
             .collect()
     }
-    fn from_value(value: u64, ev: &EnumeratedValue, config: &Config) -> Result<Self> {
+    fn from_value(
+        value: u64,
+        ev: &EnumeratedValue,
+        (pcs, scs): &(idents::Scope, idents::Scope),
+        config: &Config,
+    ) -> Result<Self> {
         let span = Span::call_site();
         let case = config.ident_formats.get("enum_value_accessor").unwrap();
-        let nksc = case.apply(&ev.name);
+        let name = scs.name(&ev.name);
+        let nksc = case.apply(&name);
         let is_sc = Ident::new(
             &if nksc.to_string().starts_with('_') {
                 format!("is{nksc}")
@@ -1453,32 +1727,14 @@ impl Variant {
             },
             span,
         );
-        let sc = case.sanitize(&ev.name);
-        const INTERNALS: [&str; 7] = [
-            "bit",
-            "bits",
-            "clear_bit",
-            "set",
-            "set_bit",
-            "variant",
-            "offset",
-        ];
-        let sc = Ident::new(
-            &(if INTERNALS.contains(&sc.as_ref()) {
-                sc + "_"
-            } else {
-                sc
-            }),
-            span,
-        );
         Ok(Variant {
             doc: ev
                 .description
                 .clone()
                 .unwrap_or_else(|| format!("`{value:b}`")),
-            pc: ident(&ev.name, config, "enum_value", span),
+            pc: pcs.ident(&ev.name, config, span),
             is_sc,
-            sc,
+            sc: scs.ident(&ev.name, config, span),
             value,
         })
     }
@@ -1621,6 +1877,32 @@ fn description_with_bits(description: &str, offset: u64, width: u32) -> String {
     res
 }
 
+/// Name of the field `base` belongs to, disambiguated like its accessor
+fn base_field_name(
+    base: &EnumPath,
+    rpath: &RegisterPath,
+    accessors: &idents::Scope,
+    index: &Index,
+    config: &Config,
+) -> Result<String> {
+    let name = base.field().name.remove_dim();
+    if !config.disambiguate_idents {
+        return Ok(name.into_owned());
+    }
+    if base.register() == rpath {
+        return Ok(accessors.name(&name).into_owned());
+    }
+    let Some(register) = index.registers.get(base.register()) else {
+        return Ok(name.into_owned());
+    };
+    let fields: Vec<_> = register
+        .fields()
+        .filter(|f| f.name.to_lowercase() != "reserved")
+        .collect();
+    let accessors = idents::field_accessors(&fields, base.register(), config)?;
+    Ok(accessors.name(&name).into_owned())
+}
+
 fn base_syn_path(
     base: &EnumPath,
     fpath: &FieldPath,
@@ -1671,3 +1953,39 @@ fn enums_to_map(evs: &EnumeratedValues) -> BTreeMap<u64, &EnumeratedValue> {
 fn minimal_hole(map: &BTreeMap<u64, &EnumeratedValue>, width: u32) -> Option<u64> {
     (0..(1u64 << width)).find(|&v| !map.contains_key(&v))
 }
+
+#[test]
+fn mock_keeps_read_only_bits() {
+    let register = svd_parser::parse(
+        r#"<device><name>D</name><peripherals><peripheral><name>USART1</name>
+        <baseAddress>0x40013800</baseAddress><registers><register><name>SR</name>
+        <addressOffset>0x0</addressOffset><size>32</size><resetValue>0xC0</resetValue>
+        <fields>
+        <field><name>TXE</name><bitOffset>7</bitOffset><bitWidth>1</bitWidth><access>read-only</access></field>
+        <field><name>TC</name><bitOffset>6</bitOffset><bitWidth>1</bitWidth>
+        <modifiedWriteValues>oneToClear</modifiedWriteValues></field>
+        <field><name>RXNE</name><bitOffset>5</bitOffset><bitWidth>1</bitWidth><access>read-only</access></field>
+        </fields></register></registers></peripheral></peripherals></device>"#,
+    )
+    .unwrap()
+    .peripherals[0]
+        .registers()
+        .next()
+        .unwrap()
+        .clone();
+    let span = Span::call_site();
+    let mock = render_register_mock(
+        &register,
+        Access::ReadWrite,
+        &Ident::new("SR_SPEC", span),
+        &Ident::new("u32", span),
+        32,
+        "mock",
+    )
+    .to_string();
+    assert!(
+        mock.contains("const READ_ONLY_BITMAP : u32 = 0xa0 ;"),
+        "{mock}"
+    );
+    assert!(!mock.contains("ONE_TO_SET_BITMAP"), "{mock}");
+}
@@ -0,0 +1,97 @@
+//! Flat map of the registers of a device with their absolute addresses
+//!
+//! Registers are laid out by the same code as the generated `RegisterBlock`s, so addresses in
+//! the map are the ones the generated accessors use.
+
+use anyhow::{Context, Result};
+use svd_parser::expand::Index;
+
+use crate::config::Config;
+use crate::generate::{filter, peripheral};
+use crate::ir;
+use crate::svd::{self, Device, Peripheral};
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct RegisterMap {
+    pub device: String,
+    /// Sorted by address
+    pub registers: Vec<MappedRegister>,
+}
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct MappedRegister {
+    /// SVD path with the names of the array elements, like `DMA1.CH0.CR`
+    pub path: String,
+    pub peripheral: String,
+    #[cfg_attr(feature = "serde", serde(flatten))]
+    pub register: ir::Register,
+}
+
+impl RegisterMap {
+    /// Registers of every peripheral instance of `device`, with `base_address_shift` applied
+    pub fn new(device: &Device, config: &Config) -> Result<Self> {
+        let filtered;
+        let device = if filter::is_active(config) {
+            filtered = filter::filter(device, config)?;
+            &filtered
+        } else {
+            device
+        };
+        let index = Index::create(device);
+
+        let mut registers = Vec::new();
+        for p in &device.peripherals {
+            let placed = peripheral::place_registers(p, &index, config)
+                .with_context(|| format!("Error laying out peripheral {}", p.name))?;
+            let instances: Vec<_> = match p {
+                Peripheral::Single(info) => vec![(info.name.clone(), info.base_address)],
+                Peripheral::Array(info, dim) => svd::peripheral::expand(info, dim)
+                    .map(|pi| (pi.name, pi.base_address))
+                    .collect(),
+            };
+            for (name, base_address) in instances {
+                for r in &placed {
+                    let path = format!("{name}.{}", r.path.join("."));
+                    let address = base_address + config.base_address_shift + u64::from(r.offset);
+                    registers.push(MappedRegister {
+                        register: ir::register(&r.info, &path, address, config)
+                            .with_context(|| format!("Error resolving register {path}"))?,
+                        path,
+                        peripheral: name.clone(),
+                    });
+                }
+            }
+        }
+        registers.sort_by_key(|r| r.register.address);
+        let mut end = 0;
+        for r in &mut registers {
+            let register = &mut r.register;
+            register.overlaps = register.address < end;
+            end = end.max(register.address + u64::from(register.size).div_ceil(8));
+        }
+
+        Ok(Self {
+            device: device.name.clone(),
+            registers,
+        })
+    }
+}
+
+/// JSON of the register map of one device, or of the list of maps of a family
+#[cfg(all(feature = "serde", feature = "json"))]
+pub fn render(devices: &[Device], config: &Config) -> Result<String> {
+    let maps = devices
+        .iter()
+        .map(|device| RegisterMap::new(device, config))
+        .collect::<Result<Vec<_>>>()?;
+    let json = if config.family.is_empty() && maps.len() == 1 {
+        serde_json::to_string_pretty(&maps[0])
+    } else {
+        serde_json::to_string_pretty(&maps)
+    };
+    json.context("Error serializing register map")
+}
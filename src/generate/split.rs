@@ -0,0 +1,60 @@
+//! Splitting of the generated code into module files
+
+use std::mem::take;
+use std::path::{Path, PathBuf};
+
+use quote::quote;
+use syn::{ext::IdentExt, AttrStyle, File, Item};
+
+/// Default size in bytes above which cluster and register modules get their own file
+pub const DEFAULT_THRESHOLD: usize = 64 * 1024;
+
+/// Moves inline modules of the generated code into their own files
+///
+/// Every module in the root of `file` is moved out, nested modules only when their code is larger
+/// than `threshold` bytes. Returns the module files with paths relative to the directory of the
+/// root file, `file` is left with `mod name;` declarations.
+pub fn split(file: &mut File, threshold: usize) -> Vec<(PathBuf, File)> {
+    let mut files = Vec::new();
+    split_items(&mut file.items, Path::new(""), threshold, true, &mut files);
+    files
+}
+
+fn split_items(
+    items: &mut [Item],
+    dir: &Path,
+    threshold: usize,
+    root: bool,
+    files: &mut Vec<(PathBuf, File)>,
+) {
+    for item in items {
+        let Item::Mod(m) = item else {
+            continue;
+        };
+        let Some((_, content)) = m.content.as_mut() else {
+            continue;
+        };
+        if !root && quote!(#(#content)*).to_string().len() <= threshold {
+            continue;
+        }
+
+        let name = m.ident.unraw().to_string();
+        let mut items = take(content);
+        split_items(&mut items, &dir.join(&name), threshold, false, files);
+        // inner attributes belong to the module file
+        let (attrs, outer) = take(&mut m.attrs)
+            .into_iter()
+            .partition(|attr| matches!(attr.style, AttrStyle::Inner(_)));
+        m.attrs = outer;
+        m.content = None;
+        m.semi = Some(Default::default());
+        files.push((
+            dir.join(format!("{name}.rs")),
+            File {
+                shebang: None,
+                attrs,
+                items,
+            },
+        ));
+    }
+}
@@ -0,0 +1,206 @@
+//! Custom items added to the generated code
+//!
+//! A [`Hooks`] implementation set as `hooks` in the [`Config`] is called for every
+//! peripheral, register block, register and field and can return items to add next to them.
+//! `extra_items` in the config file adds the same items declaratively.
+
+use std::fmt;
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use proc_macro2::{Ident, TokenStream};
+use quote::quote;
+
+use crate::config::{Config, ExtraItem, ExtraItemKind};
+use crate::svd::{FieldInfo, PeripheralInfo, RegisterCluster, RegisterInfo};
+use svd_parser::expand::{BlockPath, FieldPath, RegisterPath};
+
+/// Callbacks returning items to splice into the generated code
+///
+/// Elements are passed after their `derivedFrom` references are resolved. Items returned for
+/// a peripheral go to the root of the crate, gated like the peripheral by `--feature-group` and
+/// `--feature-peripheral`, all others to the module of the element.
+pub trait Hooks: Send + Sync {
+    /// Called once per peripheral and for each element of peripheral arrays, `ident` is its type
+    fn on_peripheral(
+        &self,
+        _peripheral: &PeripheralInfo,
+        _path: &BlockPath,
+        _ident: &Ident,
+    ) -> Result<TokenStream> {
+        Ok(TokenStream::new())
+    }
+
+    /// Called for the register block of each peripheral and cluster module, `ident` is its type
+    fn on_register_block(
+        &self,
+        _children: &[RegisterCluster],
+        _path: &BlockPath,
+        _ident: &Ident,
+    ) -> Result<TokenStream> {
+        Ok(TokenStream::new())
+    }
+
+    /// Called for each register module, `ident` is the register spec type
+    fn on_register_mod(
+        &self,
+        _register: &RegisterInfo,
+        _path: &RegisterPath,
+        _ident: &Ident,
+    ) -> Result<TokenStream> {
+        Ok(TokenStream::new())
+    }
+
+    /// Called for each field, `ident` is its accessor, the items go to the register module
+    fn on_field(
+        &self,
+        _field: &FieldInfo,
+        _path: &FieldPath,
+        _ident: &Ident,
+    ) -> Result<TokenStream> {
+        Ok(TokenStream::new())
+    }
+}
+
+/// [`Hooks`] implementation shared by clones of a [`Config`]
+///
+/// Configs compare equal when they share the same implementation.
+#[derive(Clone)]
+pub struct SharedHooks(pub Arc<dyn Hooks>);
+
+impl SharedHooks {
+    pub fn new(hooks: impl Hooks + 'static) -> Self {
+        Self(Arc::new(hooks))
+    }
+}
+
+impl fmt::Debug for SharedHooks {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("SharedHooks(..)")
+    }
+}
+
+impl PartialEq for SharedHooks {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for SharedHooks {}
+
+/// Items of the peripheral, each with the `cfg` attributes of the peripheral in `feature_attribute`
+pub(crate) fn peripheral(
+    peripheral: &PeripheralInfo,
+    path: &BlockPath,
+    ident: &Ident,
+    feature_attribute: &TokenStream,
+    config: &Config,
+) -> Result<TokenStream> {
+    let mut out = extra_items(
+        config,
+        ExtraItemKind::Peripheral,
+        &peripheral.name,
+        &path.to_string(),
+        ident,
+    )?;
+    if let Some(hooks) = config.hooks.as_ref() {
+        out.extend(hooks.0.on_peripheral(peripheral, path, ident)?);
+    }
+    if out.is_empty() || feature_attribute.is_empty() {
+        return Ok(out);
+    }
+    let file = syn::parse2::<syn::File>(out)
+        .with_context(|| format!("Invalid items added to peripheral `{path}`"))?;
+    Ok(file
+        .items
+        .iter()
+        .map(|item| quote!(#feature_attribute #item))
+        .collect())
+}
+
+pub(crate) fn register_block(
+    children: &[RegisterCluster],
+    path: &BlockPath,
+    ident: &Ident,
+    config: &Config,
+) -> Result<TokenStream> {
+    let mut out = extra_items(
+        config,
+        ExtraItemKind::RegisterBlock,
+        path.path.last().unwrap_or(&path.peripheral),
+        &path.to_string(),
+        ident,
+    )?;
+    if let Some(hooks) = config.hooks.as_ref() {
+        out.extend(hooks.0.on_register_block(children, path, ident)?);
+    }
+    Ok(out)
+}
+
+pub(crate) fn register_mod(
+    register: &RegisterInfo,
+    path: &RegisterPath,
+    ident: &Ident,
+    config: &Config,
+) -> Result<TokenStream> {
+    let mut out = extra_items(
+        config,
+        ExtraItemKind::RegisterMod,
+        &register.name,
+        &path.to_string(),
+        ident,
+    )?;
+    if let Some(hooks) = config.hooks.as_ref() {
+        out.extend(hooks.0.on_register_mod(register, path, ident)?);
+    }
+    Ok(out)
+}
+
+pub(crate) fn field(
+    field: &FieldInfo,
+    path: &FieldPath,
+    ident: &Ident,
+    config: &Config,
+) -> Result<TokenStream> {
+    let mut out = extra_items(
+        config,
+        ExtraItemKind::Field,
+        &field.name,
+        &path.to_string(),
+        ident,
+    )?;
+    if let Some(hooks) = config.hooks.as_ref() {
+        out.extend(hooks.0.on_field(field, path, ident)?);
+    }
+    Ok(out)
+}
+
+/// Code of the `extra_items` of `kind` matching `path`, with the placeholders filled in
+fn extra_items(
+    config: &Config,
+    kind: ExtraItemKind,
+    name: &str,
+    path: &str,
+    ident: &Ident,
+) -> Result<TokenStream> {
+    let mut out = TokenStream::new();
+    let segments: Vec<_> = path.split('.').collect();
+    for item in config.extra_items.iter().filter(|item| item.on == kind) {
+        if !item.pattern.matches(&segments) {
+            continue;
+        }
+        out.extend(expand(item, name, path, ident)?);
+    }
+    Ok(out)
+}
+
+fn expand(item: &ExtraItem, name: &str, path: &str, ident: &Ident) -> Result<TokenStream> {
+    let code = item
+        .code
+        .replace("{name}", name)
+        .replace("{path}", path)
+        .replace("{ident}", &ident.to_string());
+    code.parse::<TokenStream>()
+        .map_err(|e| anyhow::anyhow!("{e}"))
+        .with_context(|| format!("Invalid code of extra item for `{}`", item.path))
+}
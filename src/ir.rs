@@ -0,0 +1,250 @@
+//! Resolved model of registers and their fields
+//!
+//! A [`Register`] has the access, reset value and write [`Safety`] of the register and of each
+//! field, the enumerated values of the fields and the identifiers of the generated code. The
+//! register map, the C header, the GDB description and the bit layout docs of the generated
+//! registers are built from it. With the `serde` feature it can be serialized itself.
+
+use anyhow::Result;
+use proc_macro2::TokenStream;
+use quote::quote;
+
+use crate::config::Config;
+use crate::generate::idents;
+use crate::svd::{
+    self, Access, ModifiedWriteValues, ReadAction, Usage, WriteConstraint, WriteConstraintRange,
+};
+use crate::util::{self, unsuffixed, FullName};
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Register {
+    pub name: String,
+    /// Accessor method in the register block
+    pub ident: String,
+    pub description: Option<String>,
+    pub alternate_group: Option<String>,
+    /// Offset in bytes from the start of the peripheral or cluster
+    pub offset: u64,
+    pub address: u64,
+    /// Shares addresses with a previous register of the register map
+    pub overlaps: bool,
+    /// Size in bits
+    pub size: u32,
+    pub access: Access,
+    pub reset_value: u64,
+    pub reset_mask: u64,
+    pub read_action: Option<ReadAction>,
+    pub modified_write_values: Option<ModifiedWriteValues>,
+    /// Values `write` and `modify` accept for the whole register, `None` if it can't be written
+    pub write_safety: Option<Safety>,
+    /// Sorted by bit offset
+    pub fields: Vec<Field>,
+}
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Field {
+    pub name: String,
+    /// Accessor method of the register reader and writer
+    pub ident: String,
+    pub description: Option<String>,
+    pub bit_offset: u32,
+    pub bit_width: u32,
+    pub access: Access,
+    pub read_action: Option<ReadAction>,
+    pub modified_write_values: Option<ModifiedWriteValues>,
+    /// Values the field writer accepts, `None` if it can't be written
+    pub write_safety: Option<Safety>,
+    pub enums: Vec<Enum>,
+}
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Enum {
+    pub name: Option<String>,
+    pub usage: Usage,
+    pub values: Vec<EnumValue>,
+}
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct EnumValue {
+    pub name: String,
+    /// Variant of the generated enum
+    pub ident: String,
+    pub description: Option<String>,
+    /// `None` for the default value
+    pub value: Option<u64>,
+}
+
+/// Values which can be written without `unsafe`
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Safety {
+    Unsafe,
+    Range(WriteConstraintRange),
+    Safe,
+}
+
+impl Safety {
+    pub fn get(write_constraint: Option<&WriteConstraint>, width: u32) -> Self {
+        match &write_constraint {
+            Some(&WriteConstraint::Range(range))
+                if range.min == 0 && range.max == u64::MAX >> (64 - width) =>
+            {
+                // the SVD has acknowledged that it's safe to write
+                // any value that can fit in the field
+                Self::Safe
+            }
+            None if width == 1 => {
+                // the field is one bit wide, so we assume it's legal to write
+                // either value into it or it wouldn't exist; despite that
+                // if a writeConstraint exists then respect it
+                Self::Safe
+            }
+            Some(&WriteConstraint::Range(range)) => Self::Range(range),
+            _ => Self::Unsafe,
+        }
+    }
+
+    /// Whether `write` can take any value of a register of `size` bits without `unsafe`
+    ///
+    /// It can if there is a single field that covers the entire register and that field can
+    /// represent all values, or if the write constraints of the register allow the full range
+    /// of values.
+    pub fn of_register(register: &svd::RegisterInfo, size: u32) -> Self {
+        let first_field = register
+            .fields
+            .as_ref()
+            .and_then(|fields| fields.first())
+            .and_then(|field| field.write_constraint);
+        if Self::get(first_field.as_ref(), size) == Self::Safe
+            || Self::get(register.write_constraint.as_ref(), size) == Self::Safe
+        {
+            Self::Safe
+        } else {
+            Self::Unsafe
+        }
+    }
+
+    pub(crate) fn ident(&self, width: u32) -> TokenStream {
+        match self {
+            Self::Safe => quote!(Safe),
+            Self::Unsafe => quote!(Unsafe),
+            Self::Range(range) => {
+                let min = unsuffixed(range.min);
+                let max = unsuffixed(range.max);
+                if range.min == 0 {
+                    quote!(RangeTo<#max>)
+                } else if range.max == u64::MAX >> (64 - width) {
+                    quote!(RangeFrom<#min>)
+                } else {
+                    quote!(Range<#min, #max>)
+                }
+            }
+        }
+    }
+}
+
+fn ident(name: &str, config: &Config, fmt: &str) -> String {
+    util::ident_str(name, config.ident_formats.get(fmt).unwrap())
+}
+
+pub(crate) fn register(
+    r: &svd::RegisterInfo,
+    rpath: &str,
+    address: u64,
+    config: &Config,
+) -> Result<Register> {
+    let size = r.properties.size.unwrap_or(32);
+    let access = util::access_of(&r.properties, r.fields.as_deref());
+    let svd_fields: Vec<_> = r
+        .fields()
+        .filter(|f| f.name.to_lowercase() != "reserved")
+        .collect();
+    let accessors = idents::field_accessors(&svd_fields, &rpath, config)?;
+
+    let mut fields = Vec::new();
+    for f in svd_fields {
+        let fpath = format!("{rpath}.{}", f.name);
+        let faccess = f.access.unwrap_or(access);
+        let width = f.bit_range.width;
+        let mut enums = Vec::new();
+        for evs in &f.enumerated_values {
+            let (pcs, _) = idents::enum_values(evs, &fpath, config)?;
+            enums.push(Enum {
+                name: evs.name.clone(),
+                usage: evs.usage().unwrap_or(Usage::ReadWrite),
+                values: evs
+                    .values
+                    .iter()
+                    .filter(|ev| ev.name.to_lowercase() != "reserved")
+                    .map(|ev| EnumValue {
+                        name: ev.name.clone(),
+                        ident: pcs
+                            .ident(&ev.name, config, proc_macro2::Span::call_site())
+                            .to_string(),
+                        description: ev.description.clone(),
+                        value: ev.value,
+                    })
+                    .collect(),
+            });
+        }
+        let write_safety = faccess.can_write().then(|| {
+            // a write enum covering all values or with a default one makes any value safe
+            let complete = enums.iter().any(|e| {
+                e.usage != Usage::Read
+                    && (e.values.iter().any(|v| v.value.is_none())
+                        || e.values.len() as u64 >= 1 << width)
+            });
+            if complete {
+                Safety::Safe
+            } else {
+                Safety::get(f.write_constraint.as_ref(), width)
+            }
+        });
+        fields.push(Field {
+            name: f.name.clone(),
+            ident: accessors
+                .ident(&f.name, config, proc_macro2::Span::call_site())
+                .to_string(),
+            description: f.description.clone(),
+            bit_offset: f.bit_range.offset,
+            bit_width: width,
+            access: faccess,
+            read_action: f.read_action,
+            modified_write_values: f.modified_write_values,
+            write_safety,
+            enums,
+        });
+    }
+    fields.sort_by_key(|f| f.bit_offset);
+
+    let name = r.fullname(config.ignore_groups);
+    Ok(Register {
+        name: r.name.clone(),
+        ident: ident(&name, config, "register_accessor"),
+        description: r.description.clone(),
+        alternate_group: r.alternate_group.clone(),
+        offset: u64::from(r.address_offset),
+        address,
+        overlaps: false,
+        size,
+        access,
+        reset_value: r.properties.reset_value.unwrap_or(0),
+        reset_mask: r.properties.reset_mask.unwrap_or(u64::MAX >> (64 - size)),
+        read_action: r.read_action,
+        modified_write_values: r.modified_write_values,
+        write_safety: access.can_write().then(|| Safety::of_register(r, size)),
+        fields,
+    })
+}
@@ -30,6 +30,34 @@
 //! [`form`](https://github.com/djmcgill/form) commit fcb397a or newer is required for splitting
 //! the emitted `lib.rs`.
 //!
+//! Instead of using `form`, the `--split` flag makes `svd2rust` itself write each module of the
+//! crate root (peripherals, `generic`, `interrupt`) into its own file, with `mod` declarations left
+//! in `lib.rs`. Nested cluster and register modules larger than `--split-threshold` bytes (64 KiB
+//! by default) are moved into files in a directory named after their parent module.
+//!
+//! ``` text
+//! $ svd2rust -i STM32F30x.svd --split -o src
+//!
+//! $ cargo fmt
+//! ```
+//!
+//! The `cargo fmt` step can be dropped by passing `--format`, which pretty-prints the generated
+//! files with [`prettyplease`](https://crates.io/crates/prettyplease). It is also used by the
+//! [`generate()`] function when `format` is set in the [`Config`]. This requires the `format` cargo
+//! feature of `svd2rust`, which the `bin` feature enables.
+//!
+//! With the `--crate` flag `svd2rust` generates a complete crate in the output directory: the
+//! code goes to `src/`, `build.rs` and `device.x` (if the target needs them) next to a
+//! `Cargo.toml` which lists the dependencies described below for the chosen target and options
+//! (`--atomics`, `--impl-defmt`, `--add-cortex-m-int-num`, ...), the `rt` feature wiring and the
+//! features of `--feature-group` / `--feature-peripheral`. The crate is named after the device.
+//!
+//! ``` text
+//! $ svd2rust -i STM32F30x.svd --crate --split --format -o stm32f30x
+//!
+//! $ cd stm32f30x && cargo build
+//! ```
+//!
 //! ## target = cortex-m
 //!
 //! When targeting the Cortex-M architecture, `svd2rust` will generate three files in the current
@@ -322,6 +350,64 @@
 //!       - { register: "CLKCTRL.MCLKCTRLA", magic: 0xD8 }
 //! ```
 //!
+//! ## Patching the SVD file
+//!
+//! Fixes to the SVD file can be passed with `--patch my_device.yaml` instead of running
+//! [svdtools] first. The patch is applied after the device is loaded, the format follows
+//! svdtools: on every level `_delete` removes, `_modify` changes, `_add` creates and `_derive`
+//! derives elements, all other keys are name patterns (`*`, `?`, `[...]`, comma separated
+//! alternatives) selecting peripherals, registers or clusters whose contents are patched in turn.
+//! New and changed elements use the same properties as YAML device files. On the register level
+//! field patterns replace the enumerated values of the fields, as `VARIANT: [value, "description"]`
+//! (`default` for all other values), split into `_read` and `_write` when they differ, or as
+//! the name of enumerated values to derive from.
+//!
+//! ```yaml
+//! _modify:
+//!   cpu:
+//!     fpuPresent: true
+//!   USART2:
+//!     description: Universal synchronous asynchronous receiver transmitter
+//! _delete: [TIM4]
+//! _derive:
+//!   USART3:
+//!     _from: USART1
+//!     baseAddress: 0x40004800
+//! USART1:
+//!   _add:
+//!     _interrupts:
+//!       USART1_WKUP: { value: 42 }
+//!   SR:
+//!     _modify:
+//!       TXE: { access: read-only }
+//!   CR1:
+//!     M:
+//!       Bits8: [0, "8 data bits"]
+//!       Bits9: [1, "9 data bits"]
+//! ```
+//!
+//! With `--family` the patch is applied to every chip.
+//!
+//! [svdtools]: https://github.com/rust-embedded/svdtools
+//!
+//! ## Using `svd2rust` as a library
+//!
+//! [`Builder`] generates the same code in memory, for example from the `build.rs` of a crate
+//! which doesn't check in the generated code. [`Generation`] holds every generated file and
+//! writes them with [`Generation::write`], like the binary does, or to `OUT_DIR` with
+//! [`Generation::write_out_dir`] to `include!` the code at the root of the crate. The code
+//! needs to be in a single file for that, so `--split` and `--generic-mod` can't be used.
+//!
+//! [`ir::Register`] is the resolved model of a register and its fields, with access, write safety
+//! and identifiers, which the register map, C header and GDB outputs are built from. It can be
+//! serialized with the `serde` feature.
+//!
+//! The `device!` macro of the `svd2rust-macro` crate expands to the generated code directly,
+//! taking the config fields as arguments:
+//! ```ignore
+//! svd2rust_macro::device!("chip.svd", target = "none", impl_debug = true);
+//! ```
+//!
 //! ## Rust editions
 //!
 //! Default rust edition for generated code is 2021. Pass `--edition=2024` if you want to
@@ -443,6 +529,19 @@
 //! }
 //! ```
 //!
+//! ## Peripherals at runtime address
+//!
+//! When the address of a peripheral is only known at runtime, for example on FPGA soft-cores or in
+//! Linux userspace drivers which `mmap` the peripheral window from `/dev/mem` or UIO, an instance
+//! can be created from a base pointer with `from_ptr`. It returns `DynPeriph` which `deref`s to
+//! the same `RegisterBlock` as the peripheral proxy.
+//!
+//! ```ignore
+//! let base = unsafe { libc::mmap(/* .. */) };
+//! let uart = unsafe { pac::Usart1::from_ptr(base.cast()) };
+//! uart.cr1().modify(|_, w| w.ue().set_bit());
+//! ```
+//!
 //! # `read` / `modify` / `write` API
 //!
 //! Each register in the register block, e.g. the `cr1` field in the `I2C` struct, exposes a
@@ -726,6 +825,304 @@
 //! The `--impl-defmt` flag can also be specified to include `defmt::Format` implementations conditionally
 //! behind the supplied feature name.
 //!
+//! ## the `--pointer-access` flag
+//!
+//! By default register blocks are `#[repr(C)]` structures placed over device memory and registers
+//! are accessed through references to `VolatileCell`s. With `--pointer-access` register blocks and
+//! registers are instead generated as thin `Copy` wrappers around raw pointers, which use
+//! `read_volatile`/`write_volatile` and never create Rust references to device memory.
+//!
+//! The `R`/`W` and field proxy API stays the same. Register and cluster accessors return values
+//! instead of references, so most code using the PAC compiles unchanged:
+//!
+//! ```ignore
+//! let dp = pac::Peripherals::take().unwrap();
+//! dp.usart1.cr1().modify(|_, w| w.ue().set_bit());
+//! for ch in dp.dma1.ch_iter() {
+//!     ch.cr().reset();
+//! }
+//! // register blocks can be also created from any address
+//! let usart = unsafe { pac::usart1::RegisterBlock::from_ptr(0x4001_3800 as *mut u8) };
+//! ```
+//!
+//! As the register block is a pointer itself, `Periph::PTR` and `Periph::ptr()` point to a
+//! constant register block holding the address of the peripheral, not to its registers. The
+//! address of the registers is `PeripheralSpec::ADDRESS`, or `as_ptr()` of the register block.
+//!
+//! ## the `--instance-traits` flag
+//!
+//! Peripherals derived from the same base share its `RegisterBlock`, but their `PeripheralSpec`
+//! types are unrelated. With `--instance-traits` the module of each register block gets an
+//! `Instance` trait which is implemented for every peripheral using it, so drivers can be written
+//! once for the whole family. Besides the `RegisterBlock`, `ADDRESS` and `NAME` of the
+//! `PeripheralSpec` supertrait, it exposes the position of the instance in the family (`INDEX`,
+//! counted in the order of the SVD file) and the interrupts declared for the instance
+//! (`INTERRUPTS`).
+//!
+//! ```ignore
+//! pub struct Serial<I: pac::usart1::Instance> {
+//!     usart: pac::generic::Periph<I>,
+//! }
+//!
+//! impl<I: pac::usart1::Instance> Serial<I> {
+//!     pub fn listen(&mut self) {
+//!         self.usart.cr1().modify(|_, w| w.rxneie().set_bit());
+//!         for &irq in I::INTERRUPTS {
+//!             unsafe { cortex_m::peripheral::NVIC::unmask(irq) };
+//!         }
+//!     }
+//! }
+//! ```
+//!
+//! ## the `--derive-identical` flag
+//!
+//! Vendor SVD files often describe identical peripherals without `derivedFrom`, which makes
+//! svd2rust generate the same `RegisterBlock` module several times. With `--derive-identical`
+//! a peripheral or cluster whose expanded registers are equal to those of a previous one is
+//! rendered as if it was derived from it: its module becomes a re-export of the first one and only
+//! the peripheral proxy (name, address, interrupts) stays its own. Each merge is reported in the
+//! log.
+//!
+//! Blocks which already have `derivedFrom`, and blocks which are referenced from a `derivedFrom`
+//! path of another element, are left untouched. With `--feature-group` or `--feature-peripheral`
+//! blocks are only derived from blocks behind the same cargo feature.
+//!
+//! ## the `--derive-identical-enums` flag
+//!
+//! The same `enumeratedValues` (e.g. `Disabled`/`Enabled`) are often repeated for many fields, and
+//! each of them gets its own enum, reader and writer types. With `--derive-identical-enums
+//! peripheral` (or `crate`) a field whose enumerated values are identical to a field which was
+//! already rendered in the same peripheral (or anywhere in the crate) refers to the enum, reader
+//! and writer of that field, as if `derivedFrom` was used. The values are then interchangeable
+//! between the fields:
+//!
+//! ```ignore
+//! // `te` and `re` have the same `Disabled`/`Enabled` values
+//! let te = dp.usart1.cr1().read().te().variant();
+//! dp.usart1.cr1().modify(|_, w| w.re().variant(te));
+//! ```
+//!
+//! The names of the value sets and the descriptions of the values are not compared, the docs of
+//! the first field are used. The bit width, access and write constraints of the fields have to
+//! match. With `--feature-peripheral` (or `--feature-group`) the crate scope is limited to the
+//! peripheral (or group), so a field never refers to types of a peripheral which may be disabled.
+//!
+//! ## the `--family` flag
+//!
+//! `--family FILE...` takes the SVD files of several chips of a family instead of `-i` and
+//! generates one crate for all of them. Each chip gets a cargo feature named after the device
+//! (formatted like `peripheral_feature`) and exactly one of them has to be enabled. Everything
+//! that is the same in all chips is generated once, peripherals, registers, fields, enumerated
+//! values and interrupts which exist only in some of the chips or differ between them are gated
+//! by `#[cfg(feature = "chip")]` attributes:
+//!
+//! ```ignore
+//! pub struct Peripherals {
+//!     pub usart1: Usart1,
+//!     #[cfg(feature = "stm32f103")]
+//!     pub usart3: Usart3,
+//! }
+//! ```
+//!
+//! The chips are rendered one by one and the generated items are compared, so all other options
+//! apply to every chip. With `--crate` the chip features are added to `Cargo.toml`, and the
+//! `device.x` linker script contains the interrupts of all chips.
+//!
+//! ``` text
+//! $ svd2rust --family stm32f101.svd stm32f103.svd --crate -o stm32f1
+//! ```
+//!
+//! ## the `--include` and `--exclude` flags
+//!
+//! `--include PATTERN` generates only the peripherals, clusters and registers matching one of
+//! the patterns, `--exclude PATTERN` leaves out those matching. Both can be passed several times
+//! or set as lists in the config file. Patterns are paths of names like `GPIO*` or `USB.EP*`, with
+//! `*`, `?` and `[...]` matching within one level of the path, or regular expressions between
+//! slashes matched against the whole path, like `/USART[0-9]+/`. A matching element is kept
+//! or dropped with everything inside of it, exclusion takes precedence over inclusion.
+//!
+//! Elements a kept element is derived from are always kept, so `--include USART2` also
+//! generates `USART1` when `USART2` is `derivedFrom="USART1"`.
+//!
+//! ```toml
+//! include = ["RCC", "GPIO*", "USART1"]
+//! exclude = ["GPIO*.LCKR"]
+//! ```
+//!
+//! ## the `--mock` flag
+//!
+//! The `--mock FEATURE` flag adds an alternative register backend behind the supplied feature name.
+//! With the feature enabled peripherals are backed by a simulated register file in host memory
+//! instead of their device addresses, so code using the PAC can be unit-tested with `cargo test`.
+//! The simulation follows reset values, `modifiedWriteValues` (e.g. `oneToClear`) and `readAction`
+//! of registers and fields, and writes leave the bits of read-only fields as they are, so status
+//! flags set with `poke` survive writes to other fields. Registers are thread local, so tests running in parallel don't see
+//! each other's writes. Mock mode is not available for the `avr` target.
+//!
+//! The `generic::mock` module provides functions to control the simulation:
+//!
+//! ```ignore
+//! use pac::generic::mock;
+//!
+//! let p = unsafe { pac::Peripherals::steal() };
+//! // simulate hardware setting a status flag
+//! mock::poke(p.usart1.sr(), 0x20);
+//! // observe bits written to a register
+//! mock::on_write(p.usart1.dr(), |written, value| {
+//!     println!("sent {written:#x}");
+//!     value
+//! });
+//! // supply values read from a register
+//! mock::on_read(p.usart1.dr(), |_| 0x55);
+//! // put all registers back to reset state and remove hooks
+//! mock::reset();
+//! ```
+//!
+//! The generated crate must be built with `std` available when the feature is enabled.
+//!
+//! ## the `--trace` flag
+//!
+//! The `--trace FEATURE` flag adds register access tracing behind the supplied feature name.
+//! With the feature enabled every `read`, `write`, `modify`, `write_with_zero`, `reset` and atomic
+//! `set_bits`/`clear_bits`/`toggle_bits` call is reported to a sink installed with
+//! `generic::trace::set_sink`. Reports contain kind of access, address, peripheral and register
+//! names and value. Without the feature the tracing code compiles to nothing.
+//!
+//! `critical-section` is used to store the sink, so the tracing feature must enable it:
+//!
+//! ```toml
+//! [features]
+//! trace = ["critical-section"]
+//! ```
+//!
+//! ```ignore
+//! use pac::generic::trace::{self, Event, Sink};
+//!
+//! struct Logger;
+//! impl Sink for Logger {
+//!     fn event(&self, e: &Event) {
+//!         log::trace!("{e:x?}");
+//!     }
+//! }
+//!
+//! trace::set_sink(&Logger);
+//! ```
+//!
+//! ## the `--register-map` flag
+//!
+//! The `--register-map` flag writes `registers.json` next to `lib.rs`, a flat list of every
+//! register of the device sorted by address. Arrays of registers, clusters and peripherals are
+//! expanded to their elements, derived peripherals get their own entries and
+//! `--base-address-shift` is applied. The addresses come from the same layout as the generated
+//! `RegisterBlock`s. Each entry has the SVD path of the register, its size, access, reset value
+//! and mask, and its fields with their bit ranges and enumerated values, shortened here:
+//!
+//! ```json
+//! {
+//!   "device": "STM32F103",
+//!   "registers": [
+//!     {
+//!       "path": "DMA1.CH0.CR",
+//!       "peripheral": "DMA1",
+//!       "address": 1073872904,
+//!       "size": 32,
+//!       "access": "read-write",
+//!       "reset_value": 0,
+//!       "fields": [{ "name": "EN", "bit_offset": 0, "bit_width": 1, "enums": [] }]
+//!     }
+//!   ]
+//! }
+//! ```
+//!
+//! With `--family` the file holds a list with the map of each device.
+//!
+//! ## the `--gdb-xml` flag
+//!
+//! The `--gdb-xml` flag writes `gdb-target.xml` and `gdb-memory-map.xml` next to `lib.rs`, so
+//! debuggers can show the peripheral registers without the SVD file. They are built from the
+//! same register map as `--register-map`.
+//!
+//! `gdb-target.xml` is a target description `<feature>` with a `<reg>` named after the SVD path
+//! of each register, like `DMA1_CH0_CR`, grouped by peripheral. Registers with fields get a
+//! `<flags>` type with their bit ranges, and fields with enumerated values an `<enum>`. The
+//! address of each register is noted in a comment before it. `gdb-memory-map.xml` describes the
+//! address range of each peripheral as a `ram` region, so GDB allows reading it.
+//!
+//! With `--family` the files of each device are prefixed with its name.
+//!
+//! ## the `--c-header` flag
+//!
+//! The `--c-header` flag writes a CMSIS style C header named after the device, like
+//! `stm32f103.h`, next to `lib.rs`. Its structs have the layout of the generated
+//! `RegisterBlock`s, with `union`s for registers sharing addresses, and its identifiers are the
+//! ones of the Rust API joined by `_`, so ident formats and renames apply to both:
+//!
+//! ```c
+//! typedef struct {
+//!   __IOM uint32_t sr;  /*!< (@ 0x00000000) Status register */
+//!   /* ... */
+//! } usart1_RegisterBlock;
+//!
+//! #define usart1_sr_txe_Pos 7U
+//! #define usart1_sr_txe_Msk (0x1UL << usart1_sr_txe_Pos)
+//! #define usart1_cr1_ps_Even 0x0UL
+//!
+//! #define Usart1_ADDRESS 0x40011000UL
+//! #define Usart1 ((usart1_RegisterBlock *)Usart1_ADDRESS)
+//! ```
+//!
+//! Clusters get a struct named after their Rust path, like `dma1_Ch`, and interrupts are listed
+//! in `IRQn_Type` with an `_IRQn` suffix. Derived peripherals use the struct of the peripheral
+//! they derive from.
+//!
+//! ## the `lint` subcommand
+//!
+//! `svd2rust lint -i stm32f103.svd` checks the device instead of generating code. Unlike
+//! `--strict`, which stops at the first problem, it walks the whole device and prints everything
+//! it finds, one line per problem with its severity, path and code:
+//!
+//! ``` text
+//! error: GPIOB.IDR: register has no size and neither has its peripheral or the device [missing-size]
+//! warning: TIM2.CCMR1_Input: shares addresses with `CCMR1_Output`, the register block gets a union [overlapping-registers]
+//! ```
+//!
+//! Errors, like registers without size, fields outside their register, enumerated values wider
+//! than their field, unresolved `derivedFrom` references or colliding identifiers, keep the code
+//! from being generated or make it wrong. Warnings, like missing descriptions, overlapping fields
+//! or registers, or reset values setting bits no field covers, make the generated API or docs
+//! worse. Overlapping registers declared as alternates are only reported as info.
+//!
+//! The other flags apply, so the check matches the generated code: `svd2rust lint
+//! --disambiguate-idents` doesn't report identifiers it disambiguates, and `--patch` and the
+//! filters are applied first. `--json` prints the diagnostics as JSON instead, an array of
+//! reports for families. `svd2rust lint` fails when it finds errors, and with `--deny-warnings`
+//! also when it finds warnings, so CI can gate SVD updates on it.
+//!
+//! ## the `--html-reference` flag
+//!
+//! The `--html-reference` flag writes a static HTML reference of the registers named after the
+//! device, like `stm32f103.html`, next to `lib.rs`. It has an index of the peripherals, and for
+//! each peripheral instance its register map with offsets and reserved gaps, the fields of each
+//! register with their bit ranges and access, and the enumerated values of the fields. Derived
+//! peripherals link to the peripheral they are derived from. Registers are laid out like with
+//! `--register-map`, so arrays are expanded to their elements.
+//!
+//! Set the `html_url` setting to where the reference is published, and the links in the docs of
+//! peripherals and registers point to it, including registers in clusters, which the svdtools
+//! HTML has no anchors for. Each register has the anchor `PERIPHERAL:CLUSTER.REGISTER`, like
+//! `DMA1:CH0.CR`. Without `html_url` the reference is written, but the docs don't link to it.
+//!
+//! ## the `--bit-layout-docs` flag
+//!
+//! The `--bit-layout-docs` flag adds a "Bit layout" section to the docs of each register module,
+//! a table with the field, access and reset value of every bit, most significant first. Bits no
+//! field covers are marked `Res.`, and bits the reset mask leaves undefined `x`. Access is
+//! abbreviated to `r`, `w`, `rw`, `w1` or `rw1` for write-once. A table of the enumerated values
+//! with their variants follows for each field which has them.
+//!
+//! The tables are built from the SVD file, so the docs no longer need to link to the HTML of the
+//! `html_url` setting for this information.
+//!
 //! ## the `--ident-format` and `--ident-formats-theme` flags
 //!
 //! The `--ident-format type:prefix:case:suffix` (`-f`) flag can also be specified if you want to change
@@ -761,21 +1158,96 @@
 //! ```
 //!
 //! To revert old behavior for all identifiers you may pass `--ident-formats-theme legacy`.
+//!
+//! Names can also be rewritten with regular expressions before they are formatted, for
+//! example to strip prefixes repeating the name of the peripheral. `ident_renames` in the config
+//! file takes a list of rewrites for each identifier type of the table, applied in order, `to`
+//! may refer to groups of `from`:
+//! ```toml
+//! [[ident_renames.register]]
+//! from = "^USART[0-9]+_"
+//! to = ""
+//!
+//! [[ident_renames.field_accessor]]
+//! from = "^TIM_(CCMR[0-9]_)"
+//! to = "$1"
+//! ```
+//!
+//! Generation fails if a rewrite gives two elements of the same scope the same identifier, the
+//! error names the SVD paths of both.
+//!
+//! The identifiers of register accessors, field accessors and enumerated values are computed
+//! for each scope before rendering. Different names formatted to the same identifier, like
+//! the fields `TC` and `tc` of one register, make generation fail with the SVD paths of all
+//! collisions. With `--disambiguate-idents` (`disambiguate_idents = true` in the config file)
+//! the later names get `_1`, `_2` and so on appended instead, for example `tc_1()` and `Tc1R`.
+//! Accessors of register blocks are never renumbered.
+//!
+//! ## the `extra_items` config option
+//!
+//! Items can be added to the generated code for elements matching a path pattern, with the
+//! same `*` and `?` wildcards as `--include`. `on` is one of `peripheral` (items go to the root
+//! of the crate with the `cfg`s of the peripheral, once for each element of peripheral arrays),
+//! `register_block` (items go to the peripheral or cluster module), `register_mod`
+//! and `field` (items go to the register module). `{name}`, `{path}` and `{ident}` in `code` are
+//! replaced with the SVD name, the SVD path and the generated type or accessor of the element:
+//! ```toml
+//! [[extra_items]]
+//! on = "peripheral"
+//! path = "USART*"
+//! code = "impl crate::Uart for {ident} {}"
+//!
+//! [[extra_items]]
+//! on = "register_mod"
+//! path = "*.SR"
+//! code = "pub const SVD_PATH: &str = \"{path}\";"
+//! ```
+//!
+//! Library users can compute the items in code by setting `hooks` in the [`Config`] to an
+//! implementation of [`hooks::Hooks`].
 #![recursion_limit = "128"]
 
-use quote::quote;
 use svd_parser::svd;
 
+pub mod builder;
 pub mod config;
 pub mod generate;
+pub mod hooks;
+pub mod ir;
+pub mod lint;
+#[cfg(all(feature = "serde", feature = "yaml"))]
+pub mod patch;
 pub mod util;
 
+pub use builder::Builder;
 pub use config::{Config, Settings, Target};
 
+use std::path::{Path, PathBuf};
+
+/// Everything generated for a device, in memory
 #[non_exhaustive]
 pub struct Generation {
+    /// `lib.rs`, or `mod.rs` with `make_mod`
     pub lib_rs: String,
+    /// Module files moved out of `lib_rs` by `split`, with paths relative to it
+    pub modules: Vec<(PathBuf, String)>,
+    /// `generic.rs` when `generic_mod` is set
+    pub generic_rs: Option<String>,
     pub device_specific: Option<DeviceSpecific>,
+    /// `[features]` entries of the generated code, with `feature_group` or `feature_peripheral`
+    pub features: Vec<String>,
+    /// `Cargo.toml` when `make_crate` is set
+    pub cargo_toml: Option<String>,
+    /// `registers.json` when `register_map` is set
+    pub register_map: Option<String>,
+    /// GDB target description and memory map when `gdb_xml` is set, with paths relative to
+    /// `lib_rs`
+    pub gdb_xml: Vec<(PathBuf, String)>,
+    /// C header of each device when `c_header` is set, named after the device
+    pub c_headers: Vec<(PathBuf, String)>,
+    /// HTML reference of each device when `html_reference` is set, named after the device
+    pub html_reference: Vec<(PathBuf, String)>,
+    lib_name: &'static str,
 }
 
 #[non_exhaustive]
@@ -784,6 +1256,111 @@ pub struct DeviceSpecific {
     pub build_rs: String,
 }
 
+impl Generation {
+    /// Writes all files to `dir` the way the `svd2rust` binary does
+    ///
+    /// Source files, `registers.json`, the GDB files, C headers and HTML references go to `dir/src` when
+    /// `make_crate` is set, `device.x`, `build.rs`, `Cargo.toml` and `features.toml` go to `dir`.
+    pub fn write(&self, dir: &Path) -> Result<()> {
+        let src = if self.cargo_toml.is_some() {
+            dir.join("src")
+        } else {
+            dir.to_path_buf()
+        };
+        std::fs::create_dir_all(&src)
+            .with_context(|| format!("Could not create {}", src.display()))?;
+        for (module, data) in &self.modules {
+            let module = src.join(module);
+            if let Some(dir) = module.parent() {
+                std::fs::create_dir_all(dir)?;
+            }
+            std::fs::write(&module, data)
+                .with_context(|| format!("Could not write code to {}", module.display()))?;
+        }
+        std::fs::write(src.join(self.lib_name), &self.lib_rs)
+            .with_context(|| format!("Could not write code to {}", self.lib_name))?;
+        if let Some(generic_rs) = self.generic_rs.as_ref() {
+            std::fs::write(src.join("generic.rs"), generic_rs)
+                .context("Could not write code to generic.rs")?;
+        }
+        if let Some(register_map) = self.register_map.as_ref() {
+            std::fs::write(src.join("registers.json"), register_map)
+                .context("Could not write registers.json")?;
+        }
+        for (file, data) in self
+            .gdb_xml
+            .iter()
+            .chain(&self.c_headers)
+            .chain(&self.html_reference)
+        {
+            std::fs::write(src.join(file), data)
+                .with_context(|| format!("Could not write {}", file.display()))?;
+        }
+        if let Some(specific) = self.device_specific.as_ref() {
+            std::fs::write(dir.join("device.x"), format!("{}\n", specific.device_x))
+                .context("Could not write device.x")?;
+            std::fs::write(dir.join("build.rs"), format!("{}\n", specific.build_rs))
+                .context("Could not write build.rs")?;
+        }
+        if let Some(cargo_toml) = self.cargo_toml.as_ref() {
+            std::fs::write(dir.join("Cargo.toml"), cargo_toml)
+                .context("Could not write Cargo.toml")?;
+        } else if !self.features.is_empty() {
+            std::fs::write(
+                dir.join("features.toml"),
+                format!(
+                    "# Below are the FEATURES generated by svd2rust base on groupName in SVD file.\n\
+                    # Please copy them to Cargo.toml.\n\
+                    [features]\n\
+                    {}\n",
+                    self.features.join("\n")
+                ),
+            )
+            .context("Could not write features.toml")?;
+        }
+        Ok(())
+    }
+
+    /// Writes the code to `$OUT_DIR/lib.rs` from a build script, returns its path
+    ///
+    /// Inner attributes are left out so the file can be `include!`d at the root of a crate which
+    /// declares `#![no_std]` itself. `device.x`, `registers.json`, the GDB files, C headers and HTML
+    /// references are written next to it and `OUT_DIR` is added to the linker search path. The code has to be in
+    /// one file, without `split` and `generic_mod`.
+    pub fn write_out_dir(&self) -> Result<PathBuf> {
+        use quote::ToTokens;
+
+        if !self.modules.is_empty() || self.generic_rs.is_some() {
+            anyhow::bail!("Code split into several files can't be included from `OUT_DIR`");
+        }
+        let out_dir = PathBuf::from(std::env::var_os("OUT_DIR").context("OUT_DIR is not set")?);
+        let mut file = syn::parse_file(&self.lib_rs).context("Error parsing generated code")?;
+        file.attrs.clear();
+        let lib_rs = out_dir.join("lib.rs");
+        std::fs::write(&lib_rs, file.into_token_stream().to_string())
+            .context("Could not write code to lib.rs")?;
+        if let Some(register_map) = self.register_map.as_ref() {
+            std::fs::write(out_dir.join("registers.json"), register_map)
+                .context("Could not write registers.json")?;
+        }
+        for (file, data) in self
+            .gdb_xml
+            .iter()
+            .chain(&self.c_headers)
+            .chain(&self.html_reference)
+        {
+            std::fs::write(out_dir.join(file), data)
+                .with_context(|| format!("Could not write {}", file.display()))?;
+        }
+        if let Some(specific) = self.device_specific.as_ref() {
+            std::fs::write(out_dir.join("device.x"), format!("{}\n", specific.device_x))
+                .context("Could not write device.x")?;
+            println!("cargo:rustc-link-search={}", out_dir.display());
+        }
+        Ok(lib_rs)
+    }
+}
+
 use anyhow::{Context, Result};
 
 use crate::config::{IdentFormats, IdentFormatsTheme};
@@ -798,8 +1375,15 @@ pub enum SvdError {
 
 /// Generates rust code for the specified svd content.
 pub fn generate(input: &str, config: &Config) -> Result<Generation> {
-    use std::fmt::Write;
+    let config = prepare_config(config)?;
+    let device = load_from(input, &config)?;
+    render(&[device], &config).map_err(|e| SvdError::Render(e).into())
+}
 
+/// Applies the settings file, identifier format theme and renames of `config`
+///
+/// The binary does the same while parsing its arguments.
+pub(crate) fn prepare_config(config: &Config) -> Result<Config> {
     let mut config = config.clone();
 
     match config.settings_file.as_ref() {
@@ -823,40 +1407,132 @@ pub fn generate(input: &str, config: &Config) -> Result<Generation> {
     };
     ident_formats.extend(config.ident_formats.drain());
     config.ident_formats = ident_formats;
+    config.ident_formats.add_renames(&config.ident_renames)?;
+    Ok(config)
+}
+
+/// Generates all files for loaded devices, a family of devices if there are several
+pub fn render(devices: &[svd::Device], config: &Config) -> Result<Generation> {
+    use config::Target;
+    use quote::ToTokens;
+
+    if config.make_crate && config.make_mod {
+        return Err(anyhow::anyhow!(
+            "`--crate` can't be combined with `--make-mod`"
+        ));
+    }
 
-    let device = load_from(input, &config)?;
     let mut device_x = String::new();
-    let items =
-        generate::device::render(&device, &config, &mut device_x).map_err(SvdError::Render)?;
-
-    let mut lib_rs = String::new();
-    writeln!(
-        &mut lib_rs,
-        "{}",
-        quote! {
-            #items
-        }
-    )
-    .or(Err(SvdError::Fmt))?;
+    let items = if config.family.is_empty() && devices.len() == 1 {
+        generate::device::render(&devices[0], config, &mut device_x)
+            .with_context(|| "Error rendering device")?
+    } else {
+        generate::family::render(devices, config, &mut device_x)
+            .with_context(|| "Error rendering family")?
+    };
 
-    let device_specific = if device_x.is_empty() {
-        None
+    let mut modules = Vec::new();
+    let lib_rs = if config.split {
+        let mut root = syn::parse2(items).context("Error parsing generated code")?;
+        let threshold = config
+            .split_threshold
+            .unwrap_or(generate::split::DEFAULT_THRESHOLD);
+        for (module, file) in generate::split::split(&mut root, threshold) {
+            modules.push((module, util::source(file.into_token_stream(), config)?));
+        }
+        util::source(root.into_token_stream(), config)?
     } else {
+        util::source(items, config)?
+    };
+
+    let device_specific = if [
+        Target::CortexM,
+        Target::Msp430,
+        Target::XtensaLX,
+        Target::RISCV,
+    ]
+    .contains(&config.target)
+    {
         Some(DeviceSpecific {
             device_x,
-            build_rs: util::build_rs(&config).to_string(),
+            build_rs: util::source(util::build_rs(config), config)?,
         })
+    } else {
+        None
+    };
+
+    let (features, cargo_toml) = if config.make_crate {
+        (
+            Vec::new(),
+            Some(generate::manifest::render(devices, config)?),
+        )
+    } else if config.feature_group || config.feature_peripheral {
+        (generate::manifest::device_features(devices, config)?, None)
+    } else {
+        (Vec::new(), None)
+    };
+
+    let register_map = match config.register_map {
+        #[cfg(all(feature = "serde", feature = "json"))]
+        true => Some(generate::register_map::render(devices, config)?),
+        #[cfg(not(all(feature = "serde", feature = "json")))]
+        true => {
+            return Err(anyhow::anyhow!("Support for register maps is not available because svd2rust was compiled without the serde and json features"));
+        }
+        false => None,
+    };
+    let gdb_xml = if config.gdb_xml {
+        generate::gdb::render(devices, config)?
+    } else {
+        Vec::new()
     };
+    let mut c_headers = Vec::new();
+    if config.c_header {
+        for device in devices {
+            let header = generate::c_header::render(device, config)
+                .with_context(|| format!("Error rendering C header of {}", device.name))?;
+            c_headers.push((
+                PathBuf::from(format!("{}.h", device.name.to_lowercase())),
+                header,
+            ));
+        }
+    }
+
+    let mut html_reference = Vec::new();
+    if config.html_reference {
+        for device in devices {
+            let page = generate::html::render(device, config)
+                .with_context(|| format!("Error rendering HTML reference of {}", device.name))?;
+            html_reference.push((html_reference_file(device), page));
+        }
+    }
 
     Ok(Generation {
         lib_rs,
+        modules,
+        generic_rs: config
+            .generic_mod
+            .then(|| generate::device::generic_mod(config)),
         device_specific,
+        features,
+        cargo_toml,
+        register_map,
+        gdb_xml,
+        c_headers,
+        html_reference,
+        lib_name: if config.make_mod { "mod.rs" } else { "lib.rs" },
     })
 }
 
+fn html_reference_file(device: &svd::Device) -> PathBuf {
+    PathBuf::from(format!("{}.html", device.name.to_lowercase()))
+}
+
 /// Load a [Device](svd::Device) from a string slice with given [config](crate::config::Config).
+///
+/// The `patch_file` of `config` is applied before the register properties are propagated to
+/// the registers, so patched properties are inherited like the ones of the file.
 pub fn load_from(input: &str, config: &Config) -> Result<svd::Device> {
-    use config::SourceType;
     use svd_parser::ValidateLevel;
 
     let validate_level = if config.strict {
@@ -864,6 +1540,15 @@ pub fn load_from(input: &str, config: &Config) -> Result<svd::Device> {
     } else {
         ValidateLevel::Weak
     };
+    load_with(input, config, validate_level)
+}
+
+pub(crate) fn load_with(
+    input: &str,
+    config: &Config,
+    validate_level: svd_parser::ValidateLevel,
+) -> Result<svd::Device> {
+    use config::SourceType;
 
     let mut device = match config.source_type {
         SourceType::Xml => {
@@ -888,10 +1573,28 @@ pub fn load_from(input: &str, config: &Config) -> Result<svd::Device> {
             device
         }
     };
+    if let Some(patch) = config.patch_file.as_ref() {
+        apply_patch_file(&mut device, patch)?;
+    }
     svd_parser::expand_properties(&mut device);
     Ok(device)
 }
 
+#[cfg(all(feature = "serde", feature = "yaml"))]
+fn apply_patch_file(device: &mut svd::Device, path: &std::path::Path) -> Result<()> {
+    let file = std::fs::read_to_string(path).context("could not read patch file")?;
+    log::info!("Patching device {}", device.name);
+    patch::Patch::from_yaml(&file)
+        .context("could not parse patch file")?
+        .apply(device)
+        .with_context(|| format!("Error patching device {}", device.name))
+}
+
+#[cfg(not(all(feature = "serde", feature = "yaml")))]
+fn apply_patch_file(_device: &mut svd::Device, _path: &std::path::Path) -> Result<()> {
+    Err(anyhow::anyhow!("Support for patch files is not available because svd2rust was compiled without the yaml feature"))
+}
+
 /// Assigns a handler to an interrupt
 ///
 /// **NOTE** The `interrupt!` macro on Cortex-M and MSP430 device crates is closer in syntax to the
@@ -0,0 +1,468 @@
+//! Problems of a device description, for `svd2rust lint`
+//!
+//! Unlike generating code, which stops at the first error, [`Report::new`] walks the whole device
+//! and collects everything it finds: elements `svd2rust` can't generate, like registers without
+//! size, fields outside their register, enumerated values wider than their field or colliding
+//! identifiers, and ones which make the generated code worse, like overlapping registers, missing
+//! descriptions or reset values setting bits no field covers.
+
+use std::collections::HashSet;
+use std::fmt;
+
+use anyhow::Result;
+use svd_parser::expand::{
+    derive_enumerated_values, derive_field, derive_peripheral, BlockPath, Index, RegisterPath,
+};
+
+use crate::config::Config;
+use crate::generate::peripheral::{self, LayoutKind, LayoutMember};
+use crate::generate::{device, filter, idents};
+use crate::svd::{self, Device, RegisterCluster};
+
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize),
+    serde(rename_all = "snake_case")
+)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    /// Worth knowing, like registers declared as alternates of each other
+    Info,
+    /// Generated, but with a worse API or docs
+    Warning,
+    /// Not generated, or generated wrong
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Info => "info",
+            Self::Warning => "warning",
+            Self::Error => "error",
+        })
+    }
+}
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Diagnostic {
+    pub severity: Severity,
+    /// Kind of the problem, like `overlapping-registers`
+    pub code: &'static str,
+    /// SVD path of the element, like `DMA1.CH[%s].CR`
+    pub path: String,
+    pub message: String,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: {}: {} [{}]",
+            self.severity, self.path, self.message, self.code
+        )
+    }
+}
+
+/// Diagnostics of one device, in the order of the elements
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Report {
+    pub device: String,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl Report {
+    /// Checks `device` as it is generated with `config`
+    pub fn new(device: &Device, config: &Config) -> Result<Self> {
+        let filtered;
+        let device = if filter::is_active(config) {
+            filtered = filter::filter(device, config)?;
+            &filtered
+        } else {
+            device
+        };
+        let index = Index::create(device);
+        let mut lint = Lint::default();
+
+        if let Err(e) = idents::check_renames(device, config) {
+            lint.push(Severity::Error, "name-collision", &device.name, e);
+        }
+        for (path, message) in idents::collisions(device, config) {
+            lint.push(Severity::Error, "name-collision", path, message);
+        }
+
+        for p in &device.peripherals {
+            if let Some(dpath) = p.derived_from.as_ref() {
+                // the registers are checked in the peripheral they are derived from
+                if let Err(e) = derive_peripheral(&mut p.clone(), dpath, &index) {
+                    lint.push(Severity::Error, "unresolved-derivation", &p.name, e);
+                }
+                continue;
+            }
+            if p.description.is_none() {
+                lint.missing_description("peripheral", &p.name);
+            }
+            let path = BlockPath::new(&p.name);
+            let errors = lint.errors();
+            lint.block(p.registers.as_deref().unwrap_or_default(), &path, &index);
+            // the layout fails on the errors found so far
+            if lint.errors() == errors {
+                match peripheral::layout(p, &index, config) {
+                    Ok(members) => lint.overlaps(&members, &p.name),
+                    Err(e) => lint.push(Severity::Error, "layout", &p.name, format!("{e:#}")),
+                }
+            }
+        }
+
+        // whatever else keeps the code from being generated
+        if lint.errors() == 0 {
+            if let Err(e) = device::render(device, config, &mut String::new()) {
+                lint.push(Severity::Error, "render", &device.name, format!("{e:#}"));
+            }
+        }
+
+        Ok(Self {
+            device: device.name.clone(),
+            diagnostics: lint.diagnostics,
+        })
+    }
+
+    /// Number of diagnostics of `severity`
+    pub fn count(&self, severity: Severity) -> usize {
+        self.diagnostics
+            .iter()
+            .filter(|d| d.severity == severity)
+            .count()
+    }
+}
+
+/// Loads a [Device] like [`load_from`](crate::load_from), but without validating it
+///
+/// The validation stops at the first problem, [`Report::new`] reports them all.
+pub fn load_from(input: &str, config: &Config) -> Result<Device> {
+    crate::load_with(input, config, svd_parser::ValidateLevel::Disabled)
+}
+
+/// JSON of the report of one device, or of the list of reports of a family
+#[cfg(all(feature = "serde", feature = "json"))]
+pub fn to_json(reports: &[Report], config: &Config) -> Result<String> {
+    use anyhow::Context;
+
+    let json = if config.family.is_empty() && reports.len() == 1 {
+        serde_json::to_string_pretty(&reports[0])
+    } else {
+        serde_json::to_string_pretty(reports)
+    };
+    json.context("Error serializing lint report")
+}
+
+#[derive(Default)]
+struct Lint {
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Lint {
+    fn push(
+        &mut self,
+        severity: Severity,
+        code: &'static str,
+        path: impl ToString,
+        message: impl ToString,
+    ) {
+        self.diagnostics.push(Diagnostic {
+            severity,
+            code,
+            path: path.to_string(),
+            message: message.to_string(),
+        });
+    }
+
+    fn errors(&self) -> usize {
+        self.diagnostics
+            .iter()
+            .filter(|d| d.severity == Severity::Error)
+            .count()
+    }
+
+    fn missing_description(&mut self, kind: &str, path: impl ToString) {
+        self.push(
+            Severity::Warning,
+            "missing-description",
+            path,
+            format!("{kind} has no description"),
+        );
+    }
+
+    fn block(&mut self, children: &[RegisterCluster], path: &BlockPath, index: &Index) {
+        for child in children {
+            match child {
+                // derived elements are checked where they are derived from
+                RegisterCluster::Cluster(c) if c.derived_from.is_none() => {
+                    let cpath = path.new_cluster(&c.name);
+                    if c.description.is_none() {
+                        self.missing_description("cluster", &cpath);
+                    }
+                    self.block(&c.children, &cpath, index);
+                }
+                RegisterCluster::Register(r) if r.derived_from.is_none() => {
+                    self.register(r, &path.new_register(&r.name), index);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn register(&mut self, r: &svd::Register, rpath: &RegisterPath, index: &Index) {
+        if r.description.is_none() {
+            self.missing_description("register", rpath);
+        }
+        let Some(size) = r.properties.size else {
+            self.push(
+                Severity::Error,
+                "missing-size",
+                rpath,
+                "register has no size and neither has its peripheral or the device",
+            );
+            return;
+        };
+
+        let mut covered: Vec<(String, u64)> = Vec::new();
+        for f in r.fields() {
+            if f.name.to_lowercase() == "reserved" {
+                continue;
+            }
+            let mut f = f.clone();
+            let mut fpath = rpath.new_field(&f.name);
+            if let Some(dpath) = f.derived_from.take() {
+                match derive_field(&mut f, &dpath, rpath, index) {
+                    Ok(path) => fpath = path.unwrap_or(fpath),
+                    Err(e) => {
+                        self.push(Severity::Error, "unresolved-derivation", &fpath, e);
+                        continue;
+                    }
+                }
+            }
+            if f.description.is_none() {
+                self.missing_description("field", rpath.new_field(&f.name));
+            }
+
+            let width = f.bit_range.width;
+            let offsets: Vec<u32> = match &f {
+                svd::Field::Single(info) => vec![info.bit_range.offset],
+                svd::Field::Array(info, dim) => (0..dim.dim)
+                    .map(|i| info.bit_range.offset + i * dim.dim_increment)
+                    .collect(),
+            };
+            for (i, offset) in offsets.into_iter().enumerate() {
+                let name = match &f {
+                    svd::Field::Single(info) => info.name.clone(),
+                    svd::Field::Array(info, dim) => svd::array::names(info, dim)
+                        .nth(i)
+                        .unwrap_or_else(|| info.name.clone()),
+                };
+                let path = rpath.new_field(&name);
+                if offset + width > size.min(64) {
+                    self.push(
+                        Severity::Error,
+                        "field-out-of-range",
+                        &path,
+                        format!(
+                            "bits {}:{offset} are outside of the {size} bit register",
+                            offset + width - 1
+                        ),
+                    );
+                    continue;
+                }
+                let mask = (u64::MAX >> (64 - width)) << offset;
+                for (other, other_mask) in &covered {
+                    if mask & other_mask != 0 {
+                        self.push(
+                            Severity::Warning,
+                            "overlapping-fields",
+                            &path,
+                            format!("field shares bits with `{other}`"),
+                        );
+                    }
+                }
+                covered.push((name, mask));
+            }
+
+            for evs in &f.enumerated_values {
+                let mut evs = evs.clone();
+                if let Some(dpath) = evs.derived_from.take() {
+                    if let Err(e) = derive_enumerated_values(&mut evs, &dpath, &fpath, index) {
+                        self.push(
+                            Severity::Error,
+                            "unresolved-derivation",
+                            rpath.new_field(&f.name),
+                            e,
+                        );
+                        continue;
+                    }
+                }
+                for v in &evs.values {
+                    match v.value {
+                        Some(value) if width < 64 && value >> width != 0 => self.push(
+                            Severity::Error,
+                            "enum-value-too-wide",
+                            rpath.new_field(&f.name),
+                            format!(
+                                "value `{}` = {value:#x} doesn't fit in the {width} bit field",
+                                v.name
+                            ),
+                        ),
+                        _ => {}
+                    }
+                }
+            }
+        }
+
+        if let (false, Some(reset_value)) = (covered.is_empty(), r.properties.reset_value) {
+            let all = u64::MAX.checked_shr(64 - size.min(64)).unwrap_or(0);
+            let fields = covered.iter().fold(0, |mask, (_, m)| mask | m);
+            let outside = reset_value & r.properties.reset_mask.unwrap_or(all) & !fields;
+            if outside != 0 {
+                self.push(
+                    Severity::Warning,
+                    "reset-outside-fields",
+                    rpath,
+                    format!("reset value {reset_value:#x} sets bits {outside:#x} no field covers"),
+                );
+            }
+        }
+    }
+
+    /// Registers and clusters sharing addresses, which the register block can only give access
+    /// to through methods
+    fn overlaps(&mut self, members: &[LayoutMember], path: &str) {
+        let mut end = 0;
+        let mut last: Option<&LayoutMember> = None;
+        let mut clusters = HashSet::new();
+        for m in members {
+            if let Some(other) = last.filter(|_| m.offset < end) {
+                let (name, alternate) = match &m.kind {
+                    LayoutKind::Register { elements, .. } => (
+                        &elements[0].name,
+                        elements[0].alternate_register.is_some()
+                            || elements[0].alternate_group.is_some(),
+                    ),
+                    LayoutKind::Cluster { names, .. } => (&names[0], false),
+                };
+                let severity = if alternate {
+                    Severity::Info
+                } else {
+                    Severity::Warning
+                };
+                self.push(
+                    severity,
+                    "overlapping-registers",
+                    format!("{path}.{name}"),
+                    format!(
+                        "shares addresses with `{}`, the register block gets a union",
+                        member_name(other)
+                    ),
+                );
+            }
+            let member_end = m.offset + member_size(m);
+            if member_end > end {
+                end = member_end;
+                last = Some(m);
+            }
+            if let LayoutKind::Cluster {
+                ty, names, members, ..
+            } = &m.kind
+            {
+                // the elements of a cluster list share their members
+                if clusters.insert(ty) {
+                    self.overlaps(members, &format!("{path}.{}", names[0]));
+                }
+            }
+        }
+    }
+}
+
+fn member_name(m: &LayoutMember) -> &str {
+    match &m.kind {
+        LayoutKind::Register { elements, .. } => &elements[0].name,
+        LayoutKind::Cluster { names, .. } => &names[0],
+    }
+}
+
+/// Size in bytes of the member, arrays up to the end of their last increment
+fn member_size(m: &LayoutMember) -> u32 {
+    match (&m.kind, m.array) {
+        (_, Some((dim, increment))) => dim * increment,
+        (LayoutKind::Register { elements, .. }, None) => {
+            elements[0].properties.size.unwrap_or(32).div_ceil(8)
+        }
+        (LayoutKind::Cluster { size, .. }, None) => *size,
+    }
+}
+
+#[cfg(test)]
+const TEST_SVD: &str = r#"<device><name>CHIP</name><description>Lint test</description>
+<peripherals>
+<peripheral><name>GPIOA</name><description>Port A</description><baseAddress>0x40000000</baseAddress>
+<registers>
+<register><name>MODER</name><description>Mode</description><addressOffset>0x0</addressOffset>
+<size>32</size><resetValue>0xFF00</resetValue><fields>
+<field><name>MODE0</name><description>Pin 0</description><bitOffset>0</bitOffset><bitWidth>2</bitWidth>
+<enumeratedValues>
+<enumeratedValue><name>INPUT</name><description>Input</description><value>0</value></enumeratedValue>
+<enumeratedValue><name>ALT</name><description>Alternate</description><value>5</value></enumeratedValue>
+</enumeratedValues></field>
+<field><name>MODE1</name><description>Pin 1</description><bitOffset>1</bitOffset><bitWidth>2</bitWidth></field>
+</fields></register>
+<register><name>ODR</name><description>Output</description><addressOffset>0x2</addressOffset>
+<size>32</size><resetValue>0</resetValue></register>
+</registers></peripheral>
+<peripheral><name>GPIOB</name><baseAddress>0x40000400</baseAddress><registers>
+<register><name>IDR</name><description>Input</description><addressOffset>0x0</addressOffset>
+<resetValue>0</resetValue></register>
+<register><name>idr</name><description>Input</description><addressOffset>0x4</addressOffset>
+<size>32</size><resetValue>0</resetValue></register>
+</registers></peripheral>
+<peripheral><name>GPIOD</name><description>Port D</description><baseAddress>0x40000C00</baseAddress>
+<registers>
+<register><name>ODR</name><description>Output</description><addressOffset>0x0</addressOffset>
+<size>32</size><resetValue>0</resetValue></register>
+<register><name>BSR</name><description>Bit set</description><addressOffset>0x2</addressOffset>
+<size>16</size><resetValue>0</resetValue></register>
+</registers></peripheral>
+<peripheral derivedFrom="GPIOX"><name>GPIOC</name><baseAddress>0x40000800</baseAddress></peripheral>
+</peripherals></device>"#;
+
+#[test]
+fn diagnostics() {
+    let config = crate::prepare_config(&Config::default()).unwrap();
+    let device = load_from(TEST_SVD, &config).unwrap();
+    let report = Report::new(&device, &config).unwrap();
+    let diagnostics: Vec<_> = report
+        .diagnostics
+        .iter()
+        .map(|d| (d.severity, d.code, d.path.as_str()))
+        .collect();
+    assert_eq!(
+        diagnostics,
+        [
+            (Severity::Error, "name-collision", "GPIOB"),
+            (Severity::Error, "enum-value-too-wide", "GPIOA.MODER.MODE0"),
+            (Severity::Warning, "overlapping-fields", "GPIOA.MODER.MODE1"),
+            (Severity::Warning, "reset-outside-fields", "GPIOA.MODER"),
+            (Severity::Warning, "missing-description", "GPIOB"),
+            (Severity::Error, "missing-size", "GPIOB.IDR"),
+            (Severity::Warning, "overlapping-registers", "GPIOD.BSR"),
+            (Severity::Error, "unresolved-derivation", "GPIOC"),
+        ]
+    );
+    assert_eq!(report.count(Severity::Error), 4);
+    assert_eq!(
+        report.diagnostics[1].to_string(),
+        "error: GPIOA.MODER.MODE0: value `ALT` = 0x5 doesn't fit in the 2 bit field \
+         [enum-value-too-wide]"
+    );
+}
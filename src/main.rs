@@ -4,17 +4,17 @@ use log::{debug, error, info, warn};
 use svd2rust::config::{IdentFormatError, IdentFormats, IdentFormatsTheme};
 use svd2rust::util::IdentFormat;
 
-use std::io::Write;
 use std::process;
-use std::{fs::File, path::Path};
+use std::{fs::File, path::PathBuf};
 
 use anyhow::{Context, Result};
 use clap::{Arg, ArgAction, Command};
 
 use svd2rust::{
-    config::{Config, SourceType, Target},
-    generate, load_from,
-    util::{self, build_rs},
+    config::{Config, SourceType},
+    generate,
+    lint::{Report, Severity},
+    load_from,
 };
 
 fn parse_configs(app: Command) -> Result<Config> {
@@ -67,6 +67,7 @@ fn parse_configs(app: Command) -> Result<Config> {
             }
         }
     }
+    config.ident_formats.add_renames(&config.ident_renames)?;
 
     Ok(config)
 }
@@ -83,6 +84,28 @@ fn run() -> Result<()> {
                 .action(ArgAction::Set)
                 .value_name("FILE"),
         )
+        .arg(
+            Arg::new("family")
+                .long("family")
+                .help("Input SVD files of a chip family generated into one crate")
+                .action(ArgAction::Append)
+                .num_args(1..)
+                .value_name("FILE"),
+        )
+        .arg(
+            Arg::new("include")
+                .long("include")
+                .help("Generate only peripherals, clusters and registers matching the path pattern (e.g. `GPIO*`, `USB.EP*`)")
+                .action(ArgAction::Append)
+                .value_name("PATTERN"),
+        )
+        .arg(
+            Arg::new("exclude")
+                .long("exclude")
+                .help("Leave out peripherals, clusters and registers matching the path pattern")
+                .action(ArgAction::Append)
+                .value_name("PATTERN"),
+        )
         .arg(
             Arg::new("output_dir")
                 .long("output-dir")
@@ -107,6 +130,14 @@ fn run() -> Result<()> {
                 .action(ArgAction::Set)
                 .value_name("YAML_FILE"),
         )
+        .arg(
+            Arg::new("patch_file")
+                .long("patch")
+                .alias("patch_file")
+                .help("YAML file with changes applied to the device description before generation")
+                .action(ArgAction::Set)
+                .value_name("YAML_FILE"),
+        )
         .arg(
             Arg::new("edition")
                 .long("edition")
@@ -158,6 +189,34 @@ fn run() -> Result<()> {
                 .action(ArgAction::SetTrue)
                 .help("Push generic mod in separate file"),
         )
+        .arg(
+            Arg::new("register_map")
+                .long("register-map")
+                .alias("register_map")
+                .action(ArgAction::SetTrue)
+                .help("Write registers.json with the address, reset value and fields of every register"),
+        )
+        .arg(
+            Arg::new("gdb_xml")
+                .long("gdb-xml")
+                .alias("gdb_xml")
+                .action(ArgAction::SetTrue)
+                .help("Write a GDB target description and memory map of the peripheral registers"),
+        )
+        .arg(
+            Arg::new("c_header")
+                .long("c-header")
+                .alias("c_header")
+                .action(ArgAction::SetTrue)
+                .help("Write a C header with the register blocks and field masks of the generated code"),
+        )
+        .arg(
+            Arg::new("html_reference")
+                .long("html-reference")
+                .alias("html_reference")
+                .action(ArgAction::SetTrue)
+                .help("Write an HTML reference of the registers for the `html_url` links of the docs"),
+        )
         .arg(
             Arg::new("feature_group")
                 .long("feature-group")
@@ -193,6 +252,13 @@ Allowed cases are `unchanged` (''), `pascal` ('p'), `constant` ('c') and `snake`
                 .action(ArgAction::Set)
                 .value_name("THEME"),
         )
+        .arg(
+            Arg::new("disambiguate_idents")
+                .long("disambiguate-idents")
+                .alias("disambiguate_idents")
+                .action(ArgAction::SetTrue)
+                .help("Append numbers to identifiers colliding in their scope instead of failing"),
+        )
         .arg(
             Arg::new("field_names_for_enums")
                 .long("field-names-for-enums")
@@ -207,6 +273,36 @@ Allowed cases are `unchanged` (''), `pascal` ('p'), `constant` ('c') and `snake`
                 .action(ArgAction::SetTrue)
                 .help("Use array increment for cluster size"),
         )
+        .arg(
+            Arg::new("pointer_access")
+                .long("pointer-access")
+                .alias("pointer_access")
+                .action(ArgAction::SetTrue)
+                .help("Generate register blocks as raw pointer wrappers instead of structs in device memory"),
+        )
+        .arg(
+            Arg::new("instance_traits")
+                .long("instance-traits")
+                .alias("instance_traits")
+                .action(ArgAction::SetTrue)
+                .help("Generate an `Instance` trait implemented by all peripherals sharing a register block"),
+        )
+        .arg(
+            Arg::new("derive_identical")
+                .long("derive-identical")
+                .alias("derive_identical")
+                .action(ArgAction::SetTrue)
+                .help("Render structurally identical peripherals and clusters as derived from the first one"),
+        )
+        .arg(
+            Arg::new("derive_identical_enums")
+                .long("derive-identical-enums")
+                .alias("derive_identical_enums")
+                .action(ArgAction::Set)
+                .value_name("SCOPE")
+                .value_parser(["peripheral", "crate"])
+                .help("Render identical enumeratedValues inside of a peripheral or the whole crate as derived from the first one"),
+        )
         .arg(
             Arg::new("impl_debug")
                 .long("impl-debug")
@@ -230,6 +326,27 @@ Allowed cases are `unchanged` (''), `pascal` ('p'), `constant` ('c') and `snake`
                 .action(ArgAction::Set)
                 .value_name("FEATURE"),
         )
+        .arg(
+            Arg::new("bit_layout_docs")
+                .long("bit-layout-docs")
+                .alias("bit_layout_docs")
+                .action(ArgAction::SetTrue)
+                .help("Add tables of the bits, access, reset values and enumerated values of the fields to register docs"),
+        )
+        .arg(
+            Arg::new("mock")
+                .long("mock")
+                .help("Add feature gated in-memory register backend for testing on host")
+                .action(ArgAction::Set)
+                .value_name("FEATURE"),
+        )
+        .arg(
+            Arg::new("trace")
+                .long("trace")
+                .help("Add feature gated register access tracing")
+                .action(ArgAction::Set)
+                .value_name("FEATURE"),
+        )
         .arg(
             Arg::new("make_mod")
                 .long("make-mod")
@@ -238,6 +355,38 @@ Allowed cases are `unchanged` (''), `pascal` ('p'), `constant` ('c') and `snake`
                 .action(ArgAction::SetTrue)
                 .help("Create mod.rs instead of lib.rs, without inner attributes"),
         )
+        .arg(
+            Arg::new("make_crate")
+                .long("crate")
+                .alias("make-crate")
+                .alias("make_crate")
+                .action(ArgAction::SetTrue)
+                .help("Generate a complete crate with Cargo.toml, build script and src directory"),
+        )
+        .arg(
+            Arg::new("format")
+                .long("format")
+                .action(ArgAction::SetTrue)
+                .help("Pretty-print the generated code"),
+        )
+        .arg(
+            Arg::new("split")
+                .long("split")
+                .action(ArgAction::SetTrue)
+                .help("Write each peripheral module and large cluster and register modules into own file"),
+        )
+        .arg(
+            Arg::new("split_threshold")
+                .long("split-threshold")
+                .alias("split_threshold")
+                .action(ArgAction::Set)
+                .value_parser(clap::value_parser!(usize))
+                .value_name("BYTES")
+                .help(format!(
+                    "Size of nested modules which get own file with `--split`, {} by default",
+                    generate::split::DEFAULT_THRESHOLD
+                )),
+        )
         .arg(
             Arg::new("skip_crate_attributes")
                 .long("skip-crate-attributes")
@@ -314,6 +463,29 @@ Ignore this option if you are not building your own FPGA based soft-cores."),
             env!("CARGO_PKG_VERSION"),
             include_str!(concat!(env!("OUT_DIR"), "/commit-info.txt"))
         ));
+    // the arguments are global to also configure `lint`
+    let app = app.mut_args(|arg| arg.global(true));
+    let lint = Command::new("lint")
+        .about("Report all problems of the SVD file instead of generating code")
+        .arg(
+            Arg::new("json")
+                .long("json")
+                .action(ArgAction::SetTrue)
+                .help("Print the diagnostics as JSON"),
+        )
+        .arg(
+            Arg::new("deny_warnings")
+                .long("deny-warnings")
+                .alias("deny_warnings")
+                .action(ArgAction::SetTrue)
+                .help("Fail on warnings too"),
+        );
+    let app = app.subcommand(lint);
+    let lint = app
+        .clone()
+        .get_matches()
+        .subcommand_matches("lint")
+        .map(|m| (m.get_flag("json"), m.get_flag("deny_warnings")));
 
     let mut config = match parse_configs(app) {
         Ok(config) => {
@@ -328,20 +500,34 @@ Ignore this option if you are not building your own FPGA based soft-cores."),
 
     debug!("Current svd2rust config: {config:#?}");
 
-    let input = &mut String::new();
-    match config.input.as_ref() {
-        Some(file) => {
-            File::open(file)
-                .context("Cannot open the SVD file")?
-                .read_to_string(input)
-                .context("Cannot read the SVD file")?;
+    let mut inputs = Vec::new();
+    if config.family.is_empty() {
+        let mut input = String::new();
+        match config.input.as_ref() {
+            Some(file) => {
+                File::open(file)
+                    .context("Cannot open the SVD file")?
+                    .read_to_string(&mut input)
+                    .context("Cannot read the SVD file")?;
+                config.source_type = SourceType::from_path(file)
+            }
+            None => {
+                let stdin = std::io::stdin();
+                stdin
+                    .lock()
+                    .read_to_string(&mut input)
+                    .context("Cannot read from stdin")?;
+            }
         }
-        None => {
-            let stdin = std::io::stdin();
-            stdin
-                .lock()
-                .read_to_string(input)
-                .context("Cannot read from stdin")?;
+        inputs.push((input, config.source_type));
+    } else {
+        if config.input.is_some() {
+            return Err(anyhow::anyhow!("`--family` can't be combined with `-i`"));
+        }
+        for file in &config.family {
+            let input = std::fs::read_to_string(file)
+                .with_context(|| format!("Cannot read the SVD file {}", file.display()))?;
+            inputs.push((input, SourceType::from_path(file)));
         }
     }
 
@@ -361,82 +547,70 @@ Ignore this option if you are not building your own FPGA based soft-cores."),
         None => {}
     };
 
-    if let Some(file) = config.input.as_ref() {
-        config.source_type = SourceType::from_path(file)
-    }
-    let path = config.output_dir.as_deref().unwrap_or(Path::new("."));
+    let path = config
+        .output_dir
+        .clone()
+        .unwrap_or_else(|| PathBuf::from("."));
 
     info!("Parsing device from SVD file");
-    let device = load_from(input, &config)?;
+    let mut devices = Vec::with_capacity(inputs.len());
+    for (input, source_type) in &inputs {
+        config.source_type = *source_type;
+        let device = if lint.is_some() {
+            svd2rust::lint::load_from(input, &config)?
+        } else {
+            load_from(input, &config)?
+        };
+        devices.push(device);
+    }
 
-    let mut device_x = String::new();
-    info!("Rendering device");
-    let items = generate::device::render(&device, &config, &mut device_x)
-        .with_context(|| "Error rendering device")?;
+    if let Some((json, deny_warnings)) = lint {
+        return run_lint(&devices, &config, json, deny_warnings);
+    }
 
-    let filename = if config.make_mod { "mod.rs" } else { "lib.rs" };
-    let mut file = File::create(path.join(filename)).expect("Couldn't create output file");
+    info!("Rendering device");
+    svd2rust::render(&devices, &config)?.write(&path)?;
 
-    let data = items.to_string().replace(" # [", "\n#[");
-    file.write_all(data.as_ref())
-        .expect("Could not write code to lib.rs");
+    Ok(())
+}
 
-    if [
-        Target::CortexM,
-        Target::Msp430,
-        Target::XtensaLX,
-        Target::RISCV,
-    ]
-    .contains(&config.target)
-    {
-        writeln!(File::create(path.join("device.x"))?, "{device_x}")?;
-        writeln!(
-            File::create(path.join("build.rs"))?,
-            "{}",
-            build_rs(&config)
-        )?;
+fn run_lint(
+    devices: &[svd_parser::svd::Device],
+    config: &Config,
+    json: bool,
+    deny_warnings: bool,
+) -> Result<()> {
+    let mut reports = Vec::with_capacity(devices.len());
+    for device in devices {
+        info!("Linting device {}", device.name);
+        reports.push(Report::new(device, config)?);
     }
 
-    if config.feature_group || config.feature_peripheral {
-        let feature_format = config.ident_formats.get("peripheral_feature").unwrap();
-        let mut features = Vec::new();
-        if config.feature_group {
-            features.extend(
-                util::group_names(&device, feature_format)
-                    .iter()
-                    .map(|s| format!("{s} = []\n")),
-            );
-            let add_groups: Vec<_> = util::group_names(&device, feature_format)
-                .iter()
-                .map(|s| format!("\"{s}\""))
-                .collect();
-            features.push(format!("all-groups = [{}]\n", add_groups.join(",")))
-        }
-        if config.feature_peripheral {
-            features.extend(
-                util::peripheral_names(&device, feature_format)
-                    .iter()
-                    .map(|s| format!("{s} = []\n")),
-            );
-            let add_peripherals: Vec<_> = util::peripheral_names(&device, feature_format)
-                .iter()
-                .map(|s| format!("\"{s}\""))
-                .collect();
-            features.push(format!(
-                "all-peripherals = [{}]\n",
-                add_peripherals.join(",")
-            ))
+    if json {
+        #[cfg(feature = "json")]
+        println!("{}", svd2rust::lint::to_json(&reports, config)?);
+        #[cfg(not(feature = "json"))]
+        return Err(anyhow::anyhow!("Support for JSON output is not available because svd2rust was compiled without the json feature"));
+    } else {
+        for report in &reports {
+            for d in &report.diagnostics {
+                if reports.len() == 1 {
+                    println!("{d}");
+                } else {
+                    println!("{}: {d}", report.device);
+                }
+            }
         }
-        write!(
-            File::create(path.join("features.toml"))?,
-            "# Below are the FEATURES generated by svd2rust base on groupName in SVD file.\n\
-            # Please copy them to Cargo.toml.\n\
-            [features]\n\
-            {}",
-            features.join("")
-        )?;
     }
 
+    let errors: usize = reports.iter().map(|r| r.count(Severity::Error)).sum();
+    let warnings: usize = reports.iter().map(|r| r.count(Severity::Warning)).sum();
+    info!("{errors} errors, {warnings} warnings");
+    if errors > 0 || (deny_warnings && warnings > 0) {
+        return Err(anyhow::anyhow!(
+            "Linting found {errors} errors and {warnings} warnings"
+        ));
+    }
     Ok(())
 }
 
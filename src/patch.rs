@@ -0,0 +1,717 @@
+//! Patching of the device description before generation
+//!
+//! A patch is a YAML mapping in the style of [svdtools]. On every level `_delete`, `_modify`,
+//! `_add` and `_derive` change the elements of that level, all other keys are name patterns
+//! selecting elements whose contents are patched in turn.
+//!
+//! [svdtools]: https://github.com/rust-embedded/svdtools
+
+use anyhow::{anyhow, bail, Context, Result};
+use log::warn;
+use serde::{de::DeserializeOwned, Serialize};
+use serde_yaml::{Mapping, Value};
+
+use crate::svd::{
+    Device, EnumeratedValue, EnumeratedValues, FieldInfo, Interrupt, PeripheralInfo,
+    RegisterCluster, RegisterInfo, Usage, ValidateLevel,
+};
+use crate::util::glob;
+
+/// Keys of a device `_modify` changing the device itself instead of a peripheral
+const DEVICE_PROPERTIES: &[&str] = &[
+    "vendor",
+    "vendorID",
+    "name",
+    "series",
+    "version",
+    "description",
+    "licenseText",
+    "cpu",
+    "headerSystemFilename",
+    "headerDefinitionsPrefix",
+    "addressUnitBits",
+    "width",
+    "size",
+    "access",
+    "protection",
+    "resetValue",
+    "resetMask",
+];
+
+/// Alternative ways to specify the bit range of a field
+const BIT_RANGE_KEYS: &[&str] = &["bitRange", "bitOffset", "bitWidth", "lsb", "msb"];
+
+/// Changes to a [`Device`] read from a YAML patch file
+#[derive(Clone, Debug, Default)]
+pub struct Patch(Mapping);
+
+impl Patch {
+    /// Parse a patch from its YAML representation, e.g. the
+    /// contents of the file passed with `--patch`.
+    pub fn from_yaml(yaml: &str) -> Result<Self> {
+        match serde_yaml::from_str(yaml)? {
+            Value::Mapping(patch) => Ok(Self(patch)),
+            Value::Null => Ok(Self::default()),
+            _ => Err(anyhow!("Patch must be a mapping")),
+        }
+    }
+
+    /// Applies the patch to `device` as it is parsed
+    ///
+    /// Register properties like `size` are inherited from the device and peripherals only when
+    /// [`svd_parser::expand_properties`] is called afterwards, like [`load_from`](crate::load_from)
+    /// does.
+    pub fn apply(&self, device: &mut Device) -> Result<()> {
+        patch_device(device, &self.0)
+    }
+}
+
+fn patch_device(device: &mut Device, patch: &Mapping) -> Result<()> {
+    check_keys(patch, &["_delete", "_modify", "_add", "_derive"])?;
+
+    for pattern in strings(patch.get("_delete"))? {
+        delete(&mut device.peripherals, pattern, |p| &p.name, "peripheral")?;
+    }
+
+    let mut properties = Mapping::new();
+    for (key, value) in mapping(patch.get("_modify"))? {
+        if DEVICE_PROPERTIES.contains(&key) {
+            properties.insert(key.into(), value.clone());
+            continue;
+        }
+        for p in matching(&mut device.peripherals, key, |p| &p.name, "peripheral")? {
+            modify(p, value).with_context(|| format!("Could not modify peripheral {key}"))?;
+        }
+    }
+    if !properties.is_empty() {
+        let new_cpu = device.cpu.is_none() && properties.contains_key("cpu");
+        // peripherals don't take part in the round trip
+        let peripherals = std::mem::take(&mut device.peripherals);
+        let result = modify(device, &Value::Mapping(properties));
+        device.peripherals = peripherals;
+        if new_cpu {
+            result.context("Device has no cpu, `cpu` needs all of its properties")?;
+        } else {
+            result.context("Could not modify device")?;
+        }
+    }
+
+    for (name, value) in mapping(patch.get("_add"))? {
+        if device.get_peripheral(name).is_some() {
+            bail!("Peripheral {name} already exists");
+        }
+        device.peripherals.push(create(name, value)?);
+    }
+
+    for (name, value) in mapping(patch.get("_derive"))? {
+        let (from, mut properties) = derive_args(value)?;
+        if device.get_peripheral(from).is_none() {
+            bail!("Peripheral {from} to derive {name} from not found");
+        }
+        if let Some(p) = device.get_mut_peripheral(name) {
+            p.derived_from = Some(from.into());
+            p.registers = None;
+            modify(p, &Value::Mapping(properties))?;
+        } else {
+            properties.insert("derivedFrom".into(), from.into());
+            device
+                .peripherals
+                .push(create(name, &Value::Mapping(properties))?);
+        }
+    }
+
+    for (pattern, value) in entries(patch)? {
+        if pattern.starts_with('_') {
+            continue;
+        }
+        let patch = as_mapping(value)?;
+        for p in matching(&mut device.peripherals, pattern, |p| &p.name, "peripheral")? {
+            patch_peripheral(p, patch)
+                .with_context(|| format!("Could not patch peripheral {}", p.name))?;
+        }
+    }
+    Ok(())
+}
+
+fn patch_peripheral(peripheral: &mut PeripheralInfo, patch: &Mapping) -> Result<()> {
+    let had_registers = peripheral.registers.is_some();
+    let mut registers = peripheral.registers.take().unwrap_or_default();
+    patch_registers(&mut registers, Some(&mut peripheral.interrupt), patch)?;
+    if had_registers || !registers.is_empty() {
+        peripheral.registers = Some(registers);
+    }
+    Ok(())
+}
+
+/// Patches registers and clusters of a peripheral or cluster
+///
+/// `interrupts` are only present for peripherals.
+fn patch_registers(
+    children: &mut Vec<RegisterCluster>,
+    mut interrupts: Option<&mut Vec<Interrupt>>,
+    patch: &Mapping,
+) -> Result<()> {
+    check_keys(patch, &["_delete", "_modify", "_add", "_derive"])?;
+
+    let registers = match patch.get("_delete") {
+        Some(Value::Mapping(delete)) => {
+            check_keys(delete, &["_registers", "_interrupts"])?;
+            for pattern in strings(delete.get("_interrupts"))? {
+                let interrupts = peripheral_interrupts(&mut interrupts)?;
+                self::delete(interrupts, pattern, |i| &i.name, "interrupt")?;
+            }
+            strings(delete.get("_registers"))?
+        }
+        other => strings(other)?,
+    };
+    for pattern in registers {
+        delete(children, pattern, child_name, "register")?;
+    }
+
+    for (key, value) in mapping(patch.get("_modify"))? {
+        if key == "_interrupts" {
+            for (pattern, value) in mapping(Some(value))? {
+                let interrupts = peripheral_interrupts(&mut interrupts)?;
+                for i in matching(interrupts, pattern, |i| &i.name, "interrupt")? {
+                    modify(i, value)
+                        .with_context(|| format!("Could not modify interrupt {pattern}"))?;
+                }
+            }
+            continue;
+        }
+        for child in matching(children, key, child_name, "register")? {
+            match child {
+                RegisterCluster::Register(r) => modify(r, value),
+                RegisterCluster::Cluster(c) => modify(c, value),
+            }
+            .with_context(|| format!("Could not modify register {key}"))?;
+        }
+    }
+
+    for (name, value) in mapping(patch.get("_add"))? {
+        if name == "_interrupts" {
+            let interrupts = peripheral_interrupts(&mut interrupts)?;
+            for (name, value) in mapping(Some(value))? {
+                if interrupts.iter().any(|i| i.name == name) {
+                    bail!("Interrupt {name} already exists");
+                }
+                interrupts.push(create(name, value)?);
+            }
+            continue;
+        }
+        if children.iter().any(|c| child_name(c) == name) {
+            bail!("Register {name} already exists");
+        }
+        let child = if as_mapping(value)?.contains_key("children") {
+            RegisterCluster::Cluster(create(name, value)?)
+        } else {
+            RegisterCluster::Register(create(name, value)?)
+        };
+        children.push(child);
+    }
+
+    for (name, value) in mapping(patch.get("_derive"))? {
+        let (from, mut properties) = derive_args(value)?;
+        if !children.iter().any(|c| child_name(c) == from) {
+            bail!("Register {from} to derive {name} from not found");
+        }
+        match children.iter_mut().find(|c| child_name(c) == name) {
+            Some(RegisterCluster::Register(r)) => {
+                r.derived_from = Some(from.into());
+                r.fields = None;
+                modify(r, &Value::Mapping(properties))?;
+            }
+            Some(RegisterCluster::Cluster(c)) => {
+                c.derived_from = Some(from.into());
+                c.children.clear();
+                modify(c, &Value::Mapping(properties))?;
+            }
+            None => {
+                properties.insert("derivedFrom".into(), from.into());
+                children.push(RegisterCluster::Register(create(
+                    name,
+                    &Value::Mapping(properties),
+                )?));
+            }
+        }
+    }
+
+    for (pattern, value) in entries(patch)? {
+        if pattern.starts_with('_') {
+            continue;
+        }
+        let patch = as_mapping(value)?;
+        for child in matching(children, pattern, child_name, "register")? {
+            match child {
+                RegisterCluster::Register(r) => patch_fields(r, patch)
+                    .with_context(|| format!("Could not patch register {}", r.name))?,
+                RegisterCluster::Cluster(c) => patch_registers(&mut c.children, None, patch)
+                    .with_context(|| format!("Could not patch cluster {}", c.name))?,
+            }
+        }
+    }
+    Ok(())
+}
+
+fn patch_fields(register: &mut RegisterInfo, patch: &Mapping) -> Result<()> {
+    check_keys(patch, &["_delete", "_modify", "_add"])?;
+    let had_fields = register.fields.is_some();
+    let mut fields = register.fields.take().unwrap_or_default();
+
+    for pattern in strings(patch.get("_delete"))? {
+        delete(&mut fields, pattern, |f| &f.name, "field")?;
+    }
+
+    for (pattern, value) in mapping(patch.get("_modify"))? {
+        for f in matching(&mut fields, pattern, |f| &f.name, "field")? {
+            modify(f, value).with_context(|| format!("Could not modify field {pattern}"))?;
+        }
+    }
+
+    for (name, value) in mapping(patch.get("_add"))? {
+        if fields.iter().any(|f| f.name == name) {
+            bail!("Field {name} already exists");
+        }
+        fields.push(create(name, value)?);
+    }
+
+    for (pattern, value) in entries(patch)? {
+        if pattern.starts_with('_') {
+            continue;
+        }
+        for f in matching(&mut fields, pattern, |f| &f.name, "field")? {
+            set_enums(f, value)
+                .with_context(|| format!("Could not set enumerated values of {}", f.name))?;
+        }
+    }
+
+    if had_fields || !fields.is_empty() {
+        register.fields = Some(fields);
+    }
+    Ok(())
+}
+
+/// Replaces enumerated values of `field`
+///
+/// A string derives the values from the enumerated values of that name, a mapping lists the
+/// variants, for different variants on read and write under `_read` and `_write`.
+fn set_enums(field: &mut FieldInfo, value: &Value) -> Result<()> {
+    field.enumerated_values = match value {
+        Value::String(from) => vec![EnumeratedValues::builder()
+            .derived_from(Some(from.clone()))
+            .build(ValidateLevel::Weak)?],
+        Value::Mapping(enums) if enums.contains_key("_read") || enums.contains_key("_write") => {
+            check_keys(enums, &["_read", "_write"])?;
+            if let Some((key, _)) = entries(enums)?.iter().find(|(k, _)| !k.starts_with('_')) {
+                bail!("Variant {key} must be under `_read` or `_write`");
+            }
+            let mut values = Vec::new();
+            for (key, usage) in [("_read", Usage::Read), ("_write", Usage::Write)] {
+                if let Some(variants) = enums.get(key) {
+                    values.push(enumerated_values(as_mapping(variants)?, Some(usage))?);
+                }
+            }
+            values
+        }
+        Value::Mapping(variants) => vec![enumerated_values(variants, None)?],
+        _ => bail!("Enumerated values must be a mapping of variants or a name to derive from"),
+    };
+    Ok(())
+}
+
+/// Builds enumerated values from `VARIANT: [value, "description"]` entries
+///
+/// The value `default` marks the variant for all other values, `_name` names the values.
+fn enumerated_values(variants: &Mapping, usage: Option<Usage>) -> Result<EnumeratedValues> {
+    check_keys(variants, &["_name"])?;
+    let name = variants
+        .get("_name")
+        .map(|name| {
+            name.as_str()
+                .map(String::from)
+                .ok_or_else(|| anyhow!("`_name` must be a string"))
+        })
+        .transpose()?;
+    let mut values = Vec::new();
+    for (variant, value) in entries(variants)? {
+        if variant.starts_with('_') {
+            continue;
+        }
+        let (value, description) = match value {
+            Value::Sequence(s) if s.len() == 1 || s.len() == 2 => (&s[0], s.get(1)),
+            value => (value, None),
+        };
+        let description = description
+            .map(|d| {
+                d.as_str()
+                    .map(String::from)
+                    .ok_or_else(|| anyhow!("Description of {variant} must be a string"))
+            })
+            .transpose()?;
+        let builder = EnumeratedValue::builder()
+            .name(variant.into())
+            .description(description);
+        let builder = if value.as_str() == Some("default") {
+            builder.is_default(Some(true))
+        } else {
+            builder.value(Some(value.as_u64().ok_or_else(|| {
+                anyhow!("Value of {variant} must be a number or `default`")
+            })?))
+        };
+        values.push(builder.build(ValidateLevel::Weak)?);
+    }
+    Ok(EnumeratedValues::builder()
+        .name(name)
+        .usage(usage)
+        .values(values)
+        .build(ValidateLevel::Weak)?)
+}
+
+/// Changes properties of `element` by merging them into its serialized form
+///
+/// A `null` property removes the property.
+fn modify<T: Serialize + DeserializeOwned>(element: &mut T, properties: &Value) -> Result<()> {
+    let properties = as_mapping(properties)?;
+    let mut value = serde_yaml::to_value(&*element)?;
+    untag(&mut value);
+    if let Value::Mapping(map) = &mut value {
+        if properties.keys().any(is_bit_range_key) {
+            map.retain(|k, _| !is_bit_range_key(k));
+        }
+    }
+    merge(&mut value, properties);
+    *element = serde_yaml::from_value(value)?;
+    Ok(())
+}
+
+/// Turns the `!variant value` tags enums are serialized to into `variant: value` mappings
+///
+/// Flattened and untagged types, like the arrays of `MaybeArray`, can't be deserialized from
+/// tags, but from mappings like in YAML device files. An array without `dimIndex` also gets
+/// an explicit `null` one, as it is required when deserializing.
+fn untag(value: &mut Value) {
+    match value {
+        Value::Tagged(tagged) => {
+            let tag = tagged.tag.to_string();
+            let mut inner = std::mem::take(&mut tagged.value);
+            untag(&mut inner);
+            let mut map = Mapping::new();
+            map.insert(tag.trim_start_matches('!').into(), inner);
+            *value = Value::Mapping(map);
+        }
+        Value::Mapping(map) => {
+            if map.contains_key("dim") && !map.contains_key("dimIndex") {
+                map.insert("dimIndex".into(), Value::Null);
+            }
+            map.values_mut().for_each(untag)
+        }
+        Value::Sequence(list) => list.iter_mut().for_each(untag),
+        _ => {}
+    }
+}
+
+fn merge(value: &mut Value, properties: &Mapping) {
+    let Value::Mapping(map) = value else {
+        *value = Value::Mapping(properties.clone());
+        return;
+    };
+    for (key, property) in properties {
+        match (map.get_mut(key), property) {
+            (_, Value::Null) => {
+                map.remove(key);
+            }
+            (Some(value @ Value::Mapping(_)), Value::Mapping(properties)) => {
+                merge(value, properties)
+            }
+            _ => {
+                map.insert(key.clone(), property.clone());
+            }
+        }
+    }
+}
+
+fn is_bit_range_key(key: &Value) -> bool {
+    key.as_str().is_some_and(|k| BIT_RANGE_KEYS.contains(&k))
+}
+
+/// Creates an element named `name` from its serialized properties
+fn create<T: DeserializeOwned>(name: &str, properties: &Value) -> Result<T> {
+    let mut properties = as_mapping(properties)?.clone();
+    properties.insert("name".into(), name.into());
+    serde_yaml::from_value(Value::Mapping(properties))
+        .with_context(|| format!("Could not create {name}"))
+}
+
+/// Splits a `_derive` entry into the name to derive from and properties to change
+fn derive_args(value: &Value) -> Result<(&str, Mapping)> {
+    match value {
+        Value::String(from) => Ok((from, Mapping::new())),
+        Value::Mapping(properties) => {
+            let from = properties
+                .get("_from")
+                .and_then(Value::as_str)
+                .ok_or_else(|| anyhow!("`_derive` needs a `_from` name"))?;
+            let mut properties = properties.clone();
+            properties.remove("_from");
+            Ok((from, properties))
+        }
+        _ => Err(anyhow!("`_derive` takes a name or a mapping with `_from`")),
+    }
+}
+
+fn delete<T>(
+    items: &mut Vec<T>,
+    pattern: &str,
+    name: impl Fn(&T) -> &str,
+    kind: &str,
+) -> Result<()> {
+    let re = glob(pattern)?;
+    let len = items.len();
+    items.retain(|item| !re.is_match(name(item)));
+    if items.len() == len {
+        warn!("No {kind} matches `{pattern}`");
+    }
+    Ok(())
+}
+
+fn matching<'a, T>(
+    items: &'a mut [T],
+    pattern: &str,
+    name: impl Fn(&T) -> &str,
+    kind: &str,
+) -> Result<Vec<&'a mut T>> {
+    let re = glob(pattern)?;
+    let found: Vec<_> = items
+        .iter_mut()
+        .filter(|item| re.is_match(name(item)))
+        .collect();
+    if found.is_empty() {
+        warn!("No {kind} matches `{pattern}`");
+    }
+    Ok(found)
+}
+
+fn child_name(child: &RegisterCluster) -> &str {
+    match child {
+        RegisterCluster::Register(r) => &r.name,
+        RegisterCluster::Cluster(c) => &c.name,
+    }
+}
+
+fn peripheral_interrupts<'a>(
+    interrupts: &'a mut Option<&mut Vec<Interrupt>>,
+) -> Result<&'a mut Vec<Interrupt>> {
+    interrupts
+        .as_deref_mut()
+        .ok_or_else(|| anyhow!("Only peripherals have interrupts"))
+}
+
+fn check_keys(patch: &Mapping, known: &[&str]) -> Result<()> {
+    for (key, _) in entries(patch)? {
+        if key.starts_with('_') && !known.contains(&key) {
+            bail!("Unknown patch key `{key}`");
+        }
+    }
+    Ok(())
+}
+
+fn entries(map: &Mapping) -> Result<Vec<(&str, &Value)>> {
+    map.iter()
+        .map(|(key, value)| {
+            let key = key
+                .as_str()
+                .ok_or_else(|| anyhow!("Patch keys must be strings, found {key:?}"))?;
+            Ok((key, value))
+        })
+        .collect()
+}
+
+fn mapping(value: Option<&Value>) -> Result<Vec<(&str, &Value)>> {
+    match value {
+        None | Some(Value::Null) => Ok(Vec::new()),
+        Some(value) => entries(as_mapping(value)?),
+    }
+}
+
+fn as_mapping(value: &Value) -> Result<&Mapping> {
+    value
+        .as_mapping()
+        .ok_or_else(|| anyhow!("Expected a mapping, found {value:?}"))
+}
+
+/// Patterns given as one string or a list
+fn strings(value: Option<&Value>) -> Result<Vec<&str>> {
+    match value {
+        None | Some(Value::Null) => Ok(Vec::new()),
+        Some(Value::String(s)) => Ok(vec![s]),
+        Some(Value::Sequence(list)) => list
+            .iter()
+            .map(|v| {
+                v.as_str()
+                    .ok_or_else(|| anyhow!("Expected a name pattern, found {v:?}"))
+            })
+            .collect(),
+        Some(value) => Err(anyhow!("Expected a list of name patterns, found {value:?}")),
+    }
+}
+
+#[cfg(test)]
+const TEST_SVD: &str = r#"<device><name>CHIP</name>
+<cpu><name>CM3</name><revision>r1p0</revision><endian>little</endian><mpuPresent>false</mpuPresent>
+<fpuPresent>false</fpuPresent><nvicPrioBits>4</nvicPrioBits><vendorSystickConfig>false</vendorSystickConfig></cpu>
+<size>32</size><resetValue>0</resetValue><resetMask>0xFFFFFFFF</resetMask><peripherals>
+<peripheral><name>USART1</name><baseAddress>0x40013800</baseAddress>
+<interrupt><name>USART1</name><value>37</value></interrupt>
+<registers>
+<register><name>SR</name><addressOffset>0x0</addressOffset><fields>
+<field><name>TXE</name><bitOffset>7</bitOffset><bitWidth>1</bitWidth></field>
+<field><name>TC</name><bitOffset>6</bitOffset><bitWidth>1</bitWidth></field>
+</fields></register>
+<register><name>DR</name><addressOffset>0x4</addressOffset><fields>
+<field><name>DR</name><bitOffset>0</bitOffset><bitWidth>9</bitWidth>
+<writeConstraint><range><minimum>0</minimum><maximum>511</maximum></range></writeConstraint></field>
+</fields></register>
+<cluster><dim>2</dim><dimIncrement>0x10</dimIncrement><name>CH%s</name><addressOffset>0x10</addressOffset>
+<register><name>CR</name><addressOffset>0x0</addressOffset></register>
+</cluster>
+</registers></peripheral>
+<peripheral><name>USART2</name><baseAddress>0x40004400</baseAddress><registers>
+<register><name>SR</name><addressOffset>0x0</addressOffset></register>
+</registers></peripheral>
+<peripheral><name>TIM4</name><baseAddress>0x40000800</baseAddress><registers>
+<register><name>CR1</name><addressOffset>0x0</addressOffset></register>
+</registers></peripheral>
+</peripherals></device>"#;
+
+#[cfg(test)]
+fn patched(patch: &str) -> Result<Device> {
+    let mut device = svd_parser::parse(TEST_SVD)?;
+    Patch::from_yaml(patch)?.apply(&mut device)?;
+    Ok(device)
+}
+
+#[test]
+fn modify_peripheral() {
+    let device = patched(
+        "
+_modify: { USART1: { description: Serial port, groupName: USART } }
+USART1:
+  _modify: { DR: { description: Data }, CH%s: { dimIncrement: 0x20 } }
+",
+    )
+    .unwrap();
+    let usart1 = device.get_peripheral("USART1").unwrap();
+    assert_eq!(usart1.description.as_deref(), Some("Serial port"));
+    assert_eq!(usart1.group_name.as_deref(), Some("USART"));
+    // the registers are kept
+    assert_eq!(usart1.registers().count(), 2);
+    let dr = usart1.get_register("DR").unwrap();
+    assert_eq!(dr.description.as_deref(), Some("Data"));
+    assert!(dr.get_field("DR").unwrap().write_constraint.is_some());
+    match usart1.clusters().next().unwrap() {
+        crate::svd::Cluster::Array(c, dim) => {
+            assert_eq!(dim.dim_increment, 0x20);
+            assert_eq!(c.children.len(), 1);
+        }
+        _ => panic!("cluster array expected"),
+    }
+}
+
+#[test]
+fn modify_cpu() {
+    let device = patched("_modify: { cpu: { fpuPresent: true }, version: '2.0' }").unwrap();
+    let cpu = device.cpu.as_ref().unwrap();
+    assert!(cpu.fpu_present);
+    assert_eq!(cpu.name, "CM3");
+    assert_eq!(device.version, "2.0");
+    assert_eq!(device.peripherals.len(), 3);
+}
+
+#[test]
+fn modified_properties_are_inherited() {
+    let mut device =
+        patched("_modify: { size: 16 }\nUSART2: { _modify: { SR: { size: 8 } } }").unwrap();
+    svd_parser::expand_properties(&mut device);
+    let sr = device.get_peripheral("USART1").unwrap().get_register("SR");
+    assert_eq!(sr.unwrap().properties.size, Some(16));
+    let sr = device.get_peripheral("USART2").unwrap().get_register("SR");
+    assert_eq!(sr.unwrap().properties.size, Some(8));
+}
+
+#[test]
+fn delete_add_derive() {
+    let device = patched(
+        "
+_delete: [TIM*]
+_derive:
+  USART3: { _from: USART2, baseAddress: 0x40004800 }
+USART1:
+  _delete: { _registers: CH%s, _interrupts: USART1 }
+  _add:
+    BRR: { addressOffset: 0x8, description: Baud rate }
+    _interrupts: { USART1_WKUP: { value: 42 } }
+  _derive: { DR2: { _from: DR, addressOffset: 0xC } }
+",
+    )
+    .unwrap();
+    assert!(device.get_peripheral("TIM4").is_none());
+    let usart3 = device.get_peripheral("USART3").unwrap();
+    assert_eq!(usart3.derived_from.as_deref(), Some("USART2"));
+    assert_eq!(usart3.base_address, 0x4000_4800);
+    let usart1 = device.get_peripheral("USART1").unwrap();
+    assert_eq!(usart1.clusters().count(), 0);
+    assert_eq!(usart1.interrupt.len(), 1);
+    assert_eq!(usart1.interrupt[0].name, "USART1_WKUP");
+    assert_eq!(usart1.interrupt[0].value, 42);
+    let brr = usart1.get_register("BRR").unwrap();
+    assert_eq!(brr.address_offset, 8);
+    let dr2 = usart1.get_register("DR2").unwrap();
+    assert_eq!(dr2.derived_from.as_deref(), Some("DR"));
+    assert_eq!(dr2.address_offset, 0xC);
+}
+
+#[test]
+fn fields() {
+    let device = patched(
+        "
+USART1:
+  SR:
+    _modify: { TXE: { access: read-only }, T*: { description: Flag } }
+    _add: { RXNE: { bitOffset: 5, bitWidth: 1 } }
+    TC:
+      Pending: [0, Transmission pending]
+      Complete: [1]
+  DR:
+    _modify: { DR: { bitRange: '[7:0]' } }
+    DR:
+      _read: { Empty: [0] }
+      _write: { _name: DATA, Nul: [0], Other: default }
+",
+    )
+    .unwrap();
+    let usart1 = device.get_peripheral("USART1").unwrap();
+    let sr = usart1.get_register("SR").unwrap();
+    let txe = sr.get_field("TXE").unwrap();
+    assert_eq!(txe.access, Some(crate::svd::Access::ReadOnly));
+    assert_eq!(txe.description.as_deref(), Some("Flag"));
+    assert_eq!(sr.get_field("RXNE").unwrap().bit_offset(), 5);
+    let tc = &sr.get_field("TC").unwrap().enumerated_values[0];
+    assert_eq!(tc.values[0].name, "Pending");
+    assert_eq!(tc.values[1].value, Some(1));
+    let dr = usart1.get_register("DR").unwrap().get_field("DR").unwrap();
+    assert_eq!(dr.bit_width(), 8);
+    assert_eq!(dr.enumerated_values.len(), 2);
+    assert_eq!(dr.enumerated_values[1].name.as_deref(), Some("DATA"));
+    assert_eq!(dr.enumerated_values[1].values[1].is_default, Some(true));
+}
+
+#[test]
+fn errors() {
+    assert!(patched("_remove: [USART1]").is_err());
+    let mut device =
+        svd_parser::parse(&TEST_SVD.replace("<cpu>", "<!--").replace("</cpu>", "-->")).unwrap();
+    let patch = Patch::from_yaml("_modify: { cpu: { fpuPresent: true } }").unwrap();
+    assert!(patch.apply(&mut device).is_err());
+    assert!(patched("_add: { USART1: { baseAddress: 0 } }").is_err());
+    assert!(patched("USART1: { SR: { TXE: { Bits: [foo] } } }").is_err());
+}
@@ -8,6 +8,7 @@ use crate::{
 use inflections::Inflect;
 use proc_macro2::{Ident, Span, TokenStream};
 use quote::quote;
+use regex::Regex;
 use std::collections::HashSet;
 use svd_rs::{MaybeArray, Peripheral, PeripheralInfo};
 
@@ -99,9 +100,9 @@ pub fn ident(name: &str, config: &Config, fmt: &str, span: Span) -> Ident {
 
 impl IdentFormat {
     pub fn apply<'a>(&self, name: &'a str) -> Cow<'a, str> {
-        let name = match &self.case {
-            Some(case) => case.sanitize(name),
-            _ => sanitize(name),
+        let name = match self.rename(name) {
+            Cow::Borrowed(name) => self.apply_case(name),
+            Cow::Owned(name) => self.apply_case(&name).into_owned().into(),
         };
         if self.prefix.is_empty() && self.suffix.is_empty() {
             name
@@ -109,6 +110,26 @@ impl IdentFormat {
             format!("{}{}{}", self.prefix, name, self.suffix).into()
         }
     }
+    /// Applies the `rename` rewrites in order
+    pub fn rename<'a>(&self, name: &'a str) -> Cow<'a, str> {
+        let mut name = Cow::Borrowed(name);
+        for rename in &self.rename {
+            if rename.from.is_match(&name) {
+                name = rename
+                    .from
+                    .replace_all(&name, rename.to.as_str())
+                    .into_owned()
+                    .into();
+            }
+        }
+        name
+    }
+    fn apply_case<'a>(&self, name: &'a str) -> Cow<'a, str> {
+        match &self.case {
+            Some(case) => case.sanitize(name),
+            _ => sanitize(name),
+        }
+    }
     pub fn sanitize<'a>(&self, name: &'a str) -> Cow<'a, str> {
         let s = self.apply(name);
         let s = if s.as_bytes().first().unwrap_or(&0).is_ascii_digit() {
@@ -124,11 +145,7 @@ impl IdentFormat {
 }
 
 pub fn ident_str(name: &str, fmt: &IdentFormat) -> String {
-    let name = match &fmt.case {
-        Some(case) => case.sanitize(name),
-        _ => sanitize(name),
-    };
-    format!("{}{}{}", fmt.prefix, name, fmt.suffix)
+    fmt.apply(name).into_owned()
 }
 
 pub fn sanitize_keyword(sc: Cow<str>) -> Cow<str> {
@@ -177,6 +194,14 @@ pub fn escape_brackets(s: &str) -> String {
         })
 }
 
+/// Escape the characters with a meaning in HTML and XML text
+pub fn escape_html(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
 /// Escape basic html tags and brackets
 pub fn escape_special_chars(s: &str) -> Cow<'_, str> {
     if s.contains('[') {
@@ -404,6 +429,22 @@ impl U32Ext for u32 {
     }
 }
 
+/// Converts generated code into source text, pretty-printed if `format` is set in `config`
+pub fn source(tokens: TokenStream, config: &Config) -> Result<String> {
+    if config.format {
+        #[cfg(feature = "format")]
+        {
+            let file = syn::parse2(tokens)?;
+            return Ok(prettyplease::unparse(&file));
+        }
+        #[cfg(not(feature = "format"))]
+        return Err(anyhow!(
+            "Formatting is not available because svd2rust was compiled without the format feature"
+        ));
+    }
+    Ok(tokens.to_string().replace(" # [", "\n#["))
+}
+
 pub fn build_rs(config: &Config) -> TokenStream {
     let extra_build = config.extra_build();
 
@@ -504,6 +545,42 @@ pub fn peripheral_names(d: &Device, feature_format: &IdentFormat) -> Vec<String>
     v
 }
 
+/// Converts a name pattern to a regular expression
+///
+/// Patterns support `*`, `?`, `[...]` and `[!...]`, several patterns are separated by commas.
+pub fn glob(pattern: &str) -> Result<Regex> {
+    let mut re = String::from("^(?:");
+    for (i, alternative) in pattern.split(',').enumerate() {
+        if i > 0 {
+            re.push('|');
+        }
+        let mut chars = alternative.trim().chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '*' => re.push_str(".*"),
+                '?' => re.push('.'),
+                '[' => {
+                    re.push('[');
+                    let mut class = chars.by_ref().take_while(|&c| c != ']').peekable();
+                    if class.next_if_eq(&'!').is_some() {
+                        re.push('^');
+                    }
+                    for c in class {
+                        if matches!(c, '\\' | '[' | '&' | '~') {
+                            re.push('\\');
+                        }
+                        re.push(c);
+                    }
+                    re.push(']');
+                }
+                c => re.push_str(&regex::escape(c.encode_utf8(&mut [0; 4]))),
+            }
+        }
+    }
+    re.push_str(")$");
+    Regex::new(&re).map_err(|e| anyhow!("Invalid name pattern `{pattern}`: {e}"))
+}
+
 #[test]
 fn pascalcase() {
     assert_eq!(to_pascal_case("_reserved"), "_Reserved");
@@ -513,3 +590,17 @@ fn pascalcase() {
     assert_eq!(to_pascal_case("FOO_BAR_1_2"), "FooBar1_2");
     assert_eq!(to_pascal_case("FOO_BAR_1_2_"), "FooBar1_2_");
 }
+
+#[test]
+fn glob_patterns() {
+    let re = glob("USART*").unwrap();
+    assert!(re.is_match("USART") && re.is_match("USART12"));
+    assert!(!re.is_match("UART1"));
+    let re = glob("TIM?").unwrap();
+    assert!(re.is_match("TIM2") && !re.is_match("TIM12"));
+    let re = glob("GPIO[AB], GPIO[!A-C]").unwrap();
+    assert!(re.is_match("GPIOA") && re.is_match("GPIOD"));
+    assert!(!re.is_match("GPIOC"));
+    assert!(glob("CR.1").unwrap().is_match("CR.1"));
+    assert!(!glob("CR.1").unwrap().is_match("CRX1"));
+}
@@ -0,0 +1,127 @@
+//! Procedural macro generating a peripheral access crate inline from an SVD file
+//!
+//! ```ignore
+//! #![no_std]
+//! #![allow(non_camel_case_types, non_snake_case)]
+//!
+//! svd2rust_macro::device!("chip.svd", target = "none", impl_debug = true);
+//! ```
+//!
+//! The first argument is the path of the SVD file relative to the directory of `Cargo.toml`,
+//! the other ones set fields of the `svd2rust` [`Config`] with the values they would have in
+//! `svd2rust.toml`. `settings_file` and `patch_file` are relative to `Cargo.toml` as well. The
+//! crate is rebuilt when any of these files change.
+//!
+//! The generated code refers to its items with `crate::` paths, so the macro has to be called
+//! at the root of the crate, which also declares the crate attributes. `device.x` isn't
+//! generated, use `svd2rust::Builder` from a build script if the `rt` feature needs it.
+
+use std::path::PathBuf;
+
+use proc_macro2::{Span, TokenStream};
+use quote::quote;
+use serde_json::Value;
+use svd2rust::{Builder, Config};
+use syn::parse::{Parse, ParseStream};
+use syn::{parse_macro_input, Expr, ExprLit, Ident, Lit, LitStr, Token};
+
+/// Expands to the peripheral access crate generated from an SVD file
+///
+/// `device!("path/to/chip.svd", name = value, ...)`, see the [crate] documentation.
+#[proc_macro]
+pub fn device(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let args = parse_macro_input!(input as Args);
+    expand(args)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+struct Args {
+    path: LitStr,
+    config: Vec<(Ident, Value)>,
+}
+
+impl Parse for Args {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let path = input.parse()?;
+        let mut config = Vec::new();
+        while !input.is_empty() {
+            input.parse::<Token![,]>()?;
+            if input.is_empty() {
+                break;
+            }
+            let name = input.parse()?;
+            input.parse::<Token![=]>()?;
+            config.push((name, value(&input.parse()?)?));
+        }
+        Ok(Self { path, config })
+    }
+}
+
+/// Converts a literal or an array of literals to the value deserialized into the config
+fn value(expr: &Expr) -> syn::Result<Value> {
+    match expr {
+        Expr::Lit(ExprLit { lit, .. }) => match lit {
+            Lit::Str(s) => Ok(s.value().into()),
+            Lit::Bool(b) => Ok(b.value.into()),
+            Lit::Int(i) => Ok(i.base10_parse::<u64>()?.into()),
+            _ => Err(syn::Error::new_spanned(
+                lit,
+                "expected a string, boolean or integer",
+            )),
+        },
+        Expr::Array(array) => array
+            .elems
+            .iter()
+            .map(value)
+            .collect::<syn::Result<_>>()
+            .map(Value::Array),
+        _ => Err(syn::Error::new_spanned(
+            expr,
+            "expected a literal or an array of literals",
+        )),
+    }
+}
+
+fn expand(args: Args) -> syn::Result<TokenStream> {
+    let error = |msg: String| syn::Error::new(Span::call_site(), msg);
+
+    let dir = PathBuf::from(
+        std::env::var_os("CARGO_MANIFEST_DIR")
+            .ok_or_else(|| error("CARGO_MANIFEST_DIR is not set".into()))?,
+    );
+    let mut config = serde_json::Map::new();
+    for (name, value) in args.config {
+        config.insert(name.to_string(), value);
+    }
+    let mut config: Config = serde_json::from_value(config.into())
+        .map_err(|e| error(format!("Invalid svd2rust config: {e}")))?;
+    if config.split || config.generic_mod || config.make_crate {
+        return Err(error(
+            "`split`, `generic_mod` and `make_crate` can't be used inline".into(),
+        ));
+    }
+    config.settings_file = config.settings_file.map(|path| dir.join(path));
+    config.patch_file = config.patch_file.map(|path| dir.join(path));
+
+    let path = dir.join(args.path.value());
+    let generation = Builder::new()
+        .config(config.clone())
+        .file(&path)
+        .generate()
+        .map_err(|e| syn::Error::new(args.path.span(), format!("{e:#}")))?;
+    let mut file = syn::parse_file(&generation.lib_rs)?;
+    // crate attributes are up to the calling crate
+    file.attrs.clear();
+
+    let tracked = [Some(path), config.settings_file, config.patch_file]
+        .into_iter()
+        .flatten()
+        .map(|path| path.display().to_string());
+    Ok(quote! {
+        const _: () = {
+            #(let _ = include_bytes!(#tracked);)*
+        };
+        #file
+    })
+}
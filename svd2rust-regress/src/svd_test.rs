@@ -1,5 +1,5 @@
 use anyhow::{anyhow, Context, Result};
-use svd2rust::{util::Case, Target};
+use svd2rust::{generate::manifest, util::Case, Config, Target};
 
 use crate::{command::CommandExt, tests::TestCase, Opts};
 use std::io::prelude::*;
@@ -11,21 +11,7 @@ use std::{
     path::Path,
 };
 
-const CRATES_ALL: &[&str] = &[
-    "critical-section = {version = \"1.0\", optional = true}",
-    "vcell = \"0.1.2\"",
-];
-const CRATES_MSP430: &[&str] = &["msp430 = \"0.4.0\"", "msp430-rt = \"0.4.0\""];
-const CRATES_ATOMICS: &[&str] =
-    &["portable-atomic = { version = \"1\", default-features = false }"];
-const CRATES_CORTEX_M_LEGACY_INT_NUM: &[&str] = &["cortex-m = { version = \"0.7\" }"];
-const CRATES_CORTEX_M: &[&str] = &["cortex-m-types = \"0.1\"", "cortex-m-rt = \"0.7\""];
-const CRATES_RISCV: &[&str] = &["riscv = \"0.12.1\"", "riscv-rt = \"0.13.0\""];
-const CRATES_MIPS: &[&str] = &["mips-mcu = \"0.1.0\""];
 const PROFILE_ALL: &[&str] = &["[profile.dev]", "incremental = false"];
-const FEATURES_ALL: &[&str] = &["[features]"];
-const FEATURES_CORTEX_M: &[&str] = &["rt = [\"cortex-m-rt/device\"]"];
-const FEATURES_XTENSA_LX: &[&str] = &["rt = []"];
 const WORKSPACE_EXCLUDE: &[&str] = &["[workspace]"];
 
 fn path_helper_base(base: &Path, input: &[&str]) -> PathBuf {
@@ -420,35 +406,19 @@ impl TestCase {
             .open(svd_toml)
             .with_context(|| "Failed to open Cargo.toml for appending")?;
 
-        let cargo_toml_fragments = CRATES_ALL
-            .iter()
-            .chain(match &self.arch {
-                Target::CortexM => CRATES_CORTEX_M.iter(),
-                Target::RISCV => CRATES_RISCV.iter(),
-                Target::Mips => CRATES_MIPS.iter(),
-                Target::Msp430 => CRATES_MSP430.iter(),
-                Target::XtensaLX => [].iter(),
-                Target::Avr => [].iter(),
-                Target::None => unreachable!(),
-            })
-            .chain(opts.as_ref().map_or(Vec::new().into_iter(), |opts| {
-                let mut fragments = Vec::new();
-                if opts.iter().any(|v| v.contains("atomics")) {
-                    fragments.extend(CRATES_ATOMICS);
-                }
-                if opts.iter().any(|v| v.contains("add-cortex-m-int-num")) {
-                    fragments.extend(CRATES_CORTEX_M_LEGACY_INT_NUM);
-                }
-                fragments.into_iter()
-            }))
-            .chain(PROFILE_ALL.iter())
-            .chain(FEATURES_ALL.iter())
-            .chain(match &self.arch {
-                Target::CortexM => FEATURES_CORTEX_M.iter(),
-                Target::XtensaLX => FEATURES_XTENSA_LX.iter(),
-                _ => [].iter(),
-            })
-            .chain(WORKSPACE_EXCLUDE.iter());
+        let mut config = Config::default();
+        config.target = self.arch;
+        if let Some(opts) = opts.as_ref() {
+            config.atomics = opts.iter().any(|v| v.contains("atomics"));
+            config.add_cortex_m_int_num = opts.iter().any(|v| v.contains("add-cortex-m-int-num"));
+        }
+        let features = manifest::features(&config);
+        let cargo_toml_fragments = manifest::dependencies(&config)
+            .into_iter()
+            .chain(PROFILE_ALL.iter().copied())
+            .chain(["[features]"])
+            .chain(features.iter().map(String::as_str))
+            .chain(WORKSPACE_EXCLUDE.iter().copied());
         for fragments in cargo_toml_fragments {
             writeln!(file, "{}", fragments).with_context(|| "Failed to append to file!")?;
         }